@@ -0,0 +1,111 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+// Lado del bloque de muestras por píxel en SSAA (2 => 4 muestras por píxel)
+pub const SSAA_FACTOR: usize = 2;
+
+// Un borde se suaviza solo si su contraste de luminancia supera ambos umbrales
+const FXAA_EDGE_THRESHOLD: f32 = 0.125;
+const FXAA_EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const FXAA_SUBPIXEL_QUALITY: f32 = 0.75;
+const FXAA_MIN_BLEND: f32 = 0.25;
+
+// Calidad del anti-aliasing, de menor a mayor coste
+#[derive(Clone, Copy, PartialEq)]
+pub enum AntiAlias {
+    Off,
+    Fxaa,  // Post-proceso que detecta bordes por luminancia y los mezcla con su vecino
+    Ssaa,  // Renderiza a SSAA_FACTOR veces la resolución y promedia
+}
+
+impl AntiAlias {
+    pub fn next(self) -> Self {
+        match self {
+            AntiAlias::Off => AntiAlias::Fxaa,
+            AntiAlias::Fxaa => AntiAlias::Ssaa,
+            AntiAlias::Ssaa => AntiAlias::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AntiAlias::Off => "sin AA",
+            AntiAlias::Fxaa => "FXAA",
+            AntiAlias::Ssaa => "SSAA 2x",
+        }
+    }
+}
+
+// Promedia cada bloque de SSAA_FACTOR x SSAA_FACTOR píxeles de `source` en un píxel de `target`
+pub fn resolve_ssaa(source: &Framebuffer, target: &mut Framebuffer) {
+    let samples = (SSAA_FACTOR * SSAA_FACTOR) as u32;
+
+    for y in 0..target.height {
+        for x in 0..target.width {
+            let mut sum = [0u32; 3];
+            for sy in 0..SSAA_FACTOR {
+                for sx in 0..SSAA_FACTOR {
+                    let pixel = source.buffer[(y * SSAA_FACTOR + sy) * source.width + x * SSAA_FACTOR + sx];
+                    sum[0] += (pixel >> 16) & 0xFF;
+                    sum[1] += (pixel >> 8) & 0xFF;
+                    sum[2] += pixel & 0xFF;
+                }
+            }
+            target.buffer[y * target.width + x] = ((sum[0] / samples) << 16) | ((sum[1] / samples) << 8) | (sum[2] / samples);
+        }
+    }
+}
+
+fn luma(pixel: u32) -> f32 {
+    let r = ((pixel >> 16) & 0xFF) as f32;
+    let g = ((pixel >> 8) & 0xFF) as f32;
+    let b = (pixel & 0xFF) as f32;
+    (0.299 * r + 0.587 * g + 0.114 * b) / 255.0
+}
+
+// FXAA simplificado: en cada píxel con suficiente contraste se decide si el borde es horizontal
+// o vertical y se mezcla con el vecino del otro lado del borde. Opera sobre la imagen final.
+pub fn apply_fxaa(framebuffer: &mut Framebuffer) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let source = framebuffer.buffer.clone();
+    let lumas: Vec<f32> = source.iter().map(|&pixel| luma(pixel)).collect();
+
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let index = y * width + x;
+            let center = lumas[index];
+            let north = lumas[index - width];
+            let south = lumas[index + width];
+            let west = lumas[index - 1];
+            let east = lumas[index + 1];
+
+            let max = center.max(north).max(south).max(west).max(east);
+            let min = center.min(north).min(south).min(west).min(east);
+            let range = max - min;
+            if range < FXAA_EDGE_THRESHOLD_MIN.max(max * FXAA_EDGE_THRESHOLD) {
+                continue;
+            }
+
+            // Borde horizontal si la luminancia cambia más en vertical que en horizontal
+            let horizontal = (north + south - 2.0 * center).abs() >= (west + east - 2.0 * center).abs();
+            let neighbor = if horizontal {
+                if (north - center).abs() >= (south - center).abs() { index - width } else { index + width }
+            } else if (west - center).abs() >= (east - center).abs() {
+                index - 1
+            } else {
+                index + 1
+            };
+
+            // Todo borde se mezcla un mínimo; cuanto más se aparta el píxel de la media de sus
+            // vecinos (escalones, detalles de un píxel), más se mezcla, hasta la mitad
+            let average = (north + south + west + east) * 0.25;
+            let subpixel = ((average - center).abs() / range).clamp(0.0, 1.0);
+            let smooth = subpixel * subpixel * (3.0 - 2.0 * subpixel);
+            let blend = (smooth * smooth * FXAA_SUBPIXEL_QUALITY).clamp(FXAA_MIN_BLEND, 0.5);
+
+            let color = Color::from_hex(source[index]).lerp(&Color::from_hex(source[neighbor]), blend);
+            framebuffer.buffer[index] = color.to_hex();
+        }
+    }
+}
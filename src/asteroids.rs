@@ -0,0 +1,133 @@
+use nalgebra_glm::{Vec2, Vec3, Mat4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::math::create_model_matrix;
+use crate::orbits::kepler_orbit_speed;
+use crate::vertex::Vertex;
+
+// Cinturón por defecto, entre el planeta helado y el planeta Tierra
+pub const BELT_INNER_RADIUS: f32 = 18.5;
+pub const BELT_OUTER_RADIUS: f32 = 21.0;
+pub const BELT_SEED: u64 = 42;
+pub const DEFAULT_ASTEROID_COUNT: usize = 200;
+
+// Un asteroide del cinturón: órbita circular con una pequeña altura sobre el plano
+#[derive(Serialize, Deserialize)]
+pub struct Asteroid {
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub orbit_phase: f32,
+    pub height: f32,
+    pub scale: f32,
+    pub spin: Vec3,  // Velocidad de rotación en cada eje
+}
+
+// Genera un cinturón reproducible: con la misma semilla se obtienen los mismos asteroides
+pub fn generate_asteroid_belt(count: usize, inner_radius: f32, outer_radius: f32, seed: u64) -> Vec<Asteroid> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    (0..count).map(|_| {
+        let orbit_radius = rng.gen_range(inner_radius..outer_radius);
+        Asteroid {
+            orbit_radius,
+            // Los asteroides interiores giran un poco más rápido
+            orbit_speed: kepler_orbit_speed(orbit_radius),
+            orbit_phase: rng.gen_range(0.0..(2.0 * std::f32::consts::PI)),
+            height: rng.gen_range(-0.4..0.4),
+            scale: rng.gen_range(0.05..0.18),
+            spin: Vec3::new(rng.gen_range(-0.05..0.05), rng.gen_range(-0.05..0.05), rng.gen_range(-0.05..0.05)),
+        }
+    }).collect()
+}
+
+// Matrices de modelo de todos los asteroides para el instante `time`
+pub fn asteroid_instances(asteroids: &[Asteroid], time: f32) -> Vec<Mat4> {
+    asteroids.iter().map(|asteroid| {
+        let angle = asteroid.orbit_speed * time + asteroid.orbit_phase;
+        let translation = Vec3::new(
+            asteroid.orbit_radius * angle.cos(),
+            asteroid.height,
+            asteroid.orbit_radius * angle.sin(),
+        );
+        create_model_matrix(translation, asteroid.scale, asteroid.spin * time)
+    }).collect()
+}
+
+// Icosaedro con los vértices desplazados al azar: una roca de solo 20 triángulos
+pub fn asteroid_mesh(seed: u64) -> Vec<Vertex> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+
+    let corners: Vec<Vec3> = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ].iter()
+        .map(|&(x, y, z)| Vec3::new(x, y, z).normalize() * rng.gen_range(0.75..1.1))
+        .collect();
+
+    let faces = [
+        (0, 11, 5), (0, 5, 1), (0, 1, 7), (0, 7, 10), (0, 10, 11),
+        (1, 5, 9), (5, 11, 4), (11, 10, 2), (10, 7, 6), (7, 1, 8),
+        (3, 9, 4), (3, 4, 2), (3, 2, 6), (3, 6, 8), (3, 8, 9),
+        (4, 9, 5), (2, 4, 11), (6, 2, 10), (8, 6, 7), (9, 8, 1),
+    ];
+
+    let mut vertices = Vec::with_capacity(faces.len() * 3);
+    for &(a, b, c) in &faces {
+        // Normal por cara para que las facetas de la roca se distingan
+        let normal = (corners[b] - corners[a]).cross(&(corners[c] - corners[a])).normalize();
+        for &index in &[a, b, c] {
+            vertices.push(Vertex::new(corners[index], normal, Vec2::new(0.0, 0.0)));
+        }
+    }
+
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Primeros asteroides del cinturón por defecto: cambian si cambia el orden de las tiradas
+    // del generador, y con ellos el cielo de todas las escenas guardadas
+    const SNAPSHOT: [(f32, f32, f32, f32); 3] = [
+        // (radio, fase, altura, escala)
+        (18.833525, 3.3084574, -0.20100938, 0.12055428),
+        (19.514753, 6.088525, -0.37252578, 0.13026513),
+        (18.964428, 5.3360047, 0.10347298, 0.06706625),
+    ];
+
+    #[test]
+    fn belt_is_reproducible_with_seed() {
+        let belt = generate_asteroid_belt(DEFAULT_ASTEROID_COUNT, BELT_INNER_RADIUS, BELT_OUTER_RADIUS, BELT_SEED);
+        for (asteroid, &(radius, phase, height, scale)) in belt.iter().zip(SNAPSHOT.iter()) {
+            assert!((asteroid.orbit_radius - radius).abs() < 1e-4);
+            assert!((asteroid.orbit_phase - phase).abs() < 1e-4);
+            assert!((asteroid.height - height).abs() < 1e-4);
+            assert!((asteroid.scale - scale).abs() < 1e-4);
+        }
+
+        let again = generate_asteroid_belt(DEFAULT_ASTEROID_COUNT, BELT_INNER_RADIUS, BELT_OUTER_RADIUS, BELT_SEED);
+        assert!(belt.iter().zip(&again).all(|(a, b)| a.orbit_radius == b.orbit_radius && a.orbit_phase == b.orbit_phase));
+    }
+
+    #[test]
+    fn belt_radii_stay_inside_the_ring() {
+        for seed in [BELT_SEED, 0, 7, 12345] {
+            let belt = generate_asteroid_belt(500, BELT_INNER_RADIUS, BELT_OUTER_RADIUS, seed);
+            assert_eq!(belt.len(), 500);
+            for asteroid in &belt {
+                assert!((BELT_INNER_RADIUS..=BELT_OUTER_RADIUS).contains(&asteroid.orbit_radius));
+            }
+
+            // Las posiciones de las instancias también quedan dentro del anillo
+            for matrix in asteroid_instances(&belt, 1234.0) {
+                let radius = matrix[(0, 3)].hypot(matrix[(2, 3)]);
+                assert!((BELT_INNER_RADIUS - 1e-3..=BELT_OUTER_RADIUS + 1e-3).contains(&radius));
+            }
+        }
+    }
+}
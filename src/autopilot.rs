@@ -0,0 +1,43 @@
+use std::f32::consts::PI;
+
+use crate::camera::Camera;
+
+// Segundos que se queda en cada destino antes de pasar al siguiente
+const DWELL_TIME: f32 = 5.0;
+
+// Giro lento alrededor del destino mientras espera, en radianes por segundo
+const DWELL_ORBIT_SPEED: f32 = PI / 15.0;
+
+// Recorrido automático por los destinos de warp para una demostración sin manos: viaja a cada
+// uno con la misma animación que las teclas 1-4, orbita despacio unos segundos y pasa al
+// siguiente, volviendo al primero después del último
+pub struct Autopilot {
+    destination: usize,  // Destino hacia el que viaja o en el que espera
+    dwell: f32,          // Segundos de espera que quedan en él
+}
+
+impl Autopilot {
+    // Empieza yendo hacia `destination`; quien lo crea lanza ese primer warp
+    pub fn new(destination: usize) -> Self {
+        Autopilot { destination, dwell: DWELL_TIME }
+    }
+
+    // Avanza un frame de `delta_time` segundos. Mientras dura el viaje (`traveling`) no hace
+    // nada; al llegar orbita la cámara alrededor del destino y, cuando se acaba la espera,
+    // devuelve el índice del siguiente destino para lanzar su warp.
+    pub fn step(&mut self, camera: &mut Camera, destination_count: usize, traveling: bool, delta_time: f32) -> Option<usize> {
+        if traveling || destination_count == 0 {
+            return None;
+        }
+
+        camera.orbit(DWELL_ORBIT_SPEED * delta_time, 0.0);
+        self.dwell -= delta_time;
+        if self.dwell > 0.0 {
+            return None;
+        }
+
+        self.destination = (self.destination + 1) % destination_count;
+        self.dwell = DWELL_TIME;
+        Some(self.destination)
+    }
+}
@@ -0,0 +1,356 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::codecs::hdr::HdrDecoder;
+use image::{DynamicImage, ImageBuffer, ImageResult, Rgb32FImage};
+
+use crate::framebuffer::Framebuffer;
+use crate::math::project_to_image;
+
+// Forma de muestrear la imagen panorámica del fondo
+#[derive(Clone, Copy, PartialEq)]
+pub enum BackgroundSampling {
+    Nearest,    // Píxel más cercano: rápido pero se ve en bloques al ampliar
+    Bilinear,   // Interpola los cuatro píxeles vecinos de la imagen original
+    Trilinear,  // Bilineal en los dos mipmaps adecuados a la resolución y mezcla entre ellos
+}
+
+impl BackgroundSampling {
+    pub fn next(self) -> Self {
+        match self {
+            BackgroundSampling::Nearest => BackgroundSampling::Bilinear,
+            BackgroundSampling::Bilinear => BackgroundSampling::Trilinear,
+            BackgroundSampling::Trilinear => BackgroundSampling::Nearest,
+        }
+    }
+}
+
+// Fondo de la escena: una panorámica cargada de disco o un campo de estrellas generado
+pub enum Background {
+    Image(PanoramicTexture),
+    Procedural(Starfield),
+}
+
+// Semilla del campo de estrellas si no se indica otra con `--star-seed`
+pub const DEFAULT_STAR_SEED: u64 = 2024;
+
+// Celdas del cielo en longitud y latitud; cada una contiene como mucho una estrella. No dependen
+// del tamaño de la ventana para que la misma semilla dé siempre el mismo cielo.
+const STAR_GRID_WIDTH: u64 = 2048;
+const STAR_GRID_HEIGHT: u64 = 1024;
+
+// Fracción de celdas con estrella
+const STAR_DENSITY: f32 = 0.004;
+
+// Las estrellas más débiles tienen este brillo (de 0 a 1); el resto se reparte hasta 1 con más
+// estrellas débiles que brillantes
+const MIN_STAR_BRIGHTNESS: f32 = 0.25;
+
+// Cuánto baja el brillo en el mínimo del centelleo y a qué velocidad (radianes por frame)
+const TWINKLE_DEPTH: f32 = 0.5;
+const TWINKLE_SPEED: f32 = 0.08;
+
+// Campo de estrellas determinista: cada dirección del cielo se asigna a una celda y la
+// existencia, el brillo y el tono de su estrella salen de un hash de la celda y `seed`
+#[derive(Clone, Copy)]
+pub struct Starfield {
+    pub seed: u64,
+    pub twinkle: bool,  // Varía el brillo de cada estrella con el tiempo
+}
+
+impl Default for Starfield {
+    fn default() -> Self {
+        Starfield { seed: DEFAULT_STAR_SEED, twinkle: false }
+    }
+}
+
+// Mezcla de bits de SplitMix64: valores consecutivos dan resultados sin relación aparente
+fn hash(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+// Número de 0 a 1 a partir de 24 bits de `bits`
+fn unit(bits: u64) -> f32 {
+    (bits & 0xFF_FFFF) as f32 / 0xFF_FFFF as f32
+}
+
+impl Starfield {
+    // Color de la estrella de la celda (`cell_x`, `cell_y`) en el frame `time`, o None si no hay
+    fn star(&self, cell_x: u64, cell_y: u64, time: u32) -> Option<u32> {
+        let cell = hash(self.seed ^ hash(cell_y * STAR_GRID_WIDTH + cell_x));
+        if unit(cell) >= STAR_DENSITY {
+            return None;
+        }
+
+        let brightness = MIN_STAR_BRIGHTNESS + (1.0 - MIN_STAR_BRIGHTNESS) * unit(cell >> 24).powi(3);
+        let brightness = if self.twinkle {
+            let phase = unit(cell >> 48) * 2.0 * std::f32::consts::PI;
+            brightness * (1.0 - TWINKLE_DEPTH * 0.5 * (1.0 + (time as f32 * TWINKLE_SPEED + phase).sin()))
+        } else {
+            brightness
+        };
+
+        // Tono entre azulado y anaranjado; la mayoría casi blancas
+        let warmth = unit(hash(cell)) - 0.5;
+        let channel = |tint: f32| ((brightness * (1.0 + tint)).clamp(0.0, 1.0) * 255.0) as u32;
+        Some((channel(warmth * 0.3) << 16) | (channel(0.0) << 8) | channel(-warmth * 0.3))
+    }
+}
+
+// Pinta el campo de estrellas en todo el framebuffer con la misma proyección que la panorámica:
+// cada píxel corresponde a una dirección y se colorea con la estrella de su celda, si la hay
+pub fn draw_starfield(framebuffer: &mut Framebuffer, starfield: &Starfield, time: u32) {
+    for y in 0..framebuffer.height {
+        let cell_y = (y as u64 * STAR_GRID_HEIGHT / framebuffer.height as u64).min(STAR_GRID_HEIGHT - 1);
+        for x in 0..framebuffer.width {
+            let cell_x = (x as u64 * STAR_GRID_WIDTH / framebuffer.width as u64).min(STAR_GRID_WIDTH - 1);
+
+            framebuffer.set_current_color(starfield.star(cell_x, cell_y, time).unwrap_or(0x000000));
+            framebuffer.point(x, y, 1.0);
+        }
+    }
+}
+
+// Abre una panorámica. `image::open` convierte los .hdr a 8 bits, así que esos se decodifican
+// aparte para conservar los colores en coma flotante; los .exr ya se abren en coma flotante.
+pub fn open_panorama(path: &str) -> ImageResult<DynamicImage> {
+    let is_radiance = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+    if !is_radiance {
+        // Las imágenes de 16 bits por canal se reservan para los fondos linealizados (`linearize`)
+        return image::open(path).map(|image| match image {
+            DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) | DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) => {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            }
+            image => image,
+        });
+    }
+
+    let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr()?;
+    let raw = pixels.iter().flat_map(|pixel| pixel.0).collect();
+    let image = Rgb32FImage::from_raw(metadata.width, metadata.height, raw)
+        .expect("el tamaño del .hdr no coincide con sus píxeles");
+    Ok(DynamicImage::ImageRgb32F(image))
+}
+
+// Exposición aplicada a los fondos HDR antes del mapeo de tonos
+const HDR_EXPOSURE: f32 = 1.0;
+
+// Un fondo .hdr/.exr se decodifica con colores en coma flotante (lineales y sin límite superior)
+fn is_hdr(image: &DynamicImage) -> bool {
+    matches!(image, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_))
+}
+
+// Reinhard por canal y corrección gamma: lleva un color lineal HDR al rango 0-255 de la pantalla
+fn tone_map(value: f32) -> u32 {
+    let exposed = value.max(0.0) * HDR_EXPOSURE;
+    let mapped = exposed / (1.0 + exposed);
+    (mapped.powf(1.0 / 2.2) * 255.0 + 0.5) as u32
+}
+
+// Pasa una panorámica LDR a espacio lineal decodificando la curva `gamma` (c^gamma). Se guarda
+// con 16 bits por canal para no perder los tonos oscuros, y `draw_background` la vuelve a
+// codificar con la misma gamma que la geometría. Los fondos HDR ya son lineales.
+pub fn linearize(image: DynamicImage, gamma: f32) -> DynamicImage {
+    if is_hdr(&image) {
+        return image;
+    }
+
+    let image = image.to_rgb8();
+    let raw = image.as_raw().iter()
+        .map(|&channel| ((channel as f32 / 255.0).powf(gamma) * 65535.0 + 0.5) as u16)
+        .collect();
+    let linear = ImageBuffer::from_raw(image.width(), image.height(), raw)
+        .expect("el tamaño de la imagen no coincide con sus píxeles");
+    DynamicImage::ImageRgb16(linear)
+}
+
+// Cómo se pasan a 0-255 los valores guardados en una `PanoramicTexture`
+#[derive(Clone, Copy)]
+enum PanoramaEncoding {
+    Display,  // 8 bits ya codificados para la pantalla (PNG, JPEG...)
+    Linear,   // 16 bits lineales (`linearize`); se codifican con la misma gamma que la geometría
+    Hdr,      // Coma flotante lineal sin límite superior; se mapea con `tone_map`
+}
+
+// Un nivel de la cadena de mipmaps: RGB entrelazado en coma flotante, en la escala del formato
+// original (0-255, 0-65535 o HDR)
+struct MipLevel {
+    width: u32,
+    height: u32,
+    pixels: Vec<f32>,
+}
+
+impl MipLevel {
+    // Nivel siguiente: la mitad de ancho y de alto (redondeando hacia arriba), promediando
+    // bloques de 2x2. En los tamaños impares la última fila o columna se repite.
+    fn downsample(&self) -> MipLevel {
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let (x0, y0) = (2 * x, 2 * y);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let offset = |px: u32, py: u32| ((py * self.width + px) * 3) as usize;
+                for channel in 0..3 {
+                    let sum = self.pixels[offset(x0, y0) + channel] + self.pixels[offset(x1, y0) + channel]
+                        + self.pixels[offset(x0, y1) + channel] + self.pixels[offset(x1, y1) + channel];
+                    pixels.push(sum * 0.25);
+                }
+            }
+        }
+        MipLevel { width, height, pixels }
+    }
+}
+
+// Panorámica equirectangular con su cadena de mipmaps, calculada una sola vez al cargarla. Al
+// reducirla mucho (ventana pequeña, vista secundaria, panorámicas enormes) se muestrea un nivel
+// con aproximadamente un texel por píxel en lugar de saltarse texels de la imagen original.
+pub struct PanoramicTexture {
+    levels: Vec<MipLevel>,  // El 0 es la imagen original; el último mide 1x1
+    encoding: PanoramaEncoding,
+}
+
+impl PanoramicTexture {
+    pub fn new(image: DynamicImage) -> Self {
+        let (encoding, width, height, pixels) = match image {
+            DynamicImage::ImageRgb16(image) => {
+                let pixels = image.as_raw().iter().map(|&channel| channel as f32).collect();
+                (PanoramaEncoding::Linear, image.width(), image.height(), pixels)
+            }
+            image if is_hdr(&image) => {
+                let image = image.to_rgb32f();
+                (PanoramaEncoding::Hdr, image.width(), image.height(), image.into_raw())
+            }
+            image => {
+                let image = image.to_rgb8();
+                let pixels = image.as_raw().iter().map(|&channel| channel as f32).collect();
+                (PanoramaEncoding::Display, image.width(), image.height(), pixels)
+            }
+        };
+
+        let mut levels = vec![MipLevel { width, height, pixels }];
+        while let Some(level) = levels.last().filter(|level| level.width > 1 || level.height > 1) {
+            levels.push(level.downsample());
+        }
+        PanoramicTexture { levels, encoding }
+    }
+
+    // Color en las coordenadas normalizadas (`u`, `v`), de 0 a 1, con filtrado trilineal: mezcla el
+    // muestreo bilineal de los dos niveles que rodean `lod` (0 = imagen original, cada unidad
+    // divide la resolución entre dos)
+    pub fn sample(&self, u: f32, v: f32, lod: f32) -> [f32; 3] {
+        let lod = lod.clamp(0.0, (self.levels.len() - 1) as f32);
+        let level = lod as usize;
+        let blend = lod - level as f32;
+
+        let fine = sample_bilinear(&self.levels[level], u, v);
+        if blend <= 0.0 {
+            return fine;
+        }
+        let coarse = sample_bilinear(&self.levels[level + 1], u, v);
+        [0, 1, 2].map(|channel| fine[channel] * (1.0 - blend) + coarse[channel] * blend)
+    }
+
+    // Nivel de detalle para dibujar la panorámica en un framebuffer de `width` x `height`. Cada
+    // píxel abarca 360°/width de longitud y 180°/height de latitud; multiplicado por los texels
+    // por grado de la imagen da cuántos texels caen en un píxel, y el nivel es su log2.
+    fn lod_for(&self, width: usize, height: usize) -> f32 {
+        let base = &self.levels[0];
+        let texels_x = (360.0 / width as f32) * (base.width as f32 / 360.0);
+        let texels_y = (180.0 / height as f32) * (base.height as f32 / 180.0);
+        texels_x.max(texels_y).log2().max(0.0)
+    }
+}
+
+// Pinta la imagen panorámica en todos los píxeles que todavía no tienen geometría.
+// Las imágenes HDR se muestrean en coma flotante y se mapean a 8 bits al final; las
+// linealizadas con `linearize` se interpolan en espacio lineal y se codifican con `gamma`; las
+// LDR (PNG, JPEG...) se muestrean directamente en 8 bits.
+pub fn draw_background(framebuffer: &mut Framebuffer, texture: &PanoramicTexture, sampling: BackgroundSampling, gamma: f32) {
+    match texture.encoding {
+        PanoramaEncoding::Linear => {
+            let encode = |value: f32| ((value / 65535.0).max(0.0).powf(1.0 / gamma) * 255.0 + 0.5) as u32;
+            fill_background(framebuffer, texture, sampling, encode);
+        }
+        PanoramaEncoding::Hdr => fill_background(framebuffer, texture, sampling, tone_map),
+        PanoramaEncoding::Display => fill_background(framebuffer, texture, sampling, |value| (value + 0.5) as u32),
+    }
+}
+
+// Recorre la ventana muestreando `texture`; `to_channel` convierte cada canal muestreado a un
+// valor de 0 a 255
+fn fill_background(
+    framebuffer: &mut Framebuffer,
+    texture: &PanoramicTexture,
+    sampling: BackgroundSampling,
+    to_channel: impl Fn(f32) -> u32,
+) {
+    let base = &texture.levels[0];
+    // La proyección del fondo no depende de la cámara: el nivel es el mismo en toda la pantalla
+    let lod = texture.lod_for(framebuffer.width, framebuffer.height);
+
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            // Convertimos las coordenadas de la ventana a ángulos esféricos
+            let x_angle = (x as f32 / framebuffer.width as f32) * 360.0 - 180.0; // Mapeo de 0 a 360 -> -180 a 180
+            let y_angle = (y as f32 / framebuffer.height as f32) * 180.0 - 90.0; // Mapeo de 0 a 180 -> -90 a 90
+            let (u, v) = ((x_angle + 180.0) / 360.0, (y_angle + 90.0) / 180.0);
+
+            let [r, g, b] = match sampling {
+                BackgroundSampling::Nearest => {
+                    let (x_pixel, y_pixel) = project_to_image(x_angle, y_angle, base.width, base.height);
+                    let offset = ((y_pixel * base.width + x_pixel) * 3) as usize;
+                    [base.pixels[offset], base.pixels[offset + 1], base.pixels[offset + 2]]
+                }
+                BackgroundSampling::Bilinear => sample_bilinear(base, u, v),
+                BackgroundSampling::Trilinear => texture.sample(u, v, lod),
+            };
+            // Mismo formato que el resto del framebuffer: 0x00RRGGBB
+            let color = (to_channel(r) << 16) | (to_channel(g) << 8) | to_channel(b);
+
+            framebuffer.set_current_color(color);
+            framebuffer.point(x, y, 1.0);
+        }
+    }
+}
+
+// Muestreo bilineal de un nivel en coordenadas normalizadas (0 a 1). La imagen es
+// equirectangular, así que en horizontal el borde derecho continúa en el izquierdo; en vertical
+// se fija a los polos.
+fn sample_bilinear(level: &MipLevel, u: f32, v: f32) -> [f32; 3] {
+    let (width, height) = (level.width, level.height);
+    // Centro del texel: el píxel (0, 0) cubre de 0 a 1, su centro está en 0.5
+    let x = u * width as f32 - 0.5;
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+
+    // x puede valer hasta -0.5 en el borde izquierdo; truncar solo equivale a floor si es positivo
+    let x0 = if x < 0.0 { x as i32 - 1 } else { x as i32 };
+    let y0 = y as u32;
+    let tx = x - x0 as f32;
+    let ty = y - y0 as f32;
+
+    let x0 = if x0 < 0 { (x0 + width as i32) as u32 } else { x0 as u32 };
+    let x1 = if x0 + 1 == width { 0 } else { x0 + 1 };
+    let y1 = (y0 + 1).min(height - 1);
+
+    let offset = |px: u32, py: u32| ((py * width + px) * 3) as usize;
+    let (p00, p10) = (offset(x0, y0), offset(x1, y0));
+    let (p01, p11) = (offset(x0, y1), offset(x1, y1));
+    let texel = |index: usize| level.pixels[index];
+
+    let mut result = [0.0; 3];
+    for (channel, value) in result.iter_mut().enumerate() {
+        let top = texel(p00 + channel) * (1.0 - tx) + texel(p10 + channel) * tx;
+        let bottom = texel(p01 + channel) * (1.0 - tx) + texel(p11 + channel) * tx;
+        *value = top * (1.0 - ty) + bottom * ty;
+    }
+    result
+}
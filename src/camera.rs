@@ -1,12 +1,51 @@
 
 use nalgebra_glm::{Vec3, rotate_vec3};
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 
+// Cámara en órbita alrededor de `center` o de vuelo libre, que se desplaza y gira sobre `eye`
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CameraMode {
+  #[default]
+  Orbit,
+  FreeFly,
+}
+
+// Arriba del mundo: el `up` de la cámara sin alabeo
+const WORLD_UP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+
+// Alabeo por debajo del cual se considera que la cámara está nivelada
+const LEVEL_ROLL: f32 = 1e-4;
+
+// Alineación de la vista con el arriba del mundo a partir de la cual se considera vertical y el
+// alabeo deja de estar definido. La órbita se queda algo por debajo (su inclinación máxima da 0.995).
+const VERTICAL_ALIGNMENT: f32 = 0.999;
+
+// Límite de la inclinación en vuelo libre: la vista nunca queda paralela a `up`
+const MAX_LOOK_ALIGNMENT: f32 = 0.99;
+
+// Campo de visión vertical en grados: el de partida y los límites del zoom de lente
+pub const DEFAULT_FOV: f32 = 45.0;
+const MIN_FOV: f32 = 20.0;
+const MAX_FOV: f32 = 90.0;
+
+fn default_fov() -> f32 {
+  DEFAULT_FOV
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Camera {
   pub eye: Vec3,
   pub center: Vec3,
   pub up: Vec3,
-  pub has_changed: bool
+  #[serde(default)]
+  pub mode: CameraMode,
+  #[serde(default = "default_fov")]
+  pub fov: f32,  // Campo de visión vertical en grados (ver `math::create_perspective_matrix`)
+  #[serde(skip)]
+  pub has_changed: bool,
+  #[serde(skip)]
+  orbit_distance: Option<f32>,  // Distancia al centro al pasar a vuelo libre, para recuperarla
 }
 
 impl Camera {
@@ -15,13 +54,50 @@ impl Camera {
       eye,
       center,
       up,
+      mode: CameraMode::Orbit,
+      fov: DEFAULT_FOV,
       has_changed: true,
+      orbit_distance: None,
     }
   }
 
+  // Cámara que mira a `target` desde coordenadas esféricas alrededor de él. El azimut se mide
+  // en el plano XZ desde +X hacia +Z y la elevación es positiva por encima de ese plano.
+  pub fn from_spherical(target: Vec3, azimuth: f32, elevation: f32, radius: f32) -> Self {
+    let elevation = elevation.clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+    let offset = Vec3::new(
+      radius * azimuth.cos() * elevation.cos(),
+      radius * elevation.sin(),
+      radius * azimuth.sin() * elevation.cos()
+    );
+
+    Camera::new(target + offset, target, Vec3::new(0.0, 1.0, 0.0))
+  }
+
+  // Dirección unitaria en la que mira la cámara
+  pub fn forward(&self) -> Vec3 {
+    (self.center - self.eye).normalize()
+  }
+
+  // Eje derecho de la cámara: unitario y perpendicular a la vista y a `up`
+  pub fn right(&self) -> Vec3 {
+    self.forward().cross(&self.up).normalize()
+  }
+
+  // Gira la cámara hacia `target` sin moverla: `center` pasa a esa dirección, a la misma
+  // distancia de `eye` que tenía. Si `target` coincide con `eye` no cambia nada.
+  pub fn look_at(&mut self, target: Vec3) {
+    let direction = target - self.eye;
+    if direction.magnitude() <= f32::EPSILON {
+      return;
+    }
+    let distance = (self.center - self.eye).magnitude();
+    self.center = self.eye + direction.normalize() * distance;
+    self.has_changed = true;
+  }
+
   pub fn basis_change(&self, vector: &Vec3) -> Vec3 {
-    let forward = (self.center - self.eye).normalize();
-    let right = forward.cross(&self.up).normalize();
+    let (forward, right) = (self.forward(), self.right());
     let up = right.cross(&forward).normalize();
 
     let rotated = 
@@ -32,7 +108,10 @@ impl Camera {
     rotated.normalize()
   }
 
+  // La órbita mueve el ojo en coordenadas del mundo; si la cámara está alabeada, `up` se recalcula
+  // con el mismo alabeo respecto a la nueva vista para que no quede paralelo a ella.
   pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
+    let roll = self.roll_angle();
     let radius_vector = self.eye - self.center;
     let radius = radius_vector.magnitude();
 
@@ -51,12 +130,62 @@ impl Camera {
     );
 
     self.eye = new_eye;
+    if roll.abs() > LEVEL_ROLL {
+      let forward = self.forward();
+      self.up = rotate_vec3(&level_up(forward), roll, &forward).normalize();
+    }
     self.has_changed = true;
   }
 
+  // Desplaza la cámara hacia `target` conservando la distancia y el ángulo de vista.
+  // `smoothing` es la fracción del camino que queda pendiente tras cada frame (suavizado
+  // exponencial): 0 fija el centro en el objetivo y cuanto más cerca de 1, más se rezaga.
+  pub fn follow(&mut self, target: Vec3, smoothing: f32) {
+    let step = (target - self.center) * (1.0 - smoothing);
+    self.center += step;
+    self.eye += step;
+    self.has_changed = true;
+  }
+
+  // Gira la cámara sobre su dirección de vista (alabeo). `up` se mantiene perpendicular a la
+  // vista para que look_at siga construyendo una base ortonormal.
+  pub fn roll(&mut self, angle: f32) {
+    let (forward, right) = (self.forward(), self.right());
+    let up = right.cross(&forward);
+
+    self.up = rotate_vec3(&up, angle, &forward).normalize();
+    self.has_changed = true;
+  }
+
+  // Vuelve a poner `up` en el arriba del mundo, deshaciendo el alabeo. Si la cámara mira casi en
+  // vertical se deja como está, porque `up` quedaría paralelo a la vista.
+  pub fn reset_roll(&mut self) {
+    if self.forward().dot(&WORLD_UP).abs() < VERTICAL_ALIGNMENT {
+      self.up = WORLD_UP;
+      self.has_changed = true;
+    }
+  }
+
+  // Ángulo que está girada `up` sobre la vista respecto al arriba del mundo, en radianes y positivo
+  // en el sentido de `roll`; 0 si la cámara mira casi en vertical
+  fn roll_angle(&self) -> f32 {
+    let forward = self.forward();
+    if forward.dot(&WORLD_UP).abs() >= VERTICAL_ALIGNMENT {
+      return 0.0;
+    }
+    let level = level_up(forward);
+    let up = self.up - forward * self.up.dot(&forward);
+    forward.dot(&level.cross(&up)).atan2(level.dot(&up))
+  }
+
   pub fn zoom(&mut self, delta: f32) {
-    let direction = (self.center - self.eye).normalize();
-    self.eye += direction * delta;
+    self.eye += self.forward() * delta;
+    self.has_changed = true;
+  }
+
+  // Zoom de lente: abre o cierra el campo de visión en `delta` grados sin mover la cámara
+  pub fn widen_fov(&mut self, delta: f32) {
+    self.fov = (self.fov + delta).clamp(MIN_FOV, MAX_FOV);
     self.has_changed = true;
   }
 
@@ -76,6 +205,50 @@ impl Camera {
     self.has_changed = true;
   }
 
+  // Alterna entre órbita y vuelo libre. En vuelo libre `center` queda a una unidad delante de
+  // `eye`; al volver a la órbita se recupera la distancia que tenía el centro.
+  pub fn toggle_mode(&mut self) {
+    let forward = self.forward();
+    match self.mode {
+      CameraMode::Orbit => {
+        self.orbit_distance = Some((self.center - self.eye).magnitude());
+        self.center = self.eye + forward;
+        self.mode = CameraMode::FreeFly;
+      }
+      CameraMode::FreeFly => {
+        self.center = self.eye + forward * self.orbit_distance.take().unwrap_or(1.0);
+        self.mode = CameraMode::Orbit;
+      }
+    }
+    self.has_changed = true;
+  }
+
+  // Vuelo libre: desplaza `eye` (y con él `center`) en la base de la cámara; `movement.x` va
+  // hacia la derecha, `movement.y` hacia arriba y `movement.z` hacia delante
+  pub fn fly(&mut self, movement: Vec3) {
+    let (forward, right) = (self.forward(), self.right());
+    let up = right.cross(&forward);
+
+    self.eye += right * movement.x + up * movement.y + forward * movement.z;
+    self.center = self.eye + forward;
+    self.has_changed = true;
+  }
+
+  // Vuelo libre: gira la dirección de vista sobre `eye`. El giro horizontal es alrededor de `up`
+  // y el vertical alrededor del eje derecho, sin llegar a mirar justo hacia arriba o abajo.
+  pub fn look(&mut self, delta_yaw: f32, delta_pitch: f32) {
+    let mut forward = rotate_vec3(&self.forward(), delta_yaw, &self.up).normalize();
+
+    let right = forward.cross(&self.up).normalize();
+    let pitched = rotate_vec3(&forward, delta_pitch, &right).normalize();
+    if pitched.dot(&self.up.normalize()).abs() < MAX_LOOK_ALIGNMENT {
+      forward = pitched;
+    }
+
+    self.center = self.eye + forward;
+    self.has_changed = true;
+  }
+
   pub fn check_if_changed(&mut self) -> bool {
     if self.has_changed {
       self.has_changed = false;
@@ -85,3 +258,143 @@ impl Camera {
     }
   }
 }
+
+// Arriba de una cámara nivelada que mira hacia `forward`: el arriba del mundo hecho perpendicular
+// a la vista. `forward` no puede ser vertical.
+fn level_up(forward: Vec3) -> Vec3 {
+  forward.cross(&WORLD_UP).normalize().cross(&forward).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const TOLERANCE: f32 = 1e-5;
+
+  fn assert_orthonormal(camera: &Camera) {
+    let (forward, right) = (camera.forward(), camera.right());
+    let up = camera.basis_change(&Vec3::new(0.0, 1.0, 0.0));
+    for axis in [forward, right, up] {
+      assert!((axis.magnitude() - 1.0).abs() < TOLERANCE);
+    }
+    assert!(forward.dot(&right).abs() < TOLERANCE);
+    assert!(forward.dot(&up).abs() < TOLERANCE);
+    assert!(right.dot(&up).abs() < TOLERANCE);
+  }
+
+  #[test]
+  fn basis_is_orthonormal() {
+    assert_orthonormal(&Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0)));
+    assert_orthonormal(&Camera::new(Vec3::new(3.0, 7.0, -2.0), Vec3::new(1.0, -1.0, 4.0), Vec3::new(0.0, 1.0, 0.0)));
+    assert_orthonormal(&Camera::from_spherical(Vec3::new(5.0, 0.0, 5.0), 1.2, 0.7, 20.0));
+
+    let mut rolled = Camera::new(Vec3::new(0.0, 2.0, 10.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    rolled.roll(0.8);
+    assert_orthonormal(&rolled);
+  }
+
+  #[test]
+  fn look_at_keeps_distance_and_aims_at_target() {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    let target = Vec3::new(10.0, 3.0, -4.0);
+    camera.look_at(target);
+
+    assert_eq!(camera.eye, Vec3::new(0.0, 0.0, 5.0));
+    assert!(((camera.center - camera.eye).magnitude() - 5.0).abs() < TOLERANCE);
+    assert!((camera.forward() - (target - camera.eye).normalize()).magnitude() < TOLERANCE);
+    assert_orthonormal(&camera);
+
+    // Mirar al propio ojo no cambia nada
+    let center = camera.center;
+    camera.look_at(camera.eye);
+    assert_eq!(camera.center, center);
+  }
+
+  // Azimut, elevación y radio del ojo alrededor del punto al que mira
+  fn spherical(camera: &Camera) -> (f32, f32, f32) {
+    let offset = camera.eye - camera.center;
+    let radius = offset.magnitude();
+    (offset.z.atan2(offset.x), (offset.y / radius).asin(), radius)
+  }
+
+  #[test]
+  fn from_spherical_round_trips() {
+    let target = Vec3::new(4.0, -2.0, 7.0);
+    for (azimuth, elevation, radius) in [(0.0, 0.0, 5.0), (1.2, 0.7, 20.0), (-2.5, -0.9, 3.5), (3.0, 1.4, 60.0)] {
+      let camera = Camera::from_spherical(target, azimuth, elevation, radius);
+      assert_eq!(camera.center, target);
+
+      let (round_azimuth, round_elevation, round_radius) = spherical(&camera);
+      assert!((round_azimuth - azimuth).abs() < 1e-4);
+      assert!((round_elevation - elevation).abs() < 1e-4);
+      assert!((round_radius - radius).abs() < 1e-4);
+    }
+  }
+
+  #[test]
+  fn from_spherical_clamps_the_poles() {
+    // En el polo la vista sería paralela a `up`: la elevación se queda a 0.1 rad de la vertical
+    for (elevation, clamped) in [(PI / 2.0, PI / 2.0 - 0.1), (-PI / 2.0, -PI / 2.0 + 0.1)] {
+      let camera = Camera::from_spherical(Vec3::zeros(), 0.8, elevation, 10.0);
+      let (_, round_elevation, round_radius) = spherical(&camera);
+      assert!((round_elevation - clamped).abs() < 1e-4);
+      assert!((round_radius - 10.0).abs() < 1e-4);
+      assert_orthonormal(&camera);
+    }
+  }
+
+  #[test]
+  fn roll_keeps_up_orthogonal_and_undoes() {
+    let start = Camera::new(Vec3::new(3.0, 4.0, 12.0), Vec3::new(1.0, 0.0, -2.0), Vec3::new(0.0, 1.0, 0.0));
+    let mut camera = start.clone();
+    for angle in [0.3, 1.1, -2.0, PI] {
+      camera.roll(angle);
+      assert!(camera.up.dot(&camera.forward()).abs() < TOLERANCE);
+      assert!((camera.up.magnitude() - 1.0).abs() < TOLERANCE);
+
+      // El alabeo no mueve la cámara y el opuesto la devuelve a donde estaba
+      camera.roll(-angle);
+      assert_eq!((camera.eye, camera.center), (start.eye, start.center));
+      assert!((camera.basis_change(&Vec3::new(0.0, 1.0, 0.0)) - start.basis_change(&Vec3::new(0.0, 1.0, 0.0))).magnitude() < TOLERANCE);
+    }
+  }
+
+  #[test]
+  fn full_turn_roll_returns_up_to_its_orientation() {
+    let start = Camera::new(Vec3::new(3.0, 4.0, 12.0), Vec3::new(1.0, 0.0, -2.0), Vec3::new(0.0, 1.0, 0.0));
+    let start_up = start.basis_change(&Vec3::new(0.0, 1.0, 0.0));
+
+    // Una vuelta de golpe y la misma vuelta repartida en pasos desiguales
+    for steps in [vec![2.0 * PI], vec![0.5, 1.7, PI, 2.0 * PI - 0.5 - 1.7 - PI], vec![2.0 * PI / 8.0; 8]] {
+      let mut camera = start.clone();
+      for angle in steps {
+        camera.roll(angle);
+      }
+      assert_eq!((camera.eye, camera.center), (start.eye, start.center));
+      assert!((camera.basis_change(&Vec3::new(0.0, 1.0, 0.0)) - start_up).magnitude() < TOLERANCE);
+    }
+  }
+
+  #[test]
+  fn follow_converges_and_snaps_without_smoothing() {
+    let start = Camera::new(Vec3::new(0.0, 2.0, 10.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    let target = Vec3::new(20.0, -3.0, 8.0);
+
+    // Cada paso acorta la distancia al objetivo y la cámara se mueve sin girar
+    let mut camera = start.clone();
+    let mut distance = (target - camera.center).magnitude();
+    for _ in 0..80 {
+      camera.follow(target, 0.85);
+      let next = (target - camera.center).magnitude();
+      assert!(next < distance);
+      distance = next;
+    }
+    assert!(distance < 1e-3);
+    assert!((camera.eye - camera.center - (start.eye - start.center)).magnitude() < 1e-3);
+
+    let mut snapped = start.clone();
+    snapped.follow(target, 0.0);
+    assert_eq!(snapped.center, target);
+    assert!((snapped.eye - (start.eye + target)).magnitude() < TOLERANCE);
+  }
+}
@@ -0,0 +1,215 @@
+use nalgebra_glm::Vec3;
+use crate::background::Starfield;
+use crate::camera::Camera;
+use crate::color::ToneMapping;
+use crate::godrays::GodRays;
+use crate::stats::RenderBudget;
+
+// Valor que sigue a `flag` en la línea de comandos, p. ej. `--scene archivo.json`
+pub fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+// Interpreta un vector con formato `x,y,z`
+pub fn parse_vec3(text: &str) -> Result<Vec3, String> {
+    let components: Vec<f32> = text
+        .split(',')
+        .map(|part| part.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("'{}' no es un vector válido (se espera x,y,z)", text))?;
+
+    match components[..] {
+        [x, y, z] if x.is_finite() && y.is_finite() && z.is_finite() => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!("'{}' no es un vector válido (se espera x,y,z)", text)),
+    }
+}
+
+fn arg_vec3(args: &[String], flag: &str) -> Result<Option<Vec3>, String> {
+    arg_value(args, flag)
+        .map(|value| parse_vec3(value).map_err(|err| format!("{}: {}", flag, err)))
+        .transpose()
+}
+
+// Construye la cámara a partir de `--eye`, `--center` y `--up`, usando `default`
+// para los que no se indiquen
+pub fn camera_from_args(args: &[String], default: &Camera) -> Result<Camera, String> {
+    let eye = arg_vec3(args, "--eye")?.unwrap_or(default.eye);
+    let center = arg_vec3(args, "--center")?.unwrap_or(default.center);
+    let up = arg_vec3(args, "--up")?.unwrap_or(default.up);
+
+    let view_direction = center - eye;
+    if view_direction.magnitude() < 1e-6 {
+        return Err("--eye y --center no pueden ser el mismo punto".to_string());
+    }
+    if up.magnitude() < 1e-6 {
+        return Err("--up no puede ser el vector cero".to_string());
+    }
+
+    // look_at no puede construir una base si `up` es paralelo a la dirección de vista
+    if view_direction.normalize().cross(&up.normalize()).magnitude() < 1e-4 {
+        return Err("--up no puede ser paralelo a la dirección de vista (--center - --eye)".to_string());
+    }
+
+    Ok(Camera::new(eye, center, up))
+}
+
+// Ajustes de los rayos crepusculares: `--god-ray-samples N` y `--god-ray-decay d`
+pub fn god_rays_from_args(args: &[String]) -> Result<GodRays, String> {
+    let mut settings = GodRays::default();
+
+    if let Some(value) = arg_value(args, "--god-ray-samples") {
+        settings.samples = match value.parse::<usize>() {
+            Ok(samples) if samples > 0 => samples,
+            _ => return Err(format!("--god-ray-samples: '{}' no es un entero positivo", value)),
+        };
+    }
+    if let Some(value) = arg_value(args, "--god-ray-decay") {
+        settings.decay = match value.parse::<f32>() {
+            Ok(decay) if (0.0..=1.0).contains(&decay) => decay,
+            _ => return Err(format!("--god-ray-decay: '{}' debe estar entre 0 y 1", value)),
+        };
+    }
+
+    Ok(settings)
+}
+
+// Suavizado de la cámara de seguimiento por defecto: algo de retraso sin perder al planeta
+pub const DEFAULT_FOLLOW_SMOOTHING: f32 = 0.85;
+
+// `--follow-smoothing s`, de 0 (seguimiento rígido) a menos de 1, por frame de 60 Hz
+pub fn follow_smoothing_from_args(args: &[String]) -> Result<f32, String> {
+    match arg_value(args, "--follow-smoothing") {
+        None => Ok(DEFAULT_FOLLOW_SMOOTHING),
+        Some(value) => match value.parse::<f32>() {
+            Ok(smoothing) if (0.0..1.0).contains(&smoothing) => Ok(smoothing),
+            _ => Err(format!("--follow-smoothing: '{}' debe estar entre 0 y 1 (sin incluir el 1)", value)),
+        },
+    }
+}
+
+// Campo de estrellas de `--background procedural`: `--star-seed n` elige el cielo y `--twinkle`
+// hace centellear las estrellas
+pub fn starfield_from_args(args: &[String]) -> Result<Starfield, String> {
+    let mut starfield = Starfield::default();
+
+    if let Some(value) = arg_value(args, "--star-seed") {
+        starfield.seed = value.parse::<u64>()
+            .map_err(|_| format!("--star-seed: '{}' no es un entero sin signo", value))?;
+    }
+    starfield.twinkle = args.iter().any(|arg| arg == "--twinkle");
+
+    Ok(starfield)
+}
+
+// Semilla de las fases iniciales de los planetas integrados. Con la misma semilla el sistema
+// empieza siempre igual y los destinos de warp apuntan a los planetas (ver `define_warp_positions`).
+pub const DEFAULT_PLANET_SEED: u64 = 3;
+
+// `--planet-seed n` elige otra disposición inicial de los planetas
+pub fn planet_seed_from_args(args: &[String]) -> Result<u64, String> {
+    match arg_value(args, "--planet-seed") {
+        None => Ok(DEFAULT_PLANET_SEED),
+        Some(value) => value.parse::<u64>()
+            .map_err(|_| format!("--planet-seed: '{}' no es un entero sin signo", value)),
+    }
+}
+
+// Sin `--fog` no hay niebla, así la escena se ve igual que antes de añadirla
+pub const DEFAULT_FOG_DENSITY: f32 = 0.0;
+
+// `--fog d`, densidad de la niebla por unidad de distancia; 0 la desactiva
+pub fn fog_density_from_args(args: &[String]) -> Result<f32, String> {
+    match arg_value(args, "--fog") {
+        None => Ok(DEFAULT_FOG_DENSITY),
+        Some(value) => match value.parse::<f32>() {
+            Ok(density) if density.is_finite() && density >= 0.0 => Ok(density),
+            _ => Err(format!("--fog: '{}' debe ser un número mayor o igual que 0", value)),
+        },
+    }
+}
+
+// Semiapertura del foco de la nave cuando se enciende con F1 sin haber usado `--spotlight`, en grados
+pub const DEFAULT_SPOTLIGHT_CONE: f32 = 15.0;
+
+// `--spotlight grados`, semiapertura del foco de la nave (entre 0 y 90): lo enciende desde el
+// principio con ese cono. None si no se usa.
+pub fn spotlight_cone_from_args(args: &[String]) -> Result<Option<f32>, String> {
+    match arg_value(args, "--spotlight") {
+        None => Ok(None),
+        Some(value) => match value.parse::<f32>() {
+            Ok(degrees) if degrees > 0.0 && degrees < 90.0 => Ok(Some(degrees)),
+            _ => Err(format!("--spotlight: '{}' debe ser un ángulo en grados entre 0 y 90", value)),
+        },
+    }
+}
+
+// Sin corrección gamma: los shaders están ajustados directamente en el espacio de la pantalla
+pub const DEFAULT_GAMMA: f32 = 1.0;
+
+// `--gamma g`, mayor que 0; 2.2 trata los colores de los shaders como lineales
+pub fn gamma_from_args(args: &[String]) -> Result<f32, String> {
+    match arg_value(args, "--gamma") {
+        None => Ok(DEFAULT_GAMMA),
+        Some(value) => match value.parse::<f32>() {
+            Ok(gamma) if gamma.is_finite() && gamma > 0.0 => Ok(gamma),
+            _ => Err(format!("--gamma: '{}' debe ser un número mayor que 0", value)),
+        },
+    }
+}
+
+// Franjas horizontales en las que se reparte el sombreado de cada objeto entre los hilos; hay
+// más que núcleos para que las franjas vacías (el espacio) no dejen hilos parados
+pub const DEFAULT_RASTER_TILES: usize = 32;
+
+// `--raster-tiles n`, entero positivo; 1 sombrea en un único hilo
+pub fn raster_tiles_from_args(args: &[String]) -> Result<usize, String> {
+    match arg_value(args, "--raster-tiles") {
+        None => Ok(DEFAULT_RASTER_TILES),
+        Some(value) => match value.parse::<usize>() {
+            Ok(tiles) if tiles > 0 => Ok(tiles),
+            _ => Err(format!("--raster-tiles: '{}' no es un entero positivo", value)),
+        },
+    }
+}
+
+// `--frames n` del modo sin ventana: frames que se renderizan y se guardan (por defecto 1)
+pub fn headless_frames_from_args(args: &[String]) -> Result<usize, String> {
+    match arg_value(args, "--frames") {
+        None => Ok(1),
+        Some(value) => match value.parse::<usize>() {
+            Ok(frames) if frames > 0 => Ok(frames),
+            _ => Err(format!("--frames: '{}' no es un entero positivo", value)),
+        },
+    }
+}
+
+// `--tone-map none|reinhard|aces`, curva para los colores que pasan de 255 (por defecto ninguna)
+pub fn tone_mapping_from_args(args: &[String]) -> Result<ToneMapping, String> {
+    match arg_value(args, "--tone-map") {
+        None => Ok(ToneMapping::default()),
+        Some("none") => Ok(ToneMapping::None),
+        Some("reinhard") => Ok(ToneMapping::Reinhard),
+        Some("aces") => Ok(ToneMapping::Aces),
+        Some(value) => Err(format!("--tone-map: '{}' no es una curva válida (none, reinhard o aces)", value)),
+    }
+}
+
+// Presupuesto por frame: `--triangle-budget N` y `--fragment-budget N` (sin límite si no se indican)
+pub fn budget_from_args(args: &[String]) -> Result<RenderBudget, String> {
+    let limit = |flag: &str| -> Result<Option<usize>, String> {
+        arg_value(args, flag)
+            .map(|value| match value.parse::<usize>() {
+                Ok(limit) if limit > 0 => Ok(limit),
+                _ => Err(format!("{}: '{}' no es un entero positivo", flag, value)),
+            })
+            .transpose()
+    };
+
+    Ok(RenderBudget {
+        max_triangles: limit("--triangle-budget")?,
+        max_fragments: limit("--fragment-budget")?,
+    })
+}
@@ -0,0 +1,98 @@
+use nalgebra_glm::Vec4;
+
+use crate::vertex::Vertex;
+
+// Vértice ya transformado a mundo junto con su posición en clip space (antes de dividir por w)
+#[derive(Clone)]
+pub struct ClipVertex {
+    pub vertex: Vertex,
+    pub clip: Vec4,
+}
+
+// Los lados del frustum se recortan con este margen (en múltiplos del tamaño de la pantalla):
+// basta para acotar el área que recorre el rasterizador sin partir los triángulos que apenas
+// sobresalen, que son la mayoría y se descartan píxel a píxel igual que antes.
+const GUARD_BAND: f32 = 2.0;
+
+// Planos de recorte como distancias con signo en clip space (dentro si es >= 0). El primero es
+// el plano cercano z = -w; los otros cuatro son los lados ampliados con la banda de guarda.
+const PLANE_COUNT: usize = 5;
+
+fn plane_distance(clip: &Vec4, plane: usize) -> f32 {
+    match plane {
+        0 => clip.z + clip.w,
+        1 => GUARD_BAND * clip.w - clip.x,
+        2 => GUARD_BAND * clip.w + clip.x,
+        3 => GUARD_BAND * clip.w - clip.y,
+        _ => GUARD_BAND * clip.w + clip.y,
+    }
+}
+
+// Vértice en la fracción `t` del segmento de `a` a `b`. En clip space todo es lineal, así que
+// los atributos se interpolan sin corrección de perspectiva.
+fn interpolate(a: &ClipVertex, b: &ClipVertex, t: f32) -> ClipVertex {
+    let (va, vb) = (&a.vertex, &b.vertex);
+    let vertex = Vertex {
+        position: va.position + (vb.position - va.position) * t,
+        normal: va.normal + (vb.normal - va.normal) * t,
+        tex_coords: va.tex_coords + (vb.tex_coords - va.tex_coords) * t,
+        tangent: va.tangent + (vb.tangent - va.tangent) * t,
+        color: va.color.lerp(&vb.color, t),
+        transformed_position: va.transformed_position,
+        transformed_normal: va.transformed_normal + (vb.transformed_normal - va.transformed_normal) * t,
+        transformed_tangent: va.transformed_tangent + (vb.transformed_tangent - va.transformed_tangent) * t,
+        world_position: va.world_position + (vb.world_position - va.world_position) * t,
+        clip_w: va.clip_w,
+        degenerate: false,
+    };
+
+    ClipVertex {
+        vertex,
+        clip: a.clip + (b.clip - a.clip) * t,
+    }
+}
+
+// Recorta un triángulo contra el plano cercano (y los lados ampliados) y añade el resultado a
+// `output`: nada si queda entero fuera, el mismo triángulo si queda entero dentro, y el polígono
+// recortado en abanico si cruza algún plano. Sin el recorte, un vértice detrás de la cámara
+// (w ≤ 0) saldría proyectado al otro lado de la pantalla. Se conserva el orden de los vértices,
+// así que el descarte de caras traseras sigue funcionando sobre los triángulos resultantes.
+pub fn clip_triangle(tri: [ClipVertex; 3], output: &mut Vec<[ClipVertex; 3]>) {
+    let inside = |vertex: &ClipVertex| (0..PLANE_COUNT).all(|plane| plane_distance(&vertex.clip, plane) >= 0.0);
+    if tri.iter().all(inside) {
+        output.push(tri);
+        return;
+    }
+
+    // Sutherland-Hodgman: el polígono gana como mucho un vértice por plano
+    let mut polygon: Vec<ClipVertex> = tri.to_vec();
+    for plane in 0..PLANE_COUNT {
+        if polygon.is_empty() {
+            return;
+        }
+
+        let distances: Vec<f32> = polygon.iter().map(|vertex| plane_distance(&vertex.clip, plane)).collect();
+        if distances.iter().all(|&distance| distance >= 0.0) {
+            continue;
+        }
+
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for i in 0..polygon.len() {
+            let j = (i + 1) % polygon.len();
+            let (d_current, d_next) = (distances[i], distances[j]);
+
+            if d_current >= 0.0 {
+                clipped.push(polygon[i].clone());
+            }
+            if (d_current >= 0.0) != (d_next >= 0.0) {
+                let t = d_current / (d_current - d_next);
+                clipped.push(interpolate(&polygon[i], &polygon[j], t));
+            }
+        }
+        polygon = clipped;
+    }
+
+    for k in 1..polygon.len().saturating_sub(1) {
+        output.push([polygon[0].clone(), polygon[k].clone(), polygon[k + 1].clone()]);
+    }
+}
@@ -1,78 +1,180 @@
 use std::fmt;
+use serde::{Deserialize, Serialize};
 
+// Canales de 0 a 255 en punto flotante y sin recortar por arriba: los shaders pueden pasarse de
+// 255 (brillos, emisión) y el valor se conserva hasta el mapeo de tonos o hasta `to_hex`.
+// Originalmente se guardaban como u8; se pasaron a f32 para el rango alto del tone mapping y
+// `new` sigue recibiendo u8 para que los colores constantes y `from_hex` no cambien.
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
-    r: u8,
-    g: u8,
-    b: u8,
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+// Curva con la que se comprimen los colores por encima de 255 antes de empaquetarlos
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ToneMapping {
+    #[default]
+    None,      // Se recorta cada canal en 255: los colores dentro del rango no cambian
+    Reinhard,  // c / (1 + c): suave pero oscurece los tonos medios
+    Aces,      // Aproximación de la curva fílmica ACES: más contraste y blancos que no se queman
 }
 
 impl Color {
     pub const fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
+        Color { r: r as f32, g: g as f32, b: b as f32 }
+    }
+
+    // Color con canales en la escala de 0 a 255, que pueden pasar de 255; los negativos quedan en 0
+    pub fn from_f32(r: f32, g: f32, b: f32) -> Self {
+        Color { r: r.max(0.0), g: g.max(0.0), b: b.max(0.0) }
     }
 
     pub const fn from_hex(hex: u32) -> Self {
         let r = ((hex >> 16) & 0xFF) as u8;
         let g = ((hex >> 8) & 0xFF) as u8;
         let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
+        Color::new(r, g, b)
+    }
+
+    // Color a partir de tono `h` en grados (cualquier valor, se da la vuelta cada 360), saturación
+    // `s` y valor `v` de 0 a 1
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let s = s.clamp(0.0, 1.0);
+        let v = v.max(0.0) * 255.0;
+        let chroma = v * s;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = v - chroma;
+        Color::from_f32(r + m, g + m, b + m)
+    }
+
+    // Tono en grados (0 a 360), saturación y valor (de 0 a 1; el valor pasa de 1 si el color
+    // pasa de 255)
+    fn to_hsv(self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let chroma = max - min;
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / chroma).rem_euclid(6.0)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / chroma + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue, saturation, max / 255.0)
+    }
+
+    // Gira el tono `degrees` grados conservando saturación y valor
+    pub fn shift_hue(&self, degrees: f32) -> Color {
+        let (hue, saturation, value) = self.to_hsv();
+        Color::from_hsv(hue + degrees, saturation, value)
     }
 
     pub const fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color { r: 0.0, g: 0.0, b: 0.0 }
+    }
+
+    // Empaqueta en 0x00RRGGBB recortando cada canal en 255
+    pub fn to_hex(self) -> u32 {
+        let channel = |value: f32| value.clamp(0.0, 255.0) as u32;
+        (channel(self.r) << 16) | (channel(self.g) << 8) | channel(self.b)
     }
 
-    pub fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    // Como `to_hex`, pero codificando cada canal con la curva gamma (c^(1/gamma)) antes de
+    // empaquetarlo: con gamma > 1 los tonos medios y oscuros se aclaran. Con 1.0 no cambia nada.
+    pub fn to_hex_gamma(self, gamma: f32) -> u32 {
+        if gamma == 1.0 {
+            return self.to_hex();
+        }
+        let encode = |channel: f32| ((channel.clamp(0.0, 255.0) / 255.0).powf(1.0 / gamma) * 255.0 + 0.5) as u32;
+        (encode(self.r) << 16) | (encode(self.g) << 8) | encode(self.b)
+    }
+
+    // Lleva cada canal de [0, ∞) a [0, 255] con la curva `mapping`
+    pub fn tone_mapped(self, mapping: ToneMapping) -> Color {
+        let curve = |value: f32| -> f32 {
+            let x = value / 255.0;
+            let mapped = match mapping {
+                ToneMapping::None => x.min(1.0),
+                ToneMapping::Reinhard => x / (1.0 + x),
+                // Ajuste de Krzysztof Narkowicz
+                ToneMapping::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+            };
+            mapped.clamp(0.0, 1.0) * 255.0
+        };
+        Color { r: curve(self.r), g: curve(self.g), b: curve(self.b) }
     }
 
     // Linear interpolation between two colors
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
         Color {
-            r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
-            g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
-            b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
         }
     }
 
+    // Recorta cada canal a 0..=255 (los shaders pueden haberse pasado de 255)
+    pub fn clamp(&self) -> Color {
+        Color { r: self.r.clamp(0.0, 255.0), g: self.g.clamp(0.0, 255.0), b: self.b.clamp(0.0, 255.0) }
+    }
+
+    // Escala la saturación alejando o acercando cada canal a la luminancia del color: 0 lo deja
+    // en gris, 1 no lo cambia y más de 1 lo satura. Los canales que bajarían de 0 quedan en 0.
+    pub fn saturate(&self, factor: f32) -> Color {
+        let luminance = 0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b;
+        let channel = |value: f32| luminance + (value - luminance) * factor;
+        Color::from_f32(channel(self.r), channel(self.g), channel(self.b))
+    }
+
+    // Los modos de mezcla originales de `Color` siguen disponibles aunque el pipeline ya no los
+    // use: las mezclas del framebuffer las elige `BlendMode`
+    #[allow(dead_code)]
     pub fn is_black(&self) -> bool {
-        self.r == 0 && self.g == 0 && self.b == 0 
+        self.r == 0.0 && self.g == 0.0 && self.b == 0.0
     }
 
+    #[allow(dead_code)]
     pub fn blend_normal(&self, blend: &Color) -> Color {
         if blend.is_black() { *self } else { *blend }
       }
-    
+
+    #[allow(dead_code)]
     pub fn blend_multiply(&self, blend: &Color) -> Color {
-        Color::new(
-            ((self.r as f32 * blend.r as f32) / 255.0) as u8,
-            ((self.g as f32 * blend.g as f32) / 255.0) as u8,
-            ((self.b as f32 * blend.b as f32) / 255.0) as u8
-        )
+        Color {
+            r: self.r * blend.r / 255.0,
+            g: self.g * blend.g / 255.0,
+            b: self.b * blend.b / 255.0,
+        }
     }
-    
+
     pub fn blend_add(&self, blend: &Color) -> Color {
-        Color::new(
-            (self.r as u16 + blend.r as u16).min(255) as u8,
-            (self.g as u16 + blend.g as u16).min(255) as u8,
-            (self.b as u16 + blend.b as u16).min(255) as u8
-        )
+        *self + *blend
     }
-    
-    pub fn blend_subtract(&self, blend: &Color) -> Color {
-        let r = (self.r as i16 - blend.r as i16).max(0).min(255) as u8;
-        let g = (self.g as i16 - blend.g as i16).max(0).min(255) as u8;
-        let b = (self.b as i16 - blend.b as i16).max(0).min(255) as u8;
 
-        Color::new(r, g, b)
+    #[allow(dead_code)]
+    pub fn blend_subtract(&self, blend: &Color) -> Color {
+        *self - *blend
     }
-    
+
     pub fn blend(&self, other: Color, alpha: f32) -> Color {
-        let r = (self.r as f32 * (1.0 - alpha) + other.r as f32 * alpha).round() as u8;
-        let g = (self.g as f32 * (1.0 - alpha) + other.g as f32 * alpha).round() as u8;
-        let b = (self.b as f32 * (1.0 - alpha) + other.b as f32 * alpha).round() as u8;
+        let r = self.r * (1.0 - alpha) + other.r * alpha;
+        let g = self.g * (1.0 - alpha) + other.g * alpha;
+        let b = self.b * (1.0 - alpha) + other.b * alpha;
         Color { r, g, b }
     }
 
@@ -85,24 +187,31 @@ impl Add for Color {
 
     fn add(self, other: Color) -> Color {
         Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
         }
     }
 }
 
+use std::ops::Sub;
+
+// Resta canal a canal; lo que bajaría de 0 se queda en 0
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, other: Color) -> Color {
+        Color::from_f32(self.r - other.r, self.g - other.g, self.b - other.b)
+    }
+}
+
 use std::ops::Mul;
 
 impl Mul<f32> for Color {
     type Output = Color;
 
     fn mul(self, scalar: f32) -> Color {
-        Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
-        }
+        Color::from_f32(self.r * scalar, self.g * scalar, self.b * scalar)
     }
 }
 
@@ -111,3 +220,81 @@ impl fmt::Display for Color {
         write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels(color: Color) -> (f32, f32, f32) {
+        (color.r, color.g, color.b)
+    }
+
+    #[test]
+    fn subtracting_below_zero_clamps_instead_of_wrapping() {
+        let result = Color::new(10, 200, 50) - Color::new(20, 100, 60);
+        assert_eq!(channels(result), (0.0, 100.0, 0.0));
+        assert_eq!(Color::new(0, 0, 0).blend_subtract(&Color::new(255, 255, 255)).to_hex(), 0x000000);
+    }
+
+    #[test]
+    fn clamp_bounds_overflowing_channels() {
+        let overflow = Color::new(200, 128, 250) + Color::new(100, 0, 250);
+        assert_eq!(channels(overflow), (300.0, 128.0, 500.0));
+        assert_eq!(channels(overflow.clamp()), (255.0, 128.0, 255.0));
+        assert_eq!(overflow.to_hex(), 0xFF80FF);
+    }
+
+    #[test]
+    fn saturate_scales_distance_from_gray() {
+        let color = Color::new(200, 100, 50);
+        let (r, g, b) = channels(color.saturate(0.0));
+        assert!((r - g).abs() < 1e-3 && (g - b).abs() < 1e-3);
+        assert_eq!(channels(color.saturate(1.0)), channels(color));
+
+        // Un factor grande no deja canales negativos
+        let (r, g, b) = channels(color.saturate(10.0));
+        assert!(r > 200.0 && g >= 0.0 && b == 0.0);
+    }
+
+    fn assert_close(color: Color, expected: (f32, f32, f32)) {
+        let (r, g, b) = channels(color);
+        let close = |a: f32, b: f32| (a - b).abs() < 0.5;
+        assert!(close(r, expected.0) && close(g, expected.1) && close(b, expected.2), "{} != {:?}", color, expected);
+    }
+
+    #[test]
+    fn from_hsv_matches_known_rgb() {
+        assert_close(Color::from_hsv(0.0, 1.0, 1.0), (255.0, 0.0, 0.0));
+        assert_close(Color::from_hsv(120.0, 1.0, 1.0), (0.0, 255.0, 0.0));
+        assert_close(Color::from_hsv(240.0, 1.0, 1.0), (0.0, 0.0, 255.0));
+        assert_close(Color::from_hsv(75.0, 0.0, 0.5), (127.5, 127.5, 127.5));
+        // El tono da la vuelta cada 360 grados
+        assert_close(Color::from_hsv(360.0, 1.0, 1.0), (255.0, 0.0, 0.0));
+        assert_close(Color::from_hsv(-120.0, 1.0, 1.0), (0.0, 0.0, 255.0));
+    }
+
+    #[test]
+    fn hsv_round_trips() {
+        for color in [Color::new(255, 0, 0), Color::new(0, 255, 0), Color::new(0, 0, 255), Color::new(128, 128, 128), Color::new(75, 0, 130)] {
+            let (h, s, v) = color.to_hsv();
+            assert_close(Color::from_hsv(h, s, v), channels(color));
+        }
+    }
+
+    #[test]
+    fn shift_hue_rotates_channels() {
+        assert_close(Color::new(255, 0, 0).shift_hue(120.0), (0.0, 255.0, 0.0));
+        assert_close(Color::new(0, 255, 0).shift_hue(120.0), (0.0, 0.0, 255.0));
+        assert_close(Color::new(0, 0, 255).shift_hue(120.0), (255.0, 0.0, 0.0));
+        assert_close(Color::new(200, 100, 50).shift_hue(360.0), (200.0, 100.0, 50.0));
+        // Sin saturación no hay tono que girar
+        assert_close(Color::new(90, 90, 90).shift_hue(120.0), (90.0, 90.0, 90.0));
+    }
+
+    #[test]
+    fn default_tone_mapping_keeps_in_range_colors() {
+        let color = Color::from_f32(12.0, 128.0, 255.0);
+        assert_eq!(channels(color.tone_mapped(ToneMapping::default())), channels(color));
+        assert_eq!(channels(Color::from_f32(400.0, 0.0, 0.0).tone_mapped(ToneMapping::default())), (255.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,112 @@
+use nalgebra_glm::{Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::orbits::{kepler_position, MIN_CLIP_W};
+use crate::Uniforms;
+
+// Cometa por defecto: una elipse muy alargada e inclinada respecto al plano de los planetas
+pub const DEFAULT_COMET_SEMI_MAJOR_AXIS: f32 = 26.0;
+pub const DEFAULT_COMET_ECCENTRICITY: f32 = 0.8;
+pub const DEFAULT_COMET_INCLINATION: f32 = 0.9;
+pub const DEFAULT_COMET_ORBIT_SPEED: f32 = 0.004;
+pub const DEFAULT_COMET_SCALE: f32 = 0.25;
+
+// Partículas de la cola y separación en frames simulados entre dos consecutivas: la cola recorre
+// las posiciones anteriores del núcleo, así que se alarga cerca del periastro
+const TAIL_PARTICLES: usize = 120;
+const TAIL_TIME_STEP: f32 = 0.25;
+
+// Distancia al núcleo a la que el brillo de una partícula cae a 1/e
+const TAIL_FADE_DISTANCE: f32 = 3.0;
+
+// Radio en unidades del mundo de cada partícula; en pantalla se limita para que las cercanas
+// no tapen la vista
+const TAIL_PARTICLE_SIZE: f32 = 0.12;
+const MAX_PARTICLE_RADIUS: f32 = 12.0;
+
+const TAIL_COLOR: Color = Color::new(50, 70, 100);
+
+// Cometa en una órbita kepleriana propia, independiente de la lista de planetas. La elipse es
+// la de `kepler_position` girada `inclination` radianes alrededor del eje X.
+#[derive(Serialize, Deserialize)]
+pub struct Comet {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub inclination: f32,
+    pub orbit_speed: f32,
+    pub orbit_phase: f32,
+    pub scale: f32,
+    #[serde(skip)]
+    pub lod: Cell<usize>,  // Nivel de detalle del frame anterior (ver `SphereLods::select_lod`)
+}
+
+impl Default for Comet {
+    fn default() -> Self {
+        Comet {
+            semi_major_axis: DEFAULT_COMET_SEMI_MAJOR_AXIS,
+            eccentricity: DEFAULT_COMET_ECCENTRICITY,
+            inclination: DEFAULT_COMET_INCLINATION,
+            orbit_speed: DEFAULT_COMET_ORBIT_SPEED,
+            orbit_phase: 0.0,
+            scale: DEFAULT_COMET_SCALE,
+            lod: Cell::new(0),
+        }
+    }
+}
+
+impl Comet {
+    // Posición del núcleo en el instante `time` (frames simulados)
+    pub fn position(&self, time: f32) -> Vec3 {
+        let point = kepler_position(self.semi_major_axis, self.eccentricity, self.orbit_speed * time + self.orbit_phase);
+        let (sin, cos) = self.inclination.sin_cos();
+        Vec3::new(point.x, -point.z * sin, point.z * cos)
+    }
+
+    // Partículas de la cola en el instante `time`: posición y brillo de 0 a 1, que decae con la
+    // distancia al núcleo
+    pub fn tail(&self, time: f32) -> Vec<(Vec3, f32)> {
+        let head = self.position(time);
+        (1..=TAIL_PARTICLES).map(|index| {
+            let position = self.position(time - index as f32 * TAIL_TIME_STEP);
+            let brightness = (-(position - head).magnitude() / TAIL_FADE_DISTANCE).exp();
+            (position, brightness)
+        }).collect()
+    }
+}
+
+// Dibuja cada partícula como un disco orientado a la cámara que se suma al color del
+// framebuffer, más intenso en el centro. Pasa la prueba de profundidad sin escribir en el
+// z-buffer, así que debe ir después de lo opaco.
+pub fn draw_tail(framebuffer: &mut Framebuffer, uniforms: &Uniforms, particles: &[(Vec3, f32)]) {
+    let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+    // Píxeles por unidad del mundo a distancia 1 de la cámara
+    let pixels_per_unit = uniforms.projection_matrix[(1, 1)] * framebuffer.height as f32 * 0.5;
+
+    for &(position, brightness) in particles {
+        let clip = view_projection * Vec4::new(position.x, position.y, position.z, 1.0);
+        if clip.w < MIN_CLIP_W {
+            continue;
+        }
+        let ndc = clip / clip.w;
+        let screen = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+        let radius = (TAIL_PARTICLE_SIZE * pixels_per_unit / clip.w).clamp(1.0, MAX_PARTICLE_RADIUS);
+        if screen.x + radius < 0.0 || screen.y + radius < 0.0 {
+            continue;
+        }
+
+        let (min_x, max_x) = ((screen.x - radius).floor().max(0.0), (screen.x + radius).ceil());
+        let (min_y, max_y) = ((screen.y - radius).floor().max(0.0), (screen.y + radius).ceil());
+        for y in min_y as usize..=max_y.min(framebuffer.height as f32) as usize {
+            for x in min_x as usize..=max_x.min(framebuffer.width as f32) as usize {
+                let distance = ((x as f32 + 0.5 - screen.x).powi(2) + (y as f32 + 0.5 - screen.y).powi(2)).sqrt();
+                let falloff = 1.0 - distance / radius;
+                if falloff > 0.0 {
+                    framebuffer.add_point(x, y, screen.z, TAIL_COLOR * (brightness * falloff));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::keys::{key_from_name, KeyBindings};
+use crate::Planet;
+
+// Cada cuánto se mira la fecha de modificación de un archivo vigilado
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Vigila un archivo de configuración consultando su fecha de modificación como mucho una vez
+// por `WATCH_INTERVAL`, para recargarlo al guardarlo sin reiniciar el programa
+pub struct FileWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str) -> Self {
+        FileWatcher {
+            path: path.to_string(),
+            modified: modified_time(path),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // true si el archivo ha cambiado desde la última vez que se vio. Mientras no existe o no se
+    // puede leer su fecha no se considera cambiado.
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < WATCH_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// Motivo por el que no se pudo cargar un archivo de configuración
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Ron(ron::error::SpannedError),
+    Json(serde_json::Error),
+    Invalid(String),  // El formato es correcto pero algún valor no tiene sentido
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "no se pudo leer: {}", err),
+            ConfigError::Ron(err) => write!(f, "RON inválido: {}", err),
+            ConfigError::Json(err) => write!(f, "JSON inválido: {}", err),
+            ConfigError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+// Lee una lista de planetas. Los archivos `.json` se interpretan como JSON y el resto como RON.
+// Cada entrada necesita position, rotation_speed, orbit_radius, scale y shader_mode, y también
+// orbit_speed salvo que use auto_orbit_speed; los demás campos de `Planet` son opcionales. Una
+// lista vacía no es válida: dejaría el sistema sin planetas ni estrellas que lo iluminen.
+pub fn load_planets(path: &str) -> Result<Vec<Planet>, ConfigError> {
+    let text = fs::read_to_string(path)?;
+
+    let is_json = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let planets: Vec<Planet> = if is_json {
+        serde_json::from_str(&text).map_err(ConfigError::Json)?
+    } else {
+        ron::from_str(&text).map_err(ConfigError::Ron)?
+    };
+
+    if planets.is_empty() {
+        return Err(ConfigError::Invalid("la lista de planetas está vacía".to_string()));
+    }
+    Ok(planets)
+}
+
+// Lee `KeyBindings` desde un mapa RON de acción a nombre de tecla, por ejemplo
+// `{ "move_forward": "Z", "move_left": "Q" }`. Las acciones que no aparecen conservan su tecla
+// por defecto; una acción o una tecla desconocida invalida el archivo entero.
+pub fn load_key_bindings(path: &str) -> Result<KeyBindings, ConfigError> {
+    let text = fs::read_to_string(path)?;
+    let overrides: HashMap<String, String> = ron::from_str(&text).map_err(ConfigError::Ron)?;
+
+    let mut bindings = KeyBindings::default();
+    for (action, key_name) in &overrides {
+        let key = key_from_name(key_name)
+            .ok_or_else(|| ConfigError::Invalid(format!("tecla desconocida '{}' para '{}'", key_name, action)))?;
+        let binding = bindings.binding_mut(action)
+            .ok_or_else(|| ConfigError::Invalid(format!("acción desconocida '{}'", action)))?;
+        *binding = key;
+    }
+    Ok(bindings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_planet_list_is_invalid() {
+        for extension in ["ron", "json"] {
+            let path = std::env::temp_dir().join(format!("space_travel_empty_planets.{}", extension));
+            fs::write(&path, "[]").unwrap();
+            let result = load_planets(path.to_str().unwrap());
+            fs::remove_file(&path).unwrap();
+            assert!(matches!(result, Err(ConfigError::Invalid(_))), "{}", extension);
+        }
+    }
+}
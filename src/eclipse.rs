@@ -0,0 +1,85 @@
+use nalgebra_glm::{Vec3, dot};
+
+// Cuerpos que se prueban como posibles causantes de eclipse en cada planeta: los más cercanos a
+// él entre los que están más cerca de la luz
+pub const MAX_OCCLUDERS: usize = 3;
+
+// Sombras de eclipse sobre un cuerpo: la luz principal es un disco de radio `light_radius` y cada
+// posible tapador una esfera (centro, radio). En cada fragmento se compara el tamaño aparente de
+// la luz y de cada esfera vistos desde él, así que la sombra tiene umbra y penumbra suaves.
+#[derive(Clone, Copy)]
+pub struct EclipseShadow {
+    light_radius: f32,
+    occluders: [(Vec3, f32); MAX_OCCLUDERS],
+    count: usize,
+}
+
+impl EclipseShadow {
+    // Elige entre `bodies` (centro, radio) los tapadores del cuerpo centrado en `receiver`. Se
+    // descartan el propio cuerpo y los que están más lejos de la luz que él, que no pueden
+    // quedar en medio. None si no queda ninguno.
+    pub fn new(receiver: Vec3, bodies: &[(Vec3, f32)], light_position: Vec3, light_radius: f32) -> Option<Self> {
+        let receiver_distance = (light_position - receiver).magnitude();
+        let mut candidates: Vec<(Vec3, f32)> = bodies.iter()
+            .copied()
+            .filter(|&(center, _)| (center - receiver).magnitude() > f32::EPSILON)
+            .filter(|&(center, _)| (light_position - center).magnitude() < receiver_distance)
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        candidates.sort_by(|a, b| (a.0 - receiver).magnitude().total_cmp(&(b.0 - receiver).magnitude()));
+
+        let mut occluders = [(Vec3::zeros(), 0.0); MAX_OCCLUDERS];
+        let count = candidates.len().min(MAX_OCCLUDERS);
+        occluders[..count].copy_from_slice(&candidates[..count]);
+        Some(EclipseShadow { light_radius, occluders, count })
+    }
+
+    // Fracción de la luz que llega a `point` (1 = sin eclipse, 0 = totalmente tapada)
+    pub fn visibility(&self, point: Vec3, light_position: Vec3) -> f32 {
+        let to_light = light_position - point;
+        let light_distance = to_light.magnitude();
+        if light_distance <= self.light_radius {
+            return 1.0;
+        }
+        let light_direction = to_light / light_distance;
+        let light_angle = (self.light_radius / light_distance).asin();
+
+        let mut visibility = 1.0;
+        for &(center, radius) in &self.occluders[..self.count] {
+            let to_occluder = center - point;
+            let distance = to_occluder.magnitude();
+            // Un tapador detrás del punto, más allá de la luz o que lo contiene no hace sombra
+            let alignment = dot(&to_occluder, &light_direction) / distance;
+            if distance <= radius || distance >= light_distance || alignment <= 0.0 {
+                continue;
+            }
+
+            let occluder_angle = (radius / distance).asin();
+            let separation = alignment.min(1.0).acos();
+            visibility *= 1.0 - covered_fraction(light_angle, occluder_angle, separation);
+        }
+        visibility
+    }
+}
+
+// Fracción del disco de la luz (radio angular `light_angle`) que tapa un disco de radio angular
+// `occluder_angle` cuyo centro está a `separation` del de la luz. Entre el contacto exterior y el
+// interior la transición es suave en lugar del área exacta de la intersección.
+fn covered_fraction(light_angle: f32, occluder_angle: f32, separation: f32) -> f32 {
+    let outer = light_angle + occluder_angle;
+    if separation >= outer {
+        return 0.0;
+    }
+
+    // Con los discos uno dentro del otro se tapa todo o, si el tapador es menor, su área
+    let full = (occluder_angle / light_angle).powi(2).min(1.0);
+    let inner = (light_angle - occluder_angle).abs();
+    if separation <= inner {
+        return full;
+    }
+
+    let t = (separation - inner) / (outer - inner);
+    full * (1.0 - t * t * (3.0 - 2.0 * t))
+}
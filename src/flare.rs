@@ -0,0 +1,112 @@
+use nalgebra_glm::Vec2;
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::godrays::{project_sun, sun_pixel_visible, SunOnScreen};
+use crate::scene::Scene;
+
+// Radio del resplandor respecto al del disco del Sol cuando se le mira de frente
+const GLOW_RADIUS_FACTOR: f32 = 4.0;
+const GLOW_COLOR: Color = Color::new(255, 190, 110);
+const GLOW_STRENGTH: f32 = 0.9;
+
+// Exponente con el que el resplandor y los destellos se apagan al apartar la vista del Sol
+const FACING_POWER: i32 = 4;
+
+// Muestras por lado de la rejilla con la que se mide qué parte del disco se ve
+const VISIBILITY_SAMPLES: usize = 9;
+
+// Destellos de la lente: posición en la recta del Sol al centro de la pantalla (0 = el Sol,
+// 1 = el centro, 2 = el punto simétrico), radio como fracción del alto de la pantalla y color
+const FLARE_GHOSTS: [(f32, f32, Color); 6] = [
+    (0.45, 0.020, Color::new(255, 220, 150)),
+    (0.80, 0.045, Color::new(120, 200, 120)),
+    (1.15, 0.012, Color::new(200, 200, 255)),
+    (1.40, 0.070, Color::new(90, 120, 220)),
+    (1.75, 0.030, Color::new(255, 150, 90)),
+    (2.10, 0.110, Color::new(140, 90, 200)),
+];
+const FLARE_STRENGTH: f32 = 0.6;
+
+// Post-proceso del Sol como fuente de luz: un resplandor aditivo alrededor de su posición en
+// pantalla y la cadena de destellos de la lente. Ambos se apagan según la fracción del disco que
+// queda a la vista (dentro de la pantalla y sin nada delante) y según lo de frente que lo mira la
+// cámara. Debe ir después del render, con el z-buffer de la escena.
+pub fn apply_sun_flare(framebuffer: &mut Framebuffer, scene: &Scene, camera: &Camera) {
+    let Some(sun) = project_sun(framebuffer, scene, camera) else {
+        return;
+    };
+
+    let visibility = sun_visibility(framebuffer, &sun);
+    let intensity = visibility * sun.facing.max(0.0).powi(FACING_POWER);
+    if intensity <= 0.0 {
+        return;
+    }
+
+    let sun_screen_pos = Vec2::new(sun.center.x, sun.center.y);
+    let glow_radius = sun.radius * GLOW_RADIUS_FACTOR * (0.5 + 0.5 * intensity);
+    add_disc(framebuffer, sun_screen_pos, glow_radius, GLOW_COLOR * (GLOW_STRENGTH * intensity), 2);
+    draw_lens_flare(framebuffer, sun_screen_pos, intensity);
+}
+
+// Destellos de la lente: círculos translúcidos a lo largo de la recta que va de `sun_screen_pos`
+// al centro de la pantalla y sigue al otro lado, con su brillo multiplicado por `intensity` (0 a 1)
+pub fn draw_lens_flare(framebuffer: &mut Framebuffer, sun_screen_pos: Vec2, intensity: f32) {
+    let screen_center = Vec2::new(framebuffer.width as f32 / 2.0, framebuffer.height as f32 / 2.0);
+    let axis = screen_center - sun_screen_pos;
+    let height = framebuffer.height as f32;
+
+    for (offset, radius, color) in FLARE_GHOSTS {
+        let position = sun_screen_pos + axis * offset;
+        add_disc(framebuffer, position, radius * height, color * (FLARE_STRENGTH * intensity), 1);
+    }
+}
+
+// Fracción (0 a 1) de una rejilla de puntos sobre el disco del Sol que cae dentro de la pantalla
+// y no está tapada por otro objeto
+fn sun_visibility(framebuffer: &Framebuffer, sun: &SunOnScreen) -> f32 {
+    let mut inside = 0;
+    let mut visible = 0;
+    for row in 0..VISIBILITY_SAMPLES {
+        for column in 0..VISIBILITY_SAMPLES {
+            let offset = Vec2::new(column as f32, row as f32) / (VISIBILITY_SAMPLES - 1) as f32 * 2.0 - Vec2::new(1.0, 1.0);
+            if offset.magnitude() > 1.0 {
+                continue;
+            }
+            inside += 1;
+
+            let (x, y) = (sun.center.x + offset.x * sun.radius, sun.center.y + offset.y * sun.radius);
+            if x < 0.0 || y < 0.0 || x >= framebuffer.width as f32 || y >= framebuffer.height as f32 {
+                continue;
+            }
+            if sun_pixel_visible(framebuffer.zbuffer[y as usize * framebuffer.width + x as usize], sun.front_depth) {
+                visible += 1;
+            }
+        }
+    }
+    visible as f32 / inside as f32
+}
+
+// Suma `color` en un disco de radio `radius` que se desvanece hacia el borde como
+// (1 - d/radius)^`power`, encima de todo y sin prueba de profundidad
+fn add_disc(framebuffer: &mut Framebuffer, center: Vec2, radius: f32, color: Color, power: i32) {
+    if radius <= 0.0 {
+        return;
+    }
+    let min_x = (center.x - radius).floor().max(0.0) as usize;
+    let min_y = (center.y - radius).floor().max(0.0) as usize;
+    let max_x = ((center.x + radius).ceil().max(0.0) as usize).min(framebuffer.width);
+    let max_y = ((center.y + radius).ceil().max(0.0) as usize).min(framebuffer.height);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let distance = (x as f32 + 0.5 - center.x).hypot(y as f32 + 0.5 - center.y);
+            let falloff = 1.0 - distance / radius;
+            if falloff > 0.0 {
+                let index = y * framebuffer.width + x;
+                framebuffer.buffer[index] = (Color::from_hex(framebuffer.buffer[index]) + color * falloff.powi(power)).to_hex();
+            }
+        }
+    }
+}
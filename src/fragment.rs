@@ -1,5 +1,5 @@
 
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 pub struct Fragment {
@@ -9,6 +9,9 @@ pub struct Fragment {
     pub normal: Vec3,
     pub intensity: f32,
     pub vertex_position: Vec3,
+    pub world_position: Vec3,
+    pub tex_coords: Vec2,
+    pub tangent: Vec4,  // Tangente en mundo con el sentido de la bitangente en `w`; cero si no hay
 }
 
 impl Fragment {
@@ -19,7 +22,10 @@ impl Fragment {
             depth,
             normal,
             intensity,
-            vertex_position
+            vertex_position,
+            world_position: vertex_position,
+            tex_coords: Vec2::new(0.0, 0.0),
+            tangent: Vec4::zeros(),
         }
     }
 }
\ No newline at end of file
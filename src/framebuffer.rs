@@ -1,3 +1,10 @@
+use image::{ImageResult, Rgb, RgbImage};
+
+use crate::color::Color;
+use crate::font::{glyph, GLYPH_ADVANCE, GLYPH_HEIGHT, LINE_HEIGHT};
+
+// Reducción de resolución (en cada eje) con la que se calcula el resplandor de `apply_bloom`
+const BLOOM_DOWNSCALE: usize = 4;
 
 pub struct Framebuffer {
     pub width: usize,
@@ -20,10 +27,29 @@ impl Framebuffer {
         }
     }
 
+    // Framebuffer interno para supersampling: `factor` veces el ancho y el alto de la ventana, con
+    // el z-buffer del mismo tamaño. Se reduce a la ventana con `antialias::resolve_ssaa`.
+    pub fn supersampled(width: usize, height: usize, factor: usize) -> Self {
+        Framebuffer::new(width * factor, height * factor)
+    }
+
+    // Cambia el tamaño del framebuffer; el contenido y el z-buffer quedan vacíos
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.buffer = vec![self.background_color; width * height];
+        self.zbuffer = vec![f32::INFINITY; width * height];
+    }
+
     pub fn clear(&mut self) {
         for pixel in self.buffer.iter_mut() {
             *pixel = self.background_color;
         }
+        self.clear_depth();
+    }
+
+    // Vacía solo el z-buffer: lo que se dibuje después queda delante de los colores actuales
+    pub fn clear_depth(&mut self) {
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
         }
@@ -40,6 +66,32 @@ impl Framebuffer {
         }
     }
 
+    // Mezcla el color actual con el del buffer según `alpha` (0 = invisible, 1 = opaco). Pasa la
+    // prueba de profundidad pero no escribe en el z-buffer, para que lo translúcido no tape lo
+    // que se dibuje detrás más tarde.
+    pub fn blend_point(&mut self, x: usize, y: usize, depth: f32, alpha: f32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+
+            if self.zbuffer[index] > depth {
+                let behind = Color::from_hex(self.buffer[index]);
+                self.buffer[index] = behind.blend(Color::from_hex(self.current_color), alpha.clamp(0.0, 1.0)).to_hex();
+            }
+        }
+    }
+
+    // Suma `color` al del buffer (mezcla aditiva, saturando en blanco). Como `blend_point`, pasa
+    // la prueba de profundidad sin escribir en el z-buffer.
+    pub fn add_point(&mut self, x: usize, y: usize, depth: f32, color: Color) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+
+            if self.zbuffer[index] > depth {
+                self.buffer[index] = (Color::from_hex(self.buffer[index]) + color).to_hex();
+            }
+        }
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
@@ -54,5 +106,148 @@ impl Framebuffer {
             self.buffer[index] = color;
         }
     }
-    
+
+    // Escribe `text` con la fuente de 5x7 con la esquina superior izquierda en (x, y), encima de
+    // todo y sin prueba de profundidad. '\n' empieza una línea nueva; lo que quede fuera se recorta.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: u32) {
+        for (line_index, line) in text.lines().enumerate() {
+            let top = y + line_index * LINE_HEIGHT;
+            for (char_index, c) in line.chars().enumerate() {
+                let left = x + char_index * GLYPH_ADVANCE;
+                for (column, bits) in glyph(c).iter().enumerate() {
+                    for row in 0..GLYPH_HEIGHT {
+                        if bits & (1 << row) != 0 {
+                            self.set_pixel(left + column, top + row, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Guarda el contenido actual como PNG; cada píxel del buffer es 0x00RRGGBB
+    pub fn save_png(&self, path: &str) -> ImageResult<()> {
+        let image = RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let pixel = self.buffer[y as usize * self.width + x as usize];
+            Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+        });
+        image.save(path)
+    }
+
+    // Resplandor alrededor de lo más brillante de la imagen ya compuesta: toma lo que supera
+    // `threshold` (el mayor de sus canales, de 0 a 1, para que también brillen los rojos y
+    // naranjas saturados como el Sol o la lava), lo desenfoca con un kernel gaussiano separable de
+    // `radius` píxeles y suma el resultado multiplicado por `intensity`. Si ningún píxel supera
+    // el umbral no se desenfoca nada.
+    pub fn apply_bloom(&mut self, threshold: f32, radius: usize, intensity: f32) {
+        // El resplandor es borroso de todos modos: se calcula a un cuarto de resolución
+        let (width, height) = (self.width.div_ceil(BLOOM_DOWNSCALE), self.height.div_ceil(BLOOM_DOWNSCALE));
+        let radius = radius / BLOOM_DOWNSCALE;
+
+        // Solo pasa la parte de cada color por encima del umbral, para que el borde sea suave.
+        // Cada píxel reducido acumula el promedio de su bloque.
+        let mut bright = vec![[0.0f32; 3]; width * height];
+        let mut any_bright = false;
+        let block_weight = 1.0 / (BLOOM_DOWNSCALE * BLOOM_DOWNSCALE) as f32;
+        for (index, &pixel) in self.buffer.iter().enumerate() {
+            let rgb = [(pixel >> 16 & 0xFF) as f32, (pixel >> 8 & 0xFF) as f32, (pixel & 0xFF) as f32];
+            let brightness = rgb[0].max(rgb[1]).max(rgb[2]) / 255.0;
+            if brightness > threshold {
+                let factor = (brightness - threshold) / brightness * block_weight;
+                let (x, y) = (index % self.width / BLOOM_DOWNSCALE, index / self.width / BLOOM_DOWNSCALE);
+                for (total, channel) in bright[y * width + x].iter_mut().zip(rgb) {
+                    *total += channel * factor;
+                }
+                any_bright = true;
+            }
+        }
+        if !any_bright || radius == 0 {
+            return;
+        }
+
+        let sigma = radius as f32 / 2.0;
+        let weights: Vec<f32> = (0..=radius).map(|offset| (-((offset * offset) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+        let weights: Vec<f32> = weights.iter().map(|weight| weight / total).collect();
+
+        // Pasada horizontal y luego vertical; fuera de la imagen se repite el borde
+        let blur = |source: &[[f32; 3]], step: (usize, usize)| {
+            let mut blurred = vec![[0.0f32; 3]; source.len()];
+            for y in 0..height {
+                for x in 0..width {
+                    let mut sum = source[y * width + x].map(|value| value * weights[0]);
+                    for (offset, weight) in weights.iter().enumerate().skip(1) {
+                        let before = source[y.saturating_sub(offset * step.1) * width + x.saturating_sub(offset * step.0)];
+                        let after = source[(y + offset * step.1).min(height - 1) * width + (x + offset * step.0).min(width - 1)];
+                        for channel in 0..3 {
+                            sum[channel] += (before[channel] + after[channel]) * weight;
+                        }
+                    }
+                    blurred[y * width + x] = sum;
+                }
+            }
+            blurred
+        };
+        let glow = blur(&blur(&bright, (1, 0)), (0, 1));
+
+        // Composición aditiva sobre la imagen a resolución completa
+        for (index, pixel) in self.buffer.iter_mut().enumerate() {
+            let (x, y) = (index % self.width / BLOOM_DOWNSCALE, index / self.width / BLOOM_DOWNSCALE);
+            let [r, g, b] = glow[y * width + x].map(|channel| (channel * intensity).min(255.0) as u8);
+            *pixel = (Color::from_hex(*pixel) + Color::new(r, g, b)).to_hex();
+        }
+    }
+
+    // Segmento de (x0, y0) a (x1, y1) dibujado encima de todo, sin prueba de profundidad
+    // (Bresenham). Los extremos pueden quedar fuera de la pantalla.
+    pub fn line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, color: u32) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+
+        loop {
+            self.set_border_pixel(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    // Copia otro framebuffer en la posición (x, y), rodeado de un borde de 1 píxel
+    pub fn blit(&mut self, source: &Framebuffer, x: usize, y: usize, border_color: u32) {
+        for sy in 0..source.height {
+            for sx in 0..source.width {
+                self.set_pixel(x + sx, y + sy, source.buffer[sy * source.width + sx]);
+            }
+        }
+
+        let (left, top) = (x as isize - 1, y as isize - 1);
+        let (right, bottom) = ((x + source.width) as isize, (y + source.height) as isize);
+        for bx in left..=right {
+            self.set_border_pixel(bx, top, border_color);
+            self.set_border_pixel(bx, bottom, border_color);
+        }
+        for by in top..=bottom {
+            self.set_border_pixel(left, by, border_color);
+            self.set_border_pixel(right, by, border_color);
+        }
+    }
+
+    fn set_border_pixel(&mut self, x: isize, y: isize, color: u32) {
+        if x >= 0 && y >= 0 {
+            self.set_pixel(x as usize, y as usize, color);
+        }
+    }
 }
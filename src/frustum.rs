@@ -0,0 +1,124 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::line::line;
+use crate::math::{create_perspective_matrix, create_view_matrix, create_viewport_matrix, world_to_screen};
+use crate::vertex::Vertex;
+
+// El plano lejano real está a 1000 unidades; para dibujarlo se acorta a esta distancia del ojo
+const FRUSTUM_DISPLAY_DISTANCE: f32 = 40.0;
+
+// Segmentos que no caben en pantalla ni de lejos se descartan en vez de rasterizarlos
+const MAX_SCREEN_COORDINATE: f32 = 10_000.0;
+
+// Esquinas del frustum de `camera` para un framebuffer de `width` x `height`: las cuatro del
+// plano cercano y luego las cuatro del lejano, en el orden (-1,-1), (1,-1), (1,1), (-1,1) de NDC.
+// Se obtienen desproyectando el cubo NDC con la inversa de la vista-proyección.
+pub fn frustum_corners(camera: &Camera, width: f32, height: f32) -> [Vec3; 8] {
+    let view_projection = create_perspective_matrix(width, height, camera.fov) * create_view_matrix(camera.eye, camera.center, camera.up);
+    let inverse = view_projection.try_inverse().unwrap_or(Mat4::identity());
+
+    let mut corners = [Vec3::zeros(); 8];
+    for (index, corner) in corners.iter_mut().enumerate() {
+        let x = if index % 4 == 1 || index % 4 == 2 { 1.0 } else { -1.0 };
+        let y = if index % 4 >= 2 { 1.0 } else { -1.0 };
+        let z = if index < 4 { -1.0 } else { 1.0 };
+
+        let world = inverse * Vec4::new(x, y, z, 1.0);
+        *corner = Vec3::new(world.x, world.y, world.z) / world.w;
+    }
+
+    for far in corners.iter_mut().skip(4) {
+        let direction = *far - camera.eye;
+        if direction.magnitude() > FRUSTUM_DISPLAY_DISTANCE {
+            *far = camera.eye + direction.normalize() * FRUSTUM_DISPLAY_DISTANCE;
+        }
+    }
+
+    corners
+}
+
+// Prueba de la esfera envolvente: true si la esfera de centro `center` y radio `radius` queda
+// entera fuera de alguno de los seis planos del frustum de `view_projection`. Cada plano es la
+// fila w de la matriz más o menos la de x, y o z, así que la distancia del centro a él sale de
+// sus coordenadas de recorte y solo hace falta normalizarla con la longitud de la normal.
+pub fn sphere_outside_frustum(view_projection: &Mat4, center: Vec3, radius: f32) -> bool {
+    let clip = view_projection * Vec4::new(center.x, center.y, center.z, 1.0);
+
+    (0..3).any(|axis| {
+        [1.0, -1.0].iter().any(|&sign| {
+            let plane = view_projection.row(3) + view_projection.row(axis) * sign;
+            let normal_length = Vec3::new(plane[0], plane[1], plane[2]).magnitude();
+            clip.w + clip[axis] * sign < -radius * normal_length
+        })
+    })
+}
+
+// Lo contrario de lo anterior: true si la esfera queda entera dentro de los seis planos, de modo
+// que recortar sus triángulos contra el frustum no cambiaría ninguno
+pub fn sphere_inside_frustum(view_projection: &Mat4, center: Vec3, radius: f32) -> bool {
+    let clip = view_projection * Vec4::new(center.x, center.y, center.z, 1.0);
+
+    (0..3).all(|axis| {
+        [1.0, -1.0].iter().all(|&sign| {
+            let plane = view_projection.row(3) + view_projection.row(axis) * sign;
+            let normal_length = Vec3::new(plane[0], plane[1], plane[2]).magnitude();
+            clip.w + clip[axis] * sign >= radius * normal_length
+        })
+    })
+}
+
+// Prueba de la caja envolvente: true si la caja (min, max) en coordenadas del mundo queda entera
+// fuera de alguno de los seis planos del frustum de `view_projection`, es decir, si sus ocho
+// esquinas están al otro lado del mismo plano
+pub fn aabb_outside_frustum(view_projection: &Mat4, (min, max): (Vec3, Vec3)) -> bool {
+    let corners: Vec<Vec4> = (0..8).map(|corner| view_projection * Vec4::new(
+        if corner & 1 == 0 { min.x } else { max.x },
+        if corner & 2 == 0 { min.y } else { max.y },
+        if corner & 4 == 0 { min.z } else { max.z },
+        1.0,
+    )).collect();
+
+    (0..3).any(|axis| {
+        [1.0, -1.0].iter().any(|&sign| corners.iter().all(|clip| clip.w + clip[axis] * sign < 0.0))
+    })
+}
+
+// Dibuja las 12 aristas del frustum vistas desde `view_camera`, ocultas tras la geometría
+pub fn draw_frustum(framebuffer: &mut Framebuffer, corners: &[Vec3; 8], view_camera: &Camera, color: u32) {
+    let view_matrix = create_view_matrix(view_camera.eye, view_camera.center, view_camera.up);
+    let projection_matrix = create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32, view_camera.fov);
+    let viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+
+    let edges = [
+        (0, 1), (1, 2), (2, 3), (3, 0),  // Plano cercano
+        (4, 5), (5, 6), (6, 7), (7, 4),  // Plano lejano
+        (0, 4), (1, 5), (2, 6), (3, 7),  // Aristas laterales
+    ];
+
+    framebuffer.set_current_color(color);
+    for (a, b) in edges {
+        let (Some(start), Some(end)) = (
+            world_to_screen(corners[a], &view_matrix, &projection_matrix, &viewport_matrix),
+            world_to_screen(corners[b], &view_matrix, &projection_matrix, &viewport_matrix),
+        ) else {
+            continue;
+        };
+        if [start.x, start.y, end.x, end.y].iter().any(|value| value.abs() > MAX_SCREEN_COORDINATE) {
+            continue;
+        }
+
+        let mut vertex_a = Vertex::new_with_color(corners[a], Color::from_hex(color));
+        let mut vertex_b = Vertex::new_with_color(corners[b], Color::from_hex(color));
+        vertex_a.set_transformed(start, Vec3::zeros());
+        vertex_b.set_transformed(end, Vec3::zeros());
+
+        for fragment in line(&vertex_a, &vertex_b) {
+            if fragment.position.x >= 0.0 && fragment.position.y >= 0.0 {
+                framebuffer.point(fragment.position.x as usize, fragment.position.y as usize, fragment.depth);
+            }
+        }
+    }
+}
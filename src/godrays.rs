@@ -0,0 +1,153 @@
+use nalgebra_glm::Vec3;
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::scene::Scene;
+use crate::shadow::STAR_SHADER_MODE;
+use crate::math::{create_perspective_matrix, create_view_matrix, create_viewport_matrix, world_to_screen};
+use crate::obj::SPHERE_MODEL_RADIUS;
+use crate::planet_positions;
+
+// Los rayos son borrosos por naturaleza: se calculan a media resolución para cuartear el coste
+const RAY_DOWNSCALE: usize = 2;
+
+// Parámetros del desenfoque radial; más muestras dan rayos más largos y suaves pero cuestan más
+pub struct GodRays {
+    pub samples: usize,
+    pub decay: f32,     // Atenuación de cada muestra respecto a la anterior
+    pub density: f32,   // Fracción del camino hacia el Sol que se recorre (1.0 = hasta el centro)
+    pub weight: f32,
+    pub exposure: f32,
+}
+
+impl Default for GodRays {
+    fn default() -> Self {
+        GodRays {
+            samples: 32,
+            decay: 0.95,
+            density: 0.8,
+            weight: 0.25,
+            exposure: 0.6,
+        }
+    }
+}
+
+// El Sol (la primera estrella de la escena) proyectado en la pantalla de `framebuffer`
+pub struct SunOnScreen {
+    pub center: Vec3,      // Centro en píxeles; z es su profundidad
+    pub radius: f32,       // Radio aparente en píxeles
+    pub front_depth: f32,  // Profundidad del punto del Sol más cercano a la cámara
+    pub facing: f32,       // Coseno del ángulo entre la dirección de la vista y la del Sol
+}
+
+// Proyecta el Sol con `camera`; None si no hay estrella o si su centro queda detrás de la cámara.
+// El resultado puede estar fuera de la pantalla.
+pub fn project_sun(framebuffer: &Framebuffer, scene: &Scene, camera: &Camera) -> Option<SunOnScreen> {
+    let (star, position) = scene.planets.iter()
+        .zip(planet_positions(scene))
+        .find(|(planet, _)| planet.shader_mode == STAR_SHADER_MODE)?;
+
+    let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+    let projection_matrix = create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32, camera.fov);
+    let viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+    let project = |point: Vec3| world_to_screen(point, &view_matrix, &projection_matrix, &viewport_matrix);
+
+    let radius = scene.system_scale.scale(star) * SPHERE_MODEL_RADIUS;
+    let to_camera = (camera.eye - position).normalize();
+    let right = camera.basis_change(&Vec3::new(1.0, 0.0, 0.0));
+    let center = project(position)?;
+    let edge = project(position + right * radius)?;
+    let front = project(position + to_camera * radius)?;
+
+    Some(SunOnScreen {
+        center,
+        radius: (edge.x - center.x).hypot(edge.y - center.y) + 1.0,
+        front_depth: front.z,
+        facing: camera.forward().dot(&-to_camera),
+    })
+}
+
+// Un píxel del disco del Sol está a la vista si lo que hay en el z-buffer es el propio Sol (a su
+// profundidad o más lejos) y no el fondo vacío
+pub fn sun_pixel_visible(depth: f32, front_depth: f32) -> bool {
+    depth >= front_depth - 1e-4 && depth < 1.0
+}
+
+// Post-proceso de rayos crepusculares: toma los píxeles visibles del Sol y los arrastra hacia
+// fuera desde su posición en pantalla, sumando el resultado a la imagen. No hace nada si el Sol
+// queda fuera de la pantalla o completamente tapado.
+pub fn apply_god_rays(framebuffer: &mut Framebuffer, scene: &Scene, camera: &Camera, settings: &GodRays) {
+    let Some(SunOnScreen { center, radius: radius_px, front_depth, .. }) = project_sun(framebuffer, scene, camera) else {
+        return;
+    };
+
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+    if center.x + radius_px < 0.0 || center.y + radius_px < 0.0 || center.x - radius_px >= width || center.y - radius_px >= height {
+        return;
+    }
+
+    // Máscara a media resolución con el color de los píxeles del Sol que no tapa ningún otro objeto
+    let mask_width = framebuffer.width.div_ceil(RAY_DOWNSCALE);
+    let mask_height = framebuffer.height.div_ceil(RAY_DOWNSCALE);
+    let mut mask = vec![[0.0f32; 3]; mask_width * mask_height];
+    let mut visible = false;
+    for mask_y in 0..mask_height {
+        for mask_x in 0..mask_width {
+            let (x, y) = (mask_x * RAY_DOWNSCALE, mask_y * RAY_DOWNSCALE);
+            if (x as f32 - center.x).hypot(y as f32 - center.y) > radius_px {
+                continue;
+            }
+
+            let index = y * framebuffer.width + x;
+            let depth = framebuffer.zbuffer[index];
+            if sun_pixel_visible(depth, front_depth) {
+                let hex = framebuffer.buffer[index];
+                mask[mask_y * mask_width + mask_x] = [(hex >> 16 & 0xFF) as f32, (hex >> 8 & 0xFF) as f32, (hex & 0xFF) as f32];
+                visible = true;
+            }
+        }
+    }
+    if !visible {
+        return;
+    }
+
+    // Cada píxel recorre el camino hacia el Sol acumulando la máscara con atenuación exponencial
+    let samples = settings.samples.max(1);
+    let (sun_x, sun_y) = (center.x / RAY_DOWNSCALE as f32, center.y / RAY_DOWNSCALE as f32);
+    let mut rays = vec![Color::black(); mask_width * mask_height];
+    for mask_y in 0..mask_height {
+        for mask_x in 0..mask_width {
+            let step_x = (sun_x - mask_x as f32) * settings.density / samples as f32;
+            let step_y = (sun_y - mask_y as f32) * settings.density / samples as f32;
+            let (mut sample_x, mut sample_y) = (mask_x as f32, mask_y as f32);
+            let mut illumination = settings.weight * settings.exposure;
+            let mut sum = [0.0f32; 3];
+
+            for _ in 0..samples {
+                sample_x += step_x;
+                sample_y += step_y;
+                if sample_x >= 0.0 && sample_y >= 0.0 && sample_x < mask_width as f32 && sample_y < mask_height as f32 {
+                    let sample = mask[sample_y as usize * mask_width + sample_x as usize];
+                    for (total, value) in sum.iter_mut().zip(sample) {
+                        *total += value * illumination;
+                    }
+                }
+                illumination *= settings.decay;
+            }
+
+            let [r, g, b] = sum.map(|value| value.min(255.0) as u8);
+            rays[mask_y * mask_width + mask_x] = Color::new(r, g, b);
+        }
+    }
+
+    // Composición aditiva sobre la imagen a resolución completa
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let index = y * framebuffer.width + x;
+            let ray = &rays[(y / RAY_DOWNSCALE) * mask_width + x / RAY_DOWNSCALE];
+            framebuffer.buffer[index] = Color::from_hex(framebuffer.buffer[index]).blend_add(ray).to_hex();
+        }
+    }
+}
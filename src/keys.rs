@@ -0,0 +1,266 @@
+use minifb::Key;
+
+// Tecla de cada acción del teclado. `Default` es la distribución original (pensada para QWERTY);
+// `keys.ron` puede cambiar cualquiera de ellas (ver `config::load_key_bindings`). Una misma tecla
+// puede servir para acciones de modos de cámara distintos, como W en órbita y en vuelo libre.
+pub struct KeyBindings {
+    // Cámara en órbita
+    pub orbit_left: Key,
+    pub orbit_right: Key,
+    pub orbit_up: Key,
+    pub orbit_down: Key,
+    pub zoom_in: Key,
+    pub zoom_out: Key,
+    pub fov_narrow: Key,
+    pub fov_wide: Key,
+
+    // Vuelo libre
+    pub move_forward: Key,
+    pub move_backward: Key,
+    pub look_left: Key,
+    pub look_right: Key,
+    pub look_up: Key,
+    pub look_down: Key,
+
+    // Desplazamiento en los dos modos
+    pub move_left: Key,
+    pub move_right: Key,
+    pub move_up: Key,
+    pub move_down: Key,
+    pub roll_left: Key,
+    pub roll_right: Key,
+    pub reset_roll: Key,
+
+    // Saltos a los destinos de warp
+    pub warp_1: Key,
+    pub warp_2: Key,
+    pub warp_3: Key,
+    pub warp_4: Key,
+    pub autopilot: Key,
+
+    // Simulación y planetas
+    pub pause: Key,
+    pub slower: Key,
+    pub faster: Key,
+    pub nbody: Key,
+    pub next_planet: Key,
+    pub follow: Key,
+    pub noise_zoom_in: Key,
+    pub noise_zoom_out: Key,
+    pub real_scale: Key,
+    pub exaggerate_more: Key,
+    pub exaggerate_less: Key,
+    pub camera_mode: Key,
+    pub picking: Key,
+
+    // Vistas y render
+    pub inset_view: Key,
+    pub overview: Key,
+    pub shadows: Key,
+    pub eclipses: Key,
+    pub spotlight: Key,
+    pub god_rays: Key,
+    pub background_sampling: Key,
+    pub debug_normals: Key,
+    pub orbits: Key,
+    pub backface_culling: Key,
+    pub flat_shading: Key,
+    pub wireframe: Key,
+    pub anti_alias: Key,
+    pub bloom: Key,
+    pub labels: Key,
+    pub lens_flare: Key,
+    pub minimap: Key,
+    pub tile_heatmap: Key,
+    pub brightness_up: Key,
+    pub brightness_down: Key,
+
+    // Archivos
+    pub save_scene: Key,
+    pub reload_planets: Key,
+    pub screenshot: Key,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            orbit_left: Key::Left,
+            orbit_right: Key::Right,
+            orbit_up: Key::W,
+            orbit_down: Key::S,
+            zoom_in: Key::Up,
+            zoom_out: Key::Down,
+            fov_narrow: Key::LeftBracket,
+            fov_wide: Key::RightBracket,
+            move_forward: Key::W,
+            move_backward: Key::S,
+            look_left: Key::Left,
+            look_right: Key::Right,
+            look_up: Key::Up,
+            look_down: Key::Down,
+            move_left: Key::A,
+            move_right: Key::D,
+            move_up: Key::Q,
+            move_down: Key::E,
+            roll_left: Key::C,
+            roll_right: Key::V,
+            reset_roll: Key::F2,
+            warp_1: Key::Key1,
+            warp_2: Key::Key2,
+            warp_3: Key::Key3,
+            warp_4: Key::Key4,
+            autopilot: Key::F11,
+            pause: Key::Space,
+            slower: Key::Comma,
+            faster: Key::Period,
+            nbody: Key::G,
+            next_planet: Key::Tab,
+            follow: Key::F,
+            noise_zoom_in: Key::X,
+            noise_zoom_out: Key::Z,
+            real_scale: Key::F12,
+            exaggerate_more: Key::Apostrophe,
+            exaggerate_less: Key::Semicolon,
+            camera_mode: Key::M,
+            picking: Key::K,
+            inset_view: Key::I,
+            overview: Key::O,
+            shadows: Key::H,
+            eclipses: Key::F3,
+            spotlight: Key::F1,
+            god_rays: Key::F4,
+            background_sampling: Key::B,
+            debug_normals: Key::N,
+            orbits: Key::L,
+            backface_culling: Key::U,
+            flat_shading: Key::J,
+            wireframe: Key::Y,
+            anti_alias: Key::F6,
+            bloom: Key::F7,
+            labels: Key::F8,
+            lens_flare: Key::F9,
+            minimap: Key::F10,
+            tile_heatmap: Key::T,
+            brightness_up: Key::Equal,
+            brightness_down: Key::Minus,
+            save_scene: Key::F5,
+            reload_planets: Key::R,
+            screenshot: Key::P,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn warp_keys(&self) -> [Key; 4] {
+        [self.warp_1, self.warp_2, self.warp_3, self.warp_4]
+    }
+
+    // Tecla de la acción `action`, que se llama igual que su campo; None si no existe
+    pub fn binding_mut(&mut self, action: &str) -> Option<&mut Key> {
+        let key = match action {
+            "orbit_left" => &mut self.orbit_left,
+            "orbit_right" => &mut self.orbit_right,
+            "orbit_up" => &mut self.orbit_up,
+            "orbit_down" => &mut self.orbit_down,
+            "zoom_in" => &mut self.zoom_in,
+            "zoom_out" => &mut self.zoom_out,
+            "fov_narrow" => &mut self.fov_narrow,
+            "fov_wide" => &mut self.fov_wide,
+            "move_forward" => &mut self.move_forward,
+            "move_backward" => &mut self.move_backward,
+            "look_left" => &mut self.look_left,
+            "look_right" => &mut self.look_right,
+            "look_up" => &mut self.look_up,
+            "look_down" => &mut self.look_down,
+            "move_left" => &mut self.move_left,
+            "move_right" => &mut self.move_right,
+            "move_up" => &mut self.move_up,
+            "move_down" => &mut self.move_down,
+            "roll_left" => &mut self.roll_left,
+            "roll_right" => &mut self.roll_right,
+            "reset_roll" => &mut self.reset_roll,
+            "warp_1" => &mut self.warp_1,
+            "warp_2" => &mut self.warp_2,
+            "warp_3" => &mut self.warp_3,
+            "warp_4" => &mut self.warp_4,
+            "autopilot" => &mut self.autopilot,
+            "pause" => &mut self.pause,
+            "slower" => &mut self.slower,
+            "faster" => &mut self.faster,
+            "nbody" => &mut self.nbody,
+            "next_planet" => &mut self.next_planet,
+            "follow" => &mut self.follow,
+            "noise_zoom_in" => &mut self.noise_zoom_in,
+            "noise_zoom_out" => &mut self.noise_zoom_out,
+            "real_scale" => &mut self.real_scale,
+            "exaggerate_more" => &mut self.exaggerate_more,
+            "exaggerate_less" => &mut self.exaggerate_less,
+            "camera_mode" => &mut self.camera_mode,
+            "picking" => &mut self.picking,
+            "inset_view" => &mut self.inset_view,
+            "overview" => &mut self.overview,
+            "shadows" => &mut self.shadows,
+            "eclipses" => &mut self.eclipses,
+            "spotlight" => &mut self.spotlight,
+            "god_rays" => &mut self.god_rays,
+            "background_sampling" => &mut self.background_sampling,
+            "debug_normals" => &mut self.debug_normals,
+            "orbits" => &mut self.orbits,
+            "backface_culling" => &mut self.backface_culling,
+            "flat_shading" => &mut self.flat_shading,
+            "wireframe" => &mut self.wireframe,
+            "anti_alias" => &mut self.anti_alias,
+            "bloom" => &mut self.bloom,
+            "labels" => &mut self.labels,
+            "lens_flare" => &mut self.lens_flare,
+            "minimap" => &mut self.minimap,
+            "tile_heatmap" => &mut self.tile_heatmap,
+            "brightness_up" => &mut self.brightness_up,
+            "brightness_down" => &mut self.brightness_down,
+            "save_scene" => &mut self.save_scene,
+            "reload_planets" => &mut self.reload_planets,
+            "screenshot" => &mut self.screenshot,
+            _ => return None,
+        };
+        Some(key)
+    }
+}
+
+// Nombres de las teclas: los de las variantes de `minifb::Key`
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("Key0", Key::Key0), ("Key1", Key::Key1), ("Key2", Key::Key2), ("Key3", Key::Key3),
+    ("Key4", Key::Key4), ("Key5", Key::Key5), ("Key6", Key::Key6), ("Key7", Key::Key7),
+    ("Key8", Key::Key8), ("Key9", Key::Key9), ("A", Key::A), ("B", Key::B), ("C", Key::C),
+    ("D", Key::D), ("E", Key::E), ("F", Key::F), ("G", Key::G), ("H", Key::H), ("I", Key::I),
+    ("J", Key::J), ("K", Key::K), ("L", Key::L), ("M", Key::M), ("N", Key::N), ("O", Key::O),
+    ("P", Key::P), ("Q", Key::Q), ("R", Key::R), ("S", Key::S), ("T", Key::T), ("U", Key::U),
+    ("V", Key::V), ("W", Key::W), ("X", Key::X), ("Y", Key::Y), ("Z", Key::Z), ("F1", Key::F1),
+    ("F2", Key::F2), ("F3", Key::F3), ("F4", Key::F4), ("F5", Key::F5), ("F6", Key::F6),
+    ("F7", Key::F7), ("F8", Key::F8), ("F9", Key::F9), ("F10", Key::F10), ("F11", Key::F11),
+    ("F12", Key::F12), ("F13", Key::F13), ("F14", Key::F14), ("F15", Key::F15), ("Down", Key::Down),
+    ("Left", Key::Left), ("Right", Key::Right), ("Up", Key::Up), ("Apostrophe", Key::Apostrophe),
+    ("Backquote", Key::Backquote), ("Backslash", Key::Backslash), ("Comma", Key::Comma),
+    ("Equal", Key::Equal), ("LeftBracket", Key::LeftBracket), ("Minus", Key::Minus),
+    ("Period", Key::Period), ("RightBracket", Key::RightBracket), ("Semicolon", Key::Semicolon),
+    ("Slash", Key::Slash), ("Backspace", Key::Backspace), ("Delete", Key::Delete),
+    ("End", Key::End), ("Enter", Key::Enter), ("Escape", Key::Escape), ("Home", Key::Home),
+    ("Insert", Key::Insert), ("Menu", Key::Menu), ("PageDown", Key::PageDown),
+    ("PageUp", Key::PageUp), ("Pause", Key::Pause), ("Space", Key::Space), ("Tab", Key::Tab),
+    ("NumLock", Key::NumLock), ("CapsLock", Key::CapsLock), ("ScrollLock", Key::ScrollLock),
+    ("LeftShift", Key::LeftShift), ("RightShift", Key::RightShift), ("LeftCtrl", Key::LeftCtrl),
+    ("RightCtrl", Key::RightCtrl), ("NumPad0", Key::NumPad0), ("NumPad1", Key::NumPad1),
+    ("NumPad2", Key::NumPad2), ("NumPad3", Key::NumPad3), ("NumPad4", Key::NumPad4),
+    ("NumPad5", Key::NumPad5), ("NumPad6", Key::NumPad6), ("NumPad7", Key::NumPad7),
+    ("NumPad8", Key::NumPad8), ("NumPad9", Key::NumPad9), ("NumPadDot", Key::NumPadDot),
+    ("NumPadSlash", Key::NumPadSlash), ("NumPadAsterisk", Key::NumPadAsterisk),
+    ("NumPadMinus", Key::NumPadMinus), ("NumPadPlus", Key::NumPadPlus),
+    ("NumPadEnter", Key::NumPadEnter), ("LeftAlt", Key::LeftAlt), ("RightAlt", Key::RightAlt),
+    ("LeftSuper", Key::LeftSuper), ("RightSuper", Key::RightSuper),
+];
+
+// Tecla a partir de su nombre ("A", "Key1", "F5", "Left", "Space"...), sin distinguir mayúsculas
+pub fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES.iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
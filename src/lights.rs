@@ -0,0 +1,64 @@
+use nalgebra_glm::Vec3;
+
+use crate::scene::Scene;
+use crate::shadow::{ShadowMap, STAR_SHADER_MODE, build_shadow_map};
+use crate::vertex::Vertex;
+use crate::planet_positions;
+
+// Color de la luz del Sol cuando la estrella no define uno propio (amarillo cálido)
+const DEFAULT_STAR_LIGHT: Vec3 = Vec3::new(1.0, 0.85, 0.4);
+
+// Rango de temperaturas en el que la aproximación de `blackbody_color` es razonable
+const MIN_STAR_TEMPERATURE: f32 = 1000.0;
+const MAX_STAR_TEMPERATURE: f32 = 40000.0;
+
+// Color (de 0 a 1, el canal más brillante a 1) de un cuerpo negro a `kelvin` grados, con el
+// ajuste de Tanner Helland a los datos de Mitchell Charity: rojo por debajo de ~3000 K, blanco
+// hacia los 6500 K y azulado por encima
+pub fn blackbody_color(kelvin: f32) -> Vec3 {
+    let t = kelvin.clamp(MIN_STAR_TEMPERATURE, MAX_STAR_TEMPERATURE) / 100.0;
+    let red = if t <= 66.0 { 255.0 } else { 329.69873 * (t - 60.0).powf(-0.13320476) };
+    let green = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12217 * (t - 60.0).powf(-0.07551485)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.0448
+    };
+    Vec3::new(red, green, blue).map(|channel| channel.clamp(0.0, 255.0) / 255.0)
+}
+
+// Color de la luz de una estrella: el de su temperatura, el suyo propio o el del Sol
+pub fn star_light_color(star_color: Option<Vec3>, star_temperature: f32) -> Vec3 {
+    if star_temperature > 0.0 {
+        blackbody_color(star_temperature)
+    } else {
+        star_color.unwrap_or(DEFAULT_STAR_LIGHT)
+    }
+}
+
+// Una estrella de la escena vista como luz puntual. La primera es la luz principal; las demás
+// (p. ej. la compañera de un sistema binario) suman su propia luz teñida de su color.
+pub struct StarLight {
+    pub position: Vec3,
+    pub color: Vec3,  // Componentes de 0 a 1
+    pub shadow_map: Option<ShadowMap>,
+}
+
+// Luces de todas las estrellas en el instante actual, con su mapa de sombras si se piden
+pub fn star_lights(scene: &Scene, with_shadows: bool, vertex_array: &[Vertex]) -> Vec<StarLight> {
+    scene.planets.iter()
+        .zip(planet_positions(scene))
+        .filter(|(planet, _)| planet.shader_mode == STAR_SHADER_MODE)
+        .map(|(planet, position)| StarLight {
+            position,
+            color: star_light_color(planet.star_color, planet.star_temperature),
+            shadow_map: with_shadows.then(|| build_shadow_map(scene, position, vertex_array)),
+        })
+        .collect()
+}
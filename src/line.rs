@@ -1,7 +1,10 @@
+use nalgebra_glm::Vec3;
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
 use crate::color::Color;
 
+// Rasteriza un segmento entre dos vértices ya transformados a pantalla (Bresenham),
+// interpolando la profundidad a lo largo del segmento
 pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let mut fragments = Vec::new();
 
@@ -20,10 +23,12 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let sy = if y0 < y1 { 1 } else { -1 };
 
     let mut err = if dx > dy { dx / 2 } else { -dy / 2 };
+    let steps = dx.max(dy).max(1) as f32;
+    let mut step = 0;
 
     loop {
-        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x) as f32;
-        fragments.push(Fragment::new(x0 as f32, y0 as f32, Color::new(255, 255, 255), z));
+        let z = start.z + (end.z - start.z) * step as f32 / steps;
+        fragments.push(Fragment::new(x0 as f32, y0 as f32, Color::new(255, 255, 255), z, Vec3::new(0.0, 0.0, 1.0), 1.0, a.position));
 
         if x0 == x1 && y0 == y1 { break; }
 
@@ -36,6 +41,7 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
             err += dx;
             y0 += sy;
         }
+        step += 1;
     }
 
     fragments
@@ -0,0 +1,193 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use std::cell::Cell;
+use std::f32::consts::PI;
+use crate::obj::SPHERE_MODEL_RADIUS;
+use crate::vertex::Vertex;
+
+// Tamaño aparente (radio / distancia) por debajo del cual se pasa al nivel siguiente: con la
+// perspectiva por defecto equivalen a unos 40, 15 y 2 píxeles de radio en pantalla. Por debajo
+// del último la esfera ya no se dibuja como malla sino como un punto de tamaño fijo, para que
+// no parpadee ni desaparezca al quedar por debajo de un píxel.
+const LOD_THRESHOLDS: [f32; 3] = [0.06, 0.02, 0.003];
+
+// Margen relativo alrededor de cada umbral: un planeta solo cambia de nivel cuando lo supera,
+// para que no alterne entre dos mallas si se queda justo en la distancia de cambio
+const LOD_HYSTERESIS: f32 = 0.15;
+
+// Con el fundido activado, banda relativa alrededor de los umbrales entre mallas en la que se
+// dibujan las dos y la más detallada aparece poco a poco encima de la otra
+const LOD_CROSSFADE_BAND: f32 = 0.15;
+
+// Niveles con malla; el siguiente es el punto
+const LOD_MESHES: usize = 3;
+
+// Paralelos y meridianos de las esferas generadas para los niveles medio y bajo
+const MEDIUM_SPHERE: (usize, usize) = (12, 16);
+const LOW_SPHERE: (usize, usize) = (6, 8);
+
+// Esfera en varios niveles de detalle: el 0 es el modelo completo y cada uno de los siguientes
+// tiene menos triángulos
+pub struct SphereLods {
+    levels: [Vec<Vertex>; LOD_MESHES],
+}
+
+// Cómo se dibuja una esfera: con `mesh` (None = como un punto) y, en la banda de fundido entre dos
+// niveles, además con la malla más detallada encima y la opacidad que le toca
+pub struct LodSelection<'a> {
+    pub mesh: Option<&'a Vec<Vertex>>,
+    pub fade_in: Option<(&'a Vec<Vertex>, f32)>,
+}
+
+impl SphereLods {
+    pub fn new(full: Vec<Vertex>) -> Self {
+        SphereLods {
+            levels: [
+                with_spherical_uvs(full),
+                sphere_mesh(MEDIUM_SPHERE.0, MEDIUM_SPHERE.1),
+                sphere_mesh(LOW_SPHERE.0, LOW_SPHERE.1),
+            ],
+        }
+    }
+
+    pub fn full(&self) -> &Vec<Vertex> {
+        &self.levels[0]
+    }
+
+    // Mallas para una esfera de escala `scale` a `distance` de la cámara. `level` guarda el nivel
+    // elegido en el frame anterior para aplicar la histéresis y se actualiza con el nuevo. Con
+    // `crossfade`, cerca de un cambio entre mallas se funden las dos en lugar de saltar de una a
+    // otra; el paso a punto sigue siendo un cambio con histéresis.
+    pub fn select_lod(&self, distance: f32, scale: f32, level: &Cell<usize>, crossfade: bool) -> LodSelection<'_> {
+        let size = scale * SPHERE_MODEL_RADIUS / distance.max(1e-3);
+        let selected = lod_level(size, level.get());
+        level.set(selected);
+
+        if let Some((finer, opacity)) = crossfade.then(|| crossfade_band(size)).flatten() {
+            return LodSelection { mesh: Some(&self.levels[finer + 1]), fade_in: Some((&self.levels[finer], opacity)) };
+        }
+        LodSelection { mesh: self.levels.get(selected), fade_in: None }
+    }
+}
+
+// Si `size` cae en la banda de fundido de un cambio entre dos mallas, el más detallado de los dos
+// niveles y su opacidad: 1 en el borde de la banda más cercano a la cámara y 0 en el más lejano
+fn crossfade_band(size: f32) -> Option<(usize, f32)> {
+    LOD_THRESHOLDS.iter().take(LOD_MESHES - 1).enumerate().find_map(|(index, &threshold)| {
+        let (low, high) = (threshold * (1.0 - LOD_CROSSFADE_BAND), threshold * (1.0 + LOD_CROSSFADE_BAND));
+        (low..high).contains(&size).then(|| {
+            let t = (size - low) / (high - low);
+            (index, t * t * (3.0 - 2.0 * t))
+        })
+    })
+}
+
+// Nivel para un tamaño aparente `size` partiendo de `previous`. Para bajar de detalle hay que
+// quedar por debajo del umbral menos el margen y para subir, por encima del umbral más el margen.
+fn lod_level(size: f32, previous: usize) -> usize {
+    LOD_THRESHOLDS.iter().enumerate().fold(0, |level, (index, &threshold)| {
+        let margin = if index < previous { 1.0 + LOD_HYSTERESIS } else { 1.0 - LOD_HYSTERESIS };
+        if size < threshold * margin { index + 1 } else { level }
+    })
+}
+
+// Cambia las UV de una esfera centrada en el origen por longitud y latitud, como las de
+// `sphere_mesh`, con su tangente. Las de sphere-1.obj no sirven para muestrear texturas (cada
+// cara va de 0 a 1). En los triángulos que cruzan el meridiano 0 se suma 1 a las u pequeñas para
+// que no se interpole a través de toda la textura, y en los polos, donde la longitud no está
+// definida, se usa la media de los otros dos vértices.
+fn with_spherical_uvs(mut vertices: Vec<Vertex>) -> Vec<Vertex> {
+    for triangle in vertices.chunks_exact_mut(3) {
+        let directions = triangle.iter().map(|vertex| vertex.position.normalize()).collect::<Vec<_>>();
+        let mut u: Vec<f32> = directions.iter().map(|d| d.z.atan2(d.x).rem_euclid(2.0 * PI) / (2.0 * PI)).collect();
+        let at_pole = |d: &Vec3| 1.0 - d.y.abs() < 1e-4;
+
+        let (min_u, max_u) = u.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &value| (lo.min(value), hi.max(value)));
+        if max_u - min_u > 0.5 {
+            for value in u.iter_mut().filter(|value| **value < 0.5) {
+                *value += 1.0;
+            }
+        }
+        for index in 0..3 {
+            if at_pole(&directions[index]) {
+                let others: Vec<f32> = (0..3).filter(|&other| other != index).map(|other| u[other]).collect();
+                u[index] = (others[0] + others[1]) * 0.5;
+            }
+        }
+
+        for ((vertex, direction), u) in triangle.iter_mut().zip(&directions).zip(u) {
+            let phi = 2.0 * PI * u;
+            vertex.tex_coords = Vec2::new(u, direction.y.clamp(-1.0, 1.0).acos() / PI);
+            vertex.tangent = Vec4::new(-phi.sin(), 0.0, phi.cos(), 1.0);
+        }
+    }
+    vertices
+}
+
+// Esfera UV de radio `SPHERE_MODEL_RADIUS` con `stacks` paralelos y `slices` meridianos. Las
+// caras giran en sentido antihorario vistas desde fuera, como en los OBJ.
+pub fn sphere_mesh(stacks: usize, slices: usize) -> Vec<Vertex> {
+    let point = |stack: usize, slice: usize| {
+        let theta = PI * stack as f32 / stacks as f32;
+        let phi = 2.0 * PI * slice as f32 / slices as f32;
+        let normal = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+        let mut vertex = Vertex::new(normal * SPHERE_MODEL_RADIUS, normal, Vec2::new(slice as f32 / slices as f32, stack as f32 / stacks as f32));
+        // u crece con el meridiano (hacia +phi) y v hacia el polo sur, que es N × T
+        vertex.tangent = Vec4::new(-phi.sin(), 0.0, phi.cos(), 1.0);
+        vertex
+    };
+
+    let mut vertices = Vec::with_capacity(stacks * slices * 6);
+    for stack in 0..stacks {
+        for slice in 0..slices {
+            let (a, b) = (point(stack, slice), point(stack + 1, slice));
+            let (c, d) = (point(stack + 1, slice + 1), point(stack, slice + 1));
+
+            // En los polos uno de los dos triángulos del cuadrilátero tiene área nula
+            if stack + 1 < stacks {
+                vertices.extend([a.clone(), c.clone(), b]);
+            }
+            if stack > 0 {
+                vertices.extend([a, d, c]);
+            }
+        }
+    }
+
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossfade_is_continuous_across_each_mesh_threshold() {
+        for (index, &threshold) in LOD_THRESHOLDS.iter().take(LOD_MESHES - 1).enumerate() {
+            let (low, high) = (threshold * (1.0 - LOD_CROSSFADE_BAND), threshold * (1.0 + LOD_CROSSFADE_BAND));
+            assert_eq!(crossfade_band(high * 1.01), None);
+            assert_eq!(crossfade_band(low * 0.99), None);
+
+            // La opacidad de la malla detallada baja sin saltos de 1 a 0 al alejarse
+            let mut previous = 1.0;
+            for step in 1..100 {
+                let size = high - (high - low) * step as f32 / 100.0;
+                let (finer, opacity) = crossfade_band(size).expect("dentro de la banda");
+                assert_eq!(finer, index);
+                assert!(opacity <= previous && previous - opacity < 0.05);
+                previous = opacity;
+            }
+            assert!(previous < 0.01);
+        }
+
+        // El paso a punto no se funde
+        assert_eq!(crossfade_band(LOD_THRESHOLDS[LOD_MESHES - 1]), None);
+    }
+
+    #[test]
+    fn level_switch_has_hysteresis() {
+        let threshold = LOD_THRESHOLDS[0];
+        assert_eq!(lod_level(threshold * 0.95, 0), 0);
+        assert_eq!(lod_level(threshold * 0.8, 0), 1);
+        assert_eq!(lod_level(threshold * 1.05, 1), 1);
+        assert_eq!(lod_level(threshold * 1.2, 1), 0);
+    }
+}
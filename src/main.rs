@@ -1,9 +1,12 @@
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
-use minifb::{Key, KeyRepeat, Window, WindowOptions};
-use std::time::Duration;
-use std::f32::consts::PI;
-use image::{open, DynamicImage, GenericImageView};
-use rand::Rng;
+
+use nalgebra_glm::{Vec3, Mat3, Mat4};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use std::cell::Cell;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use image::{open, DynamicImage, ImageResult, RgbImage};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 
 mod framebuffer;
@@ -14,27 +17,135 @@ mod color;
 mod fragment;
 mod shaders;
 mod camera;
+mod scene;
+mod nbody;
+mod stats;
+mod cli;
+mod shadow;
+mod lights;
+mod asteroids;
+mod background;
+mod godrays;
+mod math;
+mod presets;
+mod pacing;
+mod antialias;
+mod line;
+mod frustum;
+mod scale;
+mod picking;
+mod warp;
+mod config;
+mod orbits;
+mod clipping;
+mod rings;
+mod font;
+mod lod;
+mod normal_map;
+mod comet;
+mod keys;
+mod trails;
+mod flare;
+mod minimap;
+mod autopilot;
+mod eclipse;
 
 use framebuffer::Framebuffer;
 use vertex::Vertex;
-use obj::Obj;
-use camera::Camera;
-use triangle::triangle;
-use shaders::{vertex_shader, fragment_shader};
+use obj::{Obj, SPHERE_MODEL_RADIUS};
+use camera::{Camera, CameraMode};
+use scene::Scene;
+use nbody::{init_nbody, integrate_nbody, total_energy};
+use stats::{RenderBudget, RenderStats, draw_tile_heatmap};
+use cli::{DEFAULT_SPOTLIGHT_CONE, arg_value, budget_from_args, camera_from_args, follow_smoothing_from_args, fog_density_from_args, gamma_from_args, god_rays_from_args, headless_frames_from_args, planet_seed_from_args, raster_tiles_from_args, spotlight_cone_from_args, starfield_from_args, tone_mapping_from_args};
+use lights::{StarLight, star_lights};
+use shadow::STAR_SHADER_MODE;
+use std::path::Path;
+use std::sync::Arc;
+use rings::{ring_mesh, RingShadow, DEFAULT_RING_INNER_RADIUS, DEFAULT_RING_OUTER_RADIUS, RINGED_SHADER_MODE, RING_SEGMENTS, RING_TILT};
+use asteroids::{asteroid_instances, asteroid_mesh, generate_asteroid_belt, BELT_INNER_RADIUS, BELT_OUTER_RADIUS, BELT_SEED, DEFAULT_ASTEROID_COUNT};
+use background::{Background, BackgroundSampling, PanoramicTexture, draw_background, draw_starfield, linearize, open_panorama};
+use godrays::{apply_god_rays, GodRays};
+use flare::apply_sun_flare;
+use minimap::draw_minimap;
+use autopilot::Autopilot;
+use eclipse::EclipseShadow;
+use math::{create_model_matrix, create_normal_matrix, create_perspective_matrix, create_tilted_model_matrix, create_view_matrix, create_viewport_matrix, transform_aabb, world_to_screen};
+use presets::{PlanetPreset, PresetRegistry, DEFAULT_CLOUD_COVERAGE};
+use pacing::FramePacer;
+use antialias::{AntiAlias, apply_fxaa, resolve_ssaa, SSAA_FACTOR};
+use frustum::{aabb_outside_frustum, draw_frustum, frustum_corners, sphere_inside_frustum, sphere_outside_frustum};
+use scale::{RealScale, SystemScale, EXAGGERATION_STEP};
+use picking::{draw_crosshair, pick_planet};
+use warp::WarpAnimator;
+use config::{load_key_bindings, load_planets, FileWatcher};
+use orbits::{draw_orbit, kepler_orbit_speed, kepler_position, ORBIT_SEGMENTS};
+use fragment::Fragment;
+use color::{Color, ToneMapping};
+use line::line;
+use lod::{LodSelection, SphereLods};
+use comet::{draw_tail, Comet};
+use keys::KeyBindings;
+use trails::{draw_trail, Trail, DEFAULT_TRAIL_LENGTH};
+use font::{GLYPH_ADVANCE, GLYPH_HEIGHT};
+use normal_map::{terrain_normal_map, TERRAIN_NORMAL_MAP_HEIGHT, TERRAIN_NORMAL_MAP_WIDTH};
+use triangle::{flatten_normals, is_back_facing, triangle};
+use shaders::{vertex_to_clip, project_vertex, fragment_shader, fragment_alpha, BlendMode, RING_SHADER_MODE};
+use clipping::{ClipVertex, clip_triangle};
 use fastnoise_lite::{FastNoiseLite, NoiseType};
+use rayon::prelude::*;
 
-
-
+#[derive(Clone)]
 pub struct Uniforms {
     model_matrix: Mat4,
+    normal_matrix: Mat3,  // Inversa traspuesta de `model_matrix`; se actualiza siempre junto a ella
     view_matrix: Mat4,
     projection_matrix: Mat4,
     viewport_matrix: Mat4,
     time: u32,
-    noise: FastNoiseLite,
-    shader_mode: u8
+    noise: Arc<FastNoiseLite>,  // Compartido por todos los objetos; se crea una sola vez
+    shader_mode: u8,
+    noise_zoom: f32,  // Multiplicador del tamaño de los detalles de superficie (1.0 = original)
+    cloud_coverage: f32,  // Fracción del cielo cubierta de nubes, de 0 (despejado) a 1
+    lights: Arc<Vec<StarLight>>,  // Estrellas de la escena; la primera es la luz principal
+    star_color: Option<Vec3>,     // Color propio de una estrella (modo 1)
+    star_temperature: f32,        // Temperatura de una estrella en kelvin; 0 = sin temperatura propia
+    debug_normals: bool,  // Sustituye todos los shaders por la normal en color
+    brightness: f32,      // Multiplicador global del color sombreado (1.0 = sin cambios)
+    camera_position: Vec3,  // Ojo de la cámara que renderiza, para los términos que dependen de la vista
+    light_position: Vec3,   // Posición de la estrella principal; los planetas se iluminan desde ella
+    spotlight_position: Vec3,  // Foco de la nave: posición, dirección en la que apunta y semiapertura
+    spotlight_dir: Vec3,       // del cono en radianes (0 = apagado)
+    spotlight_cone: f32,
+    backface_culling: bool,  // Descarta los triángulos que miran en sentido contrario a la cámara
+    texture: Option<Arc<RgbImage>>,  // Textura difusa del modelo (modo 10)
+    normal_map: Option<Arc<RgbImage>>,  // Mapa de normales en espacio tangente (terreno rocoso)
+    flat_shading: bool,  // Una sola normal por cara en lugar de interpolar las de los vértices
+    gamma: f32,          // Curva con la que se codifica el color sombreado al escribirlo (1.0 = ninguna)
+    render_mode: RenderMode,
+    fog_color: Color,    // Color hacia el que se desvanecen los fragmentos lejanos
+    fog_density: f32,    // Niebla exponencial por unidad de distancia a la cámara; 0 la desactiva
+    raster_tiles: usize,  // Franjas en las que `render` reparte el sombreado entre hilos; 1 = secuencial
+    tone_mapping: ToneMapping,  // Curva que comprime los colores que pasan de 255 antes de empaquetarlos
+    ring_shadow: Option<RingShadow>,  // Anillos del planeta que se dibuja, que le dan sombra
+    eclipse_shadow: Option<EclipseShadow>,  // Cuerpos que pueden eclipsarle la luz principal
+    opacity: f32,  // Menor que 1 mezcla el objeto con lo que hay detrás (fundido entre niveles de detalle)
 }
 
+// Cómo se rasterizan los triángulos: caras rellenas y sombreadas o solo sus aristas
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum RenderMode {
+    #[default]
+    Filled,
+    Wireframe,
+}
+
+// Color de la niebla: el del espacio del fondo, un negro apenas azulado
+const FOG_COLOR: Color = Color::new(8, 8, 16);
+
+// Color fijo de las aristas en modo alambre
+const WIREFRAME_COLOR: u32 = 0x40FF80;
+
 fn create_noise() -> FastNoiseLite {
     create_cloud_noise()
 }
@@ -45,488 +156,2327 @@ fn create_cloud_noise() -> FastNoiseLite {
     noise
 }
 
-fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
-    let (sin_x, cos_x) = rotation.x.sin_cos();
-    let (sin_y, cos_y) = rotation.y.sin_cos();
-    let (sin_z, cos_z) = rotation.z.sin_cos();
-
-    let rotation_matrix_x = Mat4::new(
-        1.0,  0.0,    0.0,   0.0,
-        0.0,  cos_x, -sin_x, 0.0,
-        0.0,  sin_x,  cos_x, 0.0,
-        0.0,  0.0,    0.0,   1.0,
-    );
+// Lado en píxeles del punto con el que se dibuja una esfera demasiado pequeña para su malla
+const PLANET_POINT_SIZE: isize = 3;
+
+// Dibuja una esfera de centro `center` y radio `radius` con la malla de su nivel de detalle o, si
+// `select_lod` no dio ninguna, como un punto del color que tendría su cara visible. En la banda de
+// fundido entre dos niveles, la malla detallada se mezcla encima de la otra con su opacidad.
+fn render_sphere(framebuffer: &mut Framebuffer, uniforms: &Uniforms, lod: LodSelection, center: Vec3, radius: f32, stats: &mut RenderStats) {
+    if let Some((finer, opacity)) = lod.fade_in {
+        let uniforms_fade = Uniforms { opacity, ..uniforms.clone() };
+        if let Some(mesh) = lod.mesh {
+            render(framebuffer, uniforms, mesh, stats);
+        }
+        render_transparent(framebuffer, &uniforms_fade, finer, stats);
+        return;
+    }
 
-    let rotation_matrix_y = Mat4::new(
-        cos_y,  0.0,  sin_y, 0.0,
-        0.0,    1.0,  0.0,   0.0,
-        -sin_y, 0.0,  cos_y, 0.0,
-        0.0,    0.0,  0.0,   1.0,
-    );
+    match lod.mesh {
+        Some(mesh) => render(framebuffer, uniforms, mesh, stats),
+        None => {
+            let Some(screen_pos) = world_to_screen(center, &uniforms.view_matrix, &uniforms.projection_matrix, &uniforms.viewport_matrix) else {
+                return;
+            };
+            // El color se toma sombreando el punto de la superficie más cercano a la cámara
+            let normal = (uniforms.camera_position - center).normalize();
+            let fragment = Fragment {
+                world_position: center + normal * radius,
+                ..Fragment::new(screen_pos.x, screen_pos.y, Color::new(100, 100, 100), screen_pos.z, normal, 1.0, normal * SPHERE_MODEL_RADIUS)
+            };
+            let color = match shade_fragment(&fragment, uniforms) {
+                (color, BlendMode::Additive(emission)) => add_emission(color, emission),
+                (color, _) => color,
+            };
+            render_planet_as_point(framebuffer, screen_pos, color);
+        }
+    }
+}
 
-    let rotation_matrix_z = Mat4::new(
-        cos_z, -sin_z, 0.0, 0.0,
-        sin_z,  cos_z, 0.0, 0.0,
-        0.0,    0.0,  1.0, 0.0,
-        0.0,    0.0,  0.0, 1.0,
-    );
+// Cuadrado de `PLANET_POINT_SIZE` píxeles centrado en `screen_pos`, con prueba de profundidad
+fn render_planet_as_point(framebuffer: &mut Framebuffer, screen_pos: Vec3, color: u32) {
+    framebuffer.set_current_color(color);
+    let (center_x, center_y) = (screen_pos.x as isize, screen_pos.y as isize);
+    let start = -(PLANET_POINT_SIZE / 2);
+    for dy in start..start + PLANET_POINT_SIZE {
+        for dx in start..start + PLANET_POINT_SIZE {
+            let (x, y) = (center_x + dx, center_y + dy);
+            if x >= 0 && y >= 0 {
+                framebuffer.point(x as usize, y as usize, screen_pos.z);
+            }
+        }
+    }
+}
 
-    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], stats: &mut RenderStats) {
+    // Shader de vértices, ensamblaje de primitivas y recorte contra el plano cercano
+    let mut triangles = Vec::new();
+    assemble_triangles(vertex_array, uniforms, &mut triangles);
 
-    let transform_matrix = Mat4::new(
-        scale, 0.0,   0.0,   translation.x,
-        0.0,   scale, 0.0,   translation.y,
-        0.0,   0.0,   scale, translation.z,
-        0.0,   0.0,   0.0,   1.0,
-    );
+    // Rasterización: convertir triángulos a fragmentos (píxeles)
+    let mut fragments = Vec::new();
+    rasterize_triangles(framebuffer, uniforms, &triangles, stats, |_, tri_fragments| fragments.extend(tri_fragments));
 
-    transform_matrix * rotation_matrix
+    // Procesamiento de fragmentos: sombrear cada fragmento y dibujarlo en el framebuffer
+    if uniforms.raster_tiles > 1 {
+        draw_fragments_tiled(framebuffer, fragments, uniforms);
+    } else {
+        draw_fragments(framebuffer, fragments, uniforms);
+    }
 }
 
+// Reparte los fragmentos en `uniforms.raster_tiles` franjas horizontales según su fila y las
+// procesa en paralelo con rayon. Cada franja es dueña de su trozo del buffer de color y del
+// z-buffer, así que no hace falta copiar nada al final; dentro de una franja los fragmentos
+// conservan su orden, de modo que el resultado es idéntico píxel a píxel al del bucle secuencial.
+fn draw_fragments_tiled(framebuffer: &mut Framebuffer, fragments: Vec<Fragment>, uniforms: &Uniforms) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let rows_per_tile = height.div_ceil(uniforms.raster_tiles);
+
+    let mut bins: Vec<Vec<Fragment>> = (0..height.div_ceil(rows_per_tile)).map(|_| Vec::new()).collect();
+    for fragment in fragments {
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        if x < width && y < height {
+            bins[y / rows_per_tile].push(fragment);
+        }
+    }
 
-fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
-    look_at(&eye, &center, &up)
+    let tile_len = rows_per_tile * width;
+    framebuffer.buffer.par_chunks_mut(tile_len)
+        .zip(framebuffer.zbuffer.par_chunks_mut(tile_len))
+        .zip(bins.par_iter())
+        .enumerate()
+        .for_each(|(tile, ((colors, depths), bin))| {
+            let first_index = tile * tile_len;
+            for fragment in bin {
+                let index = fragment.position.y as usize * width + fragment.position.x as usize - first_index;
+                write_fragment(&mut colors[index], &mut depths[index], fragment, uniforms);
+            }
+        });
 }
 
-fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
-    let aspect_ratio = window_width / window_height;
-    let near = 0.1;
-    let far = 1000.0;
+// Transforma `vertex_array` y lo agrupa en triángulos listos para rasterizar (reutilizando
+// `triangles`). Cada triángulo se recorta en clip space antes de la división de perspectiva.
+fn assemble_triangles(vertex_array: &[Vertex], uniforms: &Uniforms, triangles: &mut Vec<[Vertex; 3]>) {
+    triangles.clear();
+    let mut clipped = Vec::new();
+
+    for chunk in vertex_array.chunks_exact(3) {
+        let tri = [&chunk[0], &chunk[1], &chunk[2]].map(|vertex| {
+            let (vertex, clip) = vertex_to_clip(vertex, uniforms);
+            ClipVertex { vertex, clip }
+        });
+
+        clipped.clear();
+        clip_triangle(tri, &mut clipped);
+        for tri in clipped.drain(..) {
+            let mut tri = tri.map(|ClipVertex { mut vertex, clip }| {
+                project_vertex(&mut vertex, clip, uniforms);
+                vertex
+            });
+            if uniforms.flat_shading {
+                flatten_normals(&mut tri);
+            }
+            triangles.push(tri);
+        }
+    }
+}
 
-    perspective(fov, aspect_ratio, near, far)
+// Como `assemble_triangles`, pero sin recortar: solo vale para mallas que quedan enteras dentro
+// del frustum, en las que el recorte no cambiaría ningún triángulo
+fn project_triangles(vertex_array: &[Vertex], uniforms: &Uniforms, triangles: &mut Vec<[Vertex; 3]>) {
+    triangles.clear();
+    triangles.extend(vertex_array.chunks_exact(3).map(|chunk| {
+        let mut tri = [&chunk[0], &chunk[1], &chunk[2]].map(|vertex| {
+            let (mut vertex, clip) = vertex_to_clip(vertex, uniforms);
+            project_vertex(&mut vertex, clip, uniforms);
+            vertex
+        });
+        if uniforms.flat_shading {
+            flatten_normals(&mut tri);
+        }
+        tri
+    }));
 }
 
-fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
-    Mat4::new(
-        width / 2.0, 0.0, 0.0, width / 2.0,
-        0.0, -height / 2.0, 0.0, height / 2.0,
-        0.0, 0.0, 1.0, 0.0,
-        0.0, 0.0, 0.0, 1.0
-    )
+// Rasteriza los triángulos ya proyectados que no descarta el culling de caras traseras y los
+// cuenta en `stats`. En modo alambre dibuja sus aristas; si no, pasa los fragmentos de cada uno a
+// `emit`, que decide si se sombrean enseguida o se acumulan.
+fn rasterize_triangles(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    triangles: &[[Vertex; 3]],
+    stats: &mut RenderStats,
+    mut emit: impl FnMut(&mut Framebuffer, Vec<Fragment>),
+) {
+    for tri in triangles {
+        if uniforms.backface_culling && is_back_facing(&tri[0], &tri[1], &tri[2]) {
+            continue;
+        }
+        stats.triangles += 1;
+        stats.bin_triangle(tri);
+        if uniforms.render_mode == RenderMode::Wireframe {
+            stats.fragments += draw_wireframe(framebuffer, tri);
+            continue;
+        }
+        let fragments = triangle(&tri[0], &tri[1], &tri[2]);
+        stats.fragments += fragments.len();
+        emit(framebuffer, fragments);
+    }
 }
 
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], image: &DynamicImage, image_width: u32, image_height: u32) {
-    // Renderizar la imagen panorámica de fondo
-    // Iteramos por todos los píxeles de la ventana y proyectamos la imagen panorámica sobre el fondo
-    for y in 0..framebuffer.height {
-        for x in 0..framebuffer.width {
-            // Convertimos las coordenadas de la ventana a ángulos esféricos
-            let x_angle = (x as f32 / framebuffer.width as f32) * 360.0 - 180.0; // Mapeo de 0 a 360 -> -180 a 180
-            let y_angle = (y as f32 / framebuffer.height as f32) * 180.0 - 90.0; // Mapeo de 0 a 180 -> -90 a 90
+// Sombrea y escribe en el momento cada fragmento, para las rutas que no reparten el trabajo en franjas
+fn draw_fragments(framebuffer: &mut Framebuffer, fragments: Vec<Fragment>, uniforms: &Uniforms) {
+    for fragment in fragments {
+        draw_fragment(framebuffer, &fragment, uniforms);
+    }
+}
 
-            // Proyectamos estos ángulos a coordenadas de la imagen panorámica
-            let (x_pixel, y_pixel) = project_to_image(x_angle, y_angle, image_width, image_height);
+// Modo alambre: dibuja las tres aristas de un triángulo ya proyectado con `WIREFRAME_COLOR`,
+// respetando el z-buffer. Devuelve el número de fragmentos generados.
+fn draw_wireframe(framebuffer: &mut Framebuffer, tri: &[Vertex; 3]) -> usize {
+    framebuffer.set_current_color(WIREFRAME_COLOR);
+
+    let mut count = 0;
+    for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+        for fragment in line(&tri[a], &tri[b]) {
+            count += 1;
+            if fragment.position.x >= 0.0 && fragment.position.y >= 0.0 {
+                framebuffer.point(fragment.position.x as usize, fragment.position.y as usize, fragment.depth);
+            }
+        }
+    }
+    count
+}
 
-            // Obtenemos el color del píxel correspondiente en la imagen panorámica
-            let pixel = image.get_pixel(x_pixel, y_pixel);
-            let color = (pixel[0] as u32) | ((pixel[1] as u32) << 8) | ((pixel[2] as u32) << 16);
+// Variante de `render` para geometría translúcida, cuyo shader la mezcla con lo que ya hay en el
+// buffer (`BlendMode::Alpha`). Debe dibujarse después de todo lo opaco y de atrás hacia delante.
+fn render_transparent(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], stats: &mut RenderStats) {
+    let mut triangles = Vec::new();
+    assemble_triangles(vertex_array, uniforms, &mut triangles);
+    rasterize_triangles(framebuffer, uniforms, &triangles, stats, |framebuffer, fragments| draw_fragments(framebuffer, fragments, uniforms));
+}
 
-            // Establecemos el color de fondo en el framebuffer
-            framebuffer.set_current_color(color);
-            framebuffer.point(x as usize, y as usize, 1.0);
+// Aplicamos el fragment shader, el brillo global (que hace de exposición: el color puede pasar
+// de 255) y la niebla, y comprimimos el resultado con el mapeo de tonos antes de empaquetarlo.
+// La emisión de `BlendMode::Additive` pasa por el mismo brillo y mapeo de tonos; la niebla solo
+// la atenúa, porque no tiene color de fondo al que mezclarse.
+fn shade_fragment(fragment: &Fragment, uniforms: &Uniforms) -> (u32, BlendMode) {
+    let (color, blend_mode) = fragment_shader(fragment, uniforms);
+    let pack = |color: Color| color.tone_mapped(uniforms.tone_mapping).to_hex_gamma(uniforms.gamma);
+    let blend_mode = match blend_mode {
+        _ if uniforms.opacity < 1.0 => BlendMode::Alpha,
+        BlendMode::Additive(emission) => {
+            let emission = emission * (uniforms.brightness * (1.0 - fog_amount(fragment, uniforms)));
+            BlendMode::Additive(Color::from_hex(pack(emission)))
         }
-    }
+        blend_mode => blend_mode,
+    };
+    let color = apply_fog(color * uniforms.brightness, fragment, uniforms);
+    (pack(color), blend_mode)
+}
 
-    // Ahora renderizamos los objetos 3D, como la esfera, sobre el fondo de la imagen panorámica
+// Suma una emisión ya empaquetada por `shade_fragment` a un color del buffer, saturando en blanco
+fn add_emission(color: u32, emission: Color) -> u32 {
+    (Color::from_hex(color) + emission).to_hex()
+}
 
-    // Transforma los vértices con el shader de vértices (usando las matrices de transformación)
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+// Sombrea un fragmento si pasa la prueba de profundidad contra su píxel (`color`, `depth`) y lo
+// escribe según el modo de mezcla que pide su shader. Las mezclas se hacen con los colores ya
+// empaquetados, como se ven en pantalla.
+fn write_fragment(color: &mut u32, depth: &mut f32, fragment: &Fragment, uniforms: &Uniforms) {
+    if *depth <= fragment.depth {
+        return;
     }
 
-    // Ensamblaje de primitivas: agrupar vértices en triángulos
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+    let (shaded, blend_mode) = shade_fragment(fragment, uniforms);
+    match blend_mode {
+        BlendMode::Opaque => {
+            *color = shaded;
+            *depth = fragment.depth;
+        }
+        BlendMode::Alpha => {
+            let alpha = fragment_alpha(fragment, uniforms).clamp(0.0, 1.0);
+            *color = Color::from_hex(*color).blend(Color::from_hex(shaded), alpha).to_hex();
+        }
+        BlendMode::Additive(emission) => {
+            // La superficie tapa lo de detrás como un opaco; la emisión se suma encima
+            *color = add_emission(shaded, emission);
+            *depth = fragment.depth;
         }
     }
+}
 
-    // Rasterización: convertir triángulos a fragmentos (píxeles)
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
+// Niebla exponencial: mezcla `color` con `uniforms.fog_color` según la distancia del fragmento
+// a la cámara, de modo que lo cercano (el Sol al acercarse) queda nítido y lo lejano se suaviza
+fn apply_fog(color: Color, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    if uniforms.fog_density <= 0.0 {
+        return color;
     }
+    color.lerp(&uniforms.fog_color, fog_amount(fragment, uniforms))
+}
 
-    // Procesamiento de fragmentos: sombrear cada fragmento y dibujarlo en el framebuffer
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
+// Fracción de niebla (0 a 1) que cubre un fragmento
+fn fog_amount(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+    if uniforms.fog_density <= 0.0 {
+        return 0.0;
+    }
 
-        if x < framebuffer.width && y < framebuffer.height {
-            // Aplicamos el fragment shader
-            let shaded_color = fragment_shader(&fragment, &uniforms);
-            let color = shaded_color.to_hex();
+    let distance = (fragment.world_position - uniforms.camera_position).magnitude();
+    1.0 - (-uniforms.fog_density * distance).exp()
+}
 
-            // Dibujamos el píxel con el color sombreado en el framebuffer
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
-        }
+fn draw_fragment(framebuffer: &mut Framebuffer, fragment: &Fragment, uniforms: &Uniforms) {
+    let x = fragment.position.x as usize;
+    let y = fragment.position.y as usize;
+
+    if x < framebuffer.width && y < framebuffer.height {
+        let index = y * framebuffer.width + x;
+        write_fragment(&mut framebuffer.buffer[index], &mut framebuffer.zbuffer[index], fragment, uniforms);
     }
 }
 
+// Ruta instanciada: una misma malla dibujada con muchas matrices de modelo. A diferencia de
+// `render`, reutiliza las uniformes y el buffer de triángulos transformados y sombrea cada
+// triángulo en cuanto se rasteriza. Las instancias que quedan enteras dentro del frustum (casi
+// todas en el cinturón) se proyectan vértice a vértice sin pasar por el recorte; solo las que
+// cruzan algún plano usan `assemble_triangles`.
+// Las instancias que quedan al agotarse `budget` se omiten.
+fn render_instanced(framebuffer: &mut Framebuffer, uniforms: &mut Uniforms, vertex_array: &[Vertex], instances: &[Mat4], stats: &mut RenderStats, budget: &RenderBudget) {
+    let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+    let mut triangles = Vec::with_capacity(vertex_array.len() / 3);
+
+    // Radio de la malla en su espacio de modelo; el de cada instancia lo multiplica por su mayor escala
+    let mesh_radius = vertex_array.iter().map(|vertex| vertex.position.magnitude()).fold(0.0, f32::max);
+
+    for (index, model_matrix) in instances.iter().enumerate() {
+        if stats.over_budget(budget) {
+            stats.skipped_objects += instances.len() - index;
+            break;
+        }
+
+        // Descartar instancias cuyo centro queda detrás de la cámara
+        let center = view_projection * model_matrix.column(3);
+        if center.w <= 0.0 {
+            continue;
+        }
 
-fn load_panoramic_image(path: &str) -> DynamicImage {
-    open(path).unwrap()
+        uniforms.model_matrix = *model_matrix;
+        uniforms.normal_matrix = create_normal_matrix(model_matrix);
+
+        let position = Vec3::new(model_matrix[(0, 3)], model_matrix[(1, 3)], model_matrix[(2, 3)]);
+        let scale = (0..3)
+            .map(|column| Vec3::new(model_matrix[(0, column)], model_matrix[(1, column)], model_matrix[(2, column)]).magnitude())
+            .fold(0.0, f32::max);
+        if sphere_inside_frustum(&view_projection, position, mesh_radius * scale) {
+            project_triangles(vertex_array, uniforms, &mut triangles);
+        } else {
+            assemble_triangles(vertex_array, uniforms, &mut triangles);
+        }
+        rasterize_triangles(framebuffer, uniforms, &triangles, stats, |framebuffer, fragments| draw_fragments(framebuffer, fragments, uniforms));
+    }
 }
 
-fn project_to_image(x_angle: f32, y_angle: f32, image_width: u32, image_height: u32) -> (u32, u32) {
-    // Convierte los ángulos a un rango de 0 a 1
-    let x_normalized = (x_angle + 180.0) / 360.0; // -180 a 180 -> 0 a 1
-    let y_normalized = (y_angle + 90.0) / 180.0; // -90 a 90 -> 0 a 1
-    
-    // Calcula las coordenadas de la imagen
-    let x_pixel = (x_normalized * image_width as f32) as u32;
-    let y_pixel = (y_normalized * image_height as f32) as u32;
 
-    (x_pixel, y_pixel)
+// Panorámica por defecto (LDR); se usa también si no se puede abrir la elegida con `--background`
+const DEFAULT_BACKGROUND: &str = "assets/image/space.png";
+
+// `--background procedural` sustituye la panorámica por un campo de estrellas generado
+const PROCEDURAL_BACKGROUND: &str = "procedural";
+
+// Carga el fondo equirectangular. El formato se deduce de la extensión: .hdr y .exr se
+// decodifican en coma flotante y el resto (PNG, JPEG...) en 8 bits por canal.
+fn load_panoramic_image(path: &str) -> DynamicImage {
+    open_panorama(path).unwrap_or_else(|err| {
+        eprintln!("No se pudo cargar el fondo {}: {}; se usa {}", path, err, DEFAULT_BACKGROUND);
+        open(DEFAULT_BACKGROUND).unwrap()
+    })
 }
+
 //Planetas
 // Definición de un planeta
+#[derive(Serialize, Deserialize)]
 pub struct Planet {
+    #[serde(default)]
+    name: String,         // Nombre que muestra el HUD; vacío si no se indica
     position: Vec3,
     rotation_speed: f32,
+    #[serde(default)]
+    axial_tilt: Vec3,     // Inclinación del eje de giro (radianes alrededor de X, Y y Z); cero = eje Y
     orbit_radius: f32,    // Radio de la órbita
-    orbit_speed: f32,
+    #[serde(default)]
+    orbit_speed: f32,     // Radianes de anomalía media por frame; se ignora con `auto_orbit_speed`
+    #[serde(default)]
+    auto_orbit_speed: bool,  // Deriva la velocidad del semieje mayor (ver `Planet::orbit_speed`)
+    #[serde(default)]
     orbit_phase: f32,    
+    #[serde(default)]
+    eccentricity: f32,    // 0 = órbita circular; el Sol queda en un foco y el periastro hacia +X
+    #[serde(default)]
+    semi_major_axis: Option<f32>,  // Sin él, la órbita usa `orbit_radius` como semieje mayor
+    scale: f32,
+    shader_mode: u8,
+    #[serde(default)]
+    mass: f32,            // Solo se usa en el modo n-cuerpos
+    #[serde(default)]
+    velocity: Vec3,
+    #[serde(default = "default_noise_zoom")]
+    noise_zoom: f32,      // Multiplicador del zoom de ruido del shader
+    #[serde(default = "default_cloud_coverage")]
+    cloud_coverage: f32,  // Solo la usan los planetas con atmósfera
+    #[serde(default)]
+    star_color: Option<Vec3>,  // Solo estrellas: color de 0 a 1; sin él se usa la paleta del Sol
+    #[serde(default)]
+    star_temperature: f32,     // Solo estrellas: kelvin; si es mayor que 0 manda sobre `star_color`
+    #[serde(default)]
+    real: Option<RealScale>,   // Tamaño y órbita reales para la vista a escala
+    #[serde(default)]
+    moons: Vec<Moon>,
+    #[serde(default)]
+    ring_inner_radius: Option<f32>,  // Radios de los anillos en radios del planeta (ver `Planet::rings`)
+    #[serde(default)]
+    ring_outer_radius: Option<f32>,
+    #[serde(default = "default_trail_length")]
+    trail_length: usize,  // Muestras de la estela que deja al moverse; 0 la desactiva
+    #[serde(skip)]
+    trail: Trail,
+    #[serde(skip)]
+    lod: Cell<usize>,     // Nivel de detalle elegido en el último frame
+}
+
+// Luna que orbita a su planeta; la órbita es relativa a la posición actual del planeta
+#[derive(Serialize, Deserialize)]
+pub struct Moon {
+    orbit_radius: f32,
+    orbit_speed: f32,
+    #[serde(default)]
+    orbit_phase: f32,
     scale: f32,
     shader_mode: u8,
+    #[serde(skip)]
+    lod: Cell<usize>,
+}
+
+impl Moon {
+    // Traslación, rotación y escala de la luna alrededor de un planeta situado en `parent_position`.
+    // `parent_scale` es la escala con la que se dibuja el planeta: si cambia (vista a escala real)
+    // la órbita y el tamaño de la luna cambian en la misma proporción.
+    fn transformation(&self, parent: &Planet, parent_position: Vec3, parent_scale: f32, time: f32) -> (Vec3, Vec3, f32) {
+        let ratio = if parent.scale > 0.0 { parent_scale / parent.scale } else { 1.0 };
+        let angle = self.orbit_speed * time + self.orbit_phase;
+        let orbit_radius = self.orbit_radius * ratio;
+
+        (
+            parent_position + Vec3::new(orbit_radius * angle.cos(), 0.0, orbit_radius * angle.sin()),
+            Vec3::new(0.0, -angle, 0.0),  // Acoplamiento de marea: siempre muestra la misma cara
+            self.scale * ratio,
+        )
+    }
+}
+
+fn default_noise_zoom() -> f32 {
+    1.0
+}
+
+fn default_cloud_coverage() -> f32 {
+    DEFAULT_CLOUD_COVERAGE
+}
+
+fn default_trail_length() -> usize {
+    DEFAULT_TRAIL_LENGTH
+}
+
+// `time` se mide en frames simulados: avanza 60 por segundo real con la escala de tiempo x1,
+// sea cual sea la frecuencia real de frames
+const SIMULATION_FRAMES_PER_SECOND: f32 = 60.0;
+
+impl Planet {
+    // Radios interior y exterior de los anillos, en radios del planeta: los de la configuración o,
+    // en los gigantes gaseosos, los de por defecto. None si no tiene anillos o si el exterior no
+    // es mayor que el interior, que es la forma de quitárselos a un gigante gaseoso.
+    fn rings(&self) -> Option<(f32, f32)> {
+        if self.shader_mode != RINGED_SHADER_MODE && self.ring_inner_radius.is_none() && self.ring_outer_radius.is_none() {
+            return None;
+        }
+        let inner_radius = self.ring_inner_radius.unwrap_or(DEFAULT_RING_INNER_RADIUS);
+        let outer_radius = self.ring_outer_radius.unwrap_or(DEFAULT_RING_OUTER_RADIUS);
+        (outer_radius > inner_radius).then_some((inner_radius, outer_radius))
+    }
+
+    // Velocidad angular media de la órbita: la de la configuración o, con `auto_orbit_speed`, la
+    // de la tercera ley de Kepler para su semieje mayor (sin la vista a escala real, para que no
+    // cambie durante la transición)
+    fn orbit_speed(&self) -> f32 {
+        if self.auto_orbit_speed {
+            kepler_orbit_speed(self.semi_major_axis())
+        } else {
+            self.orbit_speed
+        }
+    }
+
+    // Segundos simulados que tarda en dar una vuelta a su órbita; None si no orbita (el Sol)
+    fn orbital_period(&self) -> Option<f32> {
+        if self.orbit_radius <= 0.0 {
+            return None;
+        }
+        period_from_speed(self.orbit_speed())
+    }
+
+    // Semieje mayor de la órbita; en una órbita circular es su radio
+    fn semi_major_axis(&self) -> f32 {
+        self.semi_major_axis.unwrap_or(self.orbit_radius)
+    }
+
+    // Segundos simulados que tarda en girar sobre su eje; None si no gira
+    fn rotation_period(&self) -> Option<f32> {
+        period_from_speed(self.rotation_speed)
+    }
+
+    // Planeta con la apariencia de `preset` en una órbita circular que empieza en `orbit_phase`
+    fn from_preset(preset: &PlanetPreset, orbit_radius: f32, orbit_speed: f32, orbit_phase: f32) -> Self {
+        Planet {
+            name: String::new(),
+            position: Vec3::new(orbit_radius * orbit_phase.cos(), 0.0, orbit_radius * orbit_phase.sin()),
+            rotation_speed: preset.rotation_speed,
+            axial_tilt: Vec3::zeros(),
+            orbit_radius,
+            orbit_speed,
+            auto_orbit_speed: false,
+            orbit_phase,
+            eccentricity: 0.0,
+            semi_major_axis: None,
+            scale: preset.scale,
+            shader_mode: preset.shader_mode,
+            mass: preset.mass,
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            noise_zoom: preset.noise_zoom,
+            cloud_coverage: preset.cloud_coverage,
+            star_color: preset.star_color,
+            star_temperature: 0.0,
+            real: preset.real,
+            moons: Vec::new(),
+            ring_inner_radius: None,
+            ring_outer_radius: None,
+            // Lo que no orbita no deja estela
+            trail_length: if orbit_radius > 0.0 { DEFAULT_TRAIL_LENGTH } else { 0 },
+            trail: Trail::default(),
+            lod: Cell::new(0),
+        }
+    }
+}
+
+// Periodo de un giro completo a `speed` radianes por frame, en segundos simulados
+fn period_from_speed(speed: f32) -> Option<f32> {
+    (speed.abs() > f32::EPSILON).then(|| 2.0 * std::f32::consts::PI / speed.abs() / SIMULATION_FRAMES_PER_SECOND)
+}
+
+// Periodo legible para el título de la ventana
+fn format_period(period: Option<f32>, none: &str) -> String {
+    match period {
+        Some(seconds) if seconds >= 60.0 => format!("{:.1} min", seconds / 60.0),
+        Some(seconds) => format!("{:.1} s", seconds),
+        None => none.to_string(),
+    }
 }
 
-// Generar planetas
-pub fn generate_planets() -> Vec<Planet> {
-    let mut rng = rand::thread_rng();
+// Generar planetas a partir de los presets integrados; `seed` fija sus ángulos iniciales
+pub fn generate_planets(seed: u64) -> Vec<Planet> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let registry = PresetRegistry::with_builtins();
+
+    // Cada planeta: preset, radio de órbita y velocidad orbital, con un ángulo inicial aleatorio
+    let mut planet = |name: &str, orbit_radius: f32, orbit_speed: f32| {
+        let preset = registry.get(name).expect("preset integrado inexistente");
+        let orbit_phase = if orbit_radius > 0.0 { rng.gen_range(0.0..(2.0 * std::f32::consts::PI)) } else { 0.0 };
+        Planet {
+            name: name.to_string(),
+            ..Planet::from_preset(preset, orbit_radius, orbit_speed, orbit_phase)
+        }
+    };
+
+    let mut earth = planet("earth", 23.8, 0.015);
+    earth.moons.push(Moon {
+        orbit_radius: 2.5,
+        orbit_speed: 0.05,
+        orbit_phase: 0.0,
+        scale: 0.6,
+        shader_mode: 2,  // Superficie rocosa
+        lod: Cell::new(0),
+    });
 
     vec![
-        Planet { 
-            position: Vec3::new(0.0, 0.0, 0.0), // Sol
-            rotation_speed: 0.0, 
-            orbit_radius: 0.0,  // El Sol no orbita
-            orbit_speed: 0.0, 
-            orbit_phase: 0.0,  // No aplica al Sol
-            scale: 4.0, 
-            shader_mode: 1, 
-        },
-        Planet { 
-            position: Vec3::new(10.0, 0.0, 0.0), 
-            rotation_speed: 0.1, 
-            orbit_radius: 10.0,
-            orbit_speed: 0.02,
-            orbit_phase: rng.gen_range(0.0..(2.0 * std::f32::consts::PI)),  // Ángulo inicial aleatorio
-            scale: 2.4, 
-            shader_mode: 2, 
-        },
-        Planet { 
-            position: Vec3::new(0.0, 0.0, 18.0),
-            rotation_speed: 0.1, 
-            orbit_radius: 15.0,
-            orbit_speed: 0.01,
-            orbit_phase: rng.gen_range(0.0..(2.0 * std::f32::consts::PI)),  // Ángulo inicial aleatorio
-            scale: 1.8, 
-            shader_mode: 4, 
-        },
-        Planet { 
-            position: Vec3::new(-26.0, 0.0, 0.0), 
-            rotation_speed: 0.1, 
-            orbit_radius: 23.8,
-            orbit_speed: 0.015,
-            orbit_phase: rng.gen_range(0.0..(2.0 * std::f32::consts::PI)),  // Ángulo inicial aleatorio
-            scale: 2.2, 
-            shader_mode: 6, 
-        },
-        Planet { 
-            position: Vec3::new(0.0, 0.0, -34.0), 
-            rotation_speed: 0.01, 
-            orbit_radius: 29.2,
-            orbit_speed: 0.015,
-            orbit_phase: rng.gen_range(0.0..(2.0 * std::f32::consts::PI)),  // Ángulo inicial aleatorio
-            scale: 1.5, 
-            shader_mode: 5, 
-        },
+        planet("sun", 0.0, 0.0),  // El Sol no orbita
+        planet("rocky", 10.0, 0.02),
+        planet("icy", 15.0, 0.01),
+        earth,
+        planet("volcanic", 29.2, 0.015),
     ]
 }
 
-fn calculate_planet_transformations(planets: &[Planet], time: u32) -> Vec<(Vec3, Vec3, f32)> {
+// Estrella compañera de un sistema binario, en órbita cercana alrededor del Sol
+pub fn companion_star() -> Planet {
+    let registry = PresetRegistry::with_builtins();
+    let preset = registry.get("companion").expect("preset integrado inexistente");
+    Planet {
+        name: "companion".to_string(),
+        ..Planet::from_preset(preset, 6.5, 0.03, 0.0)
+    }
+}
+
+fn calculate_planet_transformations(planets: &[Planet], time: f32, system_scale: &SystemScale) -> Vec<(Vec3, Vec3, f32)> {
     planets.iter().map(|planet| {
-        let angle = planet.orbit_speed * time as f32 + planet.orbit_phase; // Considera el desfase inicial
-        let orbit_x = planet.orbit_radius * angle.cos();  // Posición X en la órbita circular
-        let orbit_z = planet.orbit_radius * angle.sin();  // Posición Z en la órbita circular
+        // Anomalía media: avanza a velocidad constante y considera el desfase inicial
+        let angle = planet.orbit_speed() * time + planet.orbit_phase;
+        let orbit_radius = system_scale.orbit_radius(planet);
 
         // Devolvemos la nueva posición y transformaciones
         (
-            Vec3::new(orbit_x, 0.0, orbit_z),
-            Vec3::new(0.0, planet.rotation_speed * time as f32, 0.0),
-            planet.scale,
+            kepler_position(orbit_radius, planet.eccentricity, angle),
+            Vec3::new(0.0, planet.rotation_speed * time, 0.0),
+            system_scale.scale(planet),
         )
     }).collect()
 }
 
-fn update_planets(planets: &mut Vec<Planet>, delta_time: f32) {
-    for planet in planets.iter_mut() {
-        // Calculamos el ángulo de órbita en función del tiempo
-        planet.position.x = planet.orbit_radius * planet.orbit_speed * delta_time.cos();
-        planet.position.z = planet.orbit_radius * planet.orbit_speed * delta_time.sin();
-        
-        // Actualizamos la rotación del planeta alrededor de su eje
-        planet.position.x += planet.rotation_speed * delta_time;
+// Posición actual de cada planeta en el mundo
+fn planet_positions(scene: &Scene) -> Vec<Vec3> {
+    if scene.nbody {
+        // En modo n-cuerpos la posición la determina la integración, no la órbita analítica
+        scene.planets.iter().map(|planet| planet.position).collect()
+    } else {
+        calculate_planet_transformations(&scene.planets, scene.time, &scene.system_scale)
+            .into_iter()
+            .map(|(translation, _, _)| translation)
+            .collect()
     }
 }
 
-struct WarpDestination {
-    position: Vec3,
-    target: Vec3,  // El punto al que apunta la cámara
-}
+// Distancia mínima entre la cámara y la superficie de un planeta; mayor que el plano cercano
+// para que la superficie no se recorte al rozarla
+const CAMERA_COLLISION_MARGIN: f32 = 0.3;
+
+// Saca la cámara de cualquier planeta en el que haya entrado, llevándola a la superficie (más el
+// margen) en la dirección desde el centro del planeta. Solo se elimina la componente del
+// movimiento hacia dentro, así que la cámara se desliza sobre la superficie en vez de frenar en
+// seco. En vuelo libre `center` se mueve con `eye` para conservar la dirección de vista.
+fn resolve_camera_collisions(camera: &mut Camera, planets: &[Planet], positions: &[Vec3], system_scale: &SystemScale) {
+    for (planet, &position) in planets.iter().zip(positions) {
+        let min_distance = system_scale.scale(planet) * SPHERE_MODEL_RADIUS + CAMERA_COLLISION_MARGIN;
+        let offset = camera.eye - position;
+        let distance = offset.magnitude();
+        if distance >= min_distance {
+            continue;
+        }
 
-fn define_warp_positions(planets: &[Planet]) -> Vec<WarpDestination> {
-    vec![
-        // Vista general de todos los planetas (por encima del sistema solar)
-        WarpDestination {
-            position: Vec3::new(0.0, 50.0, 50.0),  // Vista aérea
-            target: Vec3::new(0.0, 0.0, 0.0),      // Apunta al centro del sistema solar
-        },
-        // Warp al Sol
-        WarpDestination {
-            position: Vec3::new(0.0, 0.0, 6.0),  // Cerca del Sol
-            target: Vec3::new(0.0, 0.0, 0.0),    // Apunta al centro del Sol
-        },
-        // Warp al planeta rocoso
-        WarpDestination {
-            position: Vec3::new(24.834557, 3.2328124, 0.9333064),  // A una distancia razonable del planeta rocoso
-            target: Vec3::new(10.0, 0.0, 0.0),    // Apunta al planeta rocoso
-        },
-        // Warp al planeta helado
-        WarpDestination {
-            position: Vec3::new(-1.149943e-6, 2.3341978, 44.307625), // Más alejado del planeta helado
-            target: Vec3::new(0.0, 0.0, 18.0),    // Apunta al planeta helado
-        },
-    ]
+        // Justo en el centro no hay dirección: se sale por arriba
+        let direction = if distance > f32::EPSILON { offset / distance } else { Vec3::new(0.0, 1.0, 0.0) };
+        let correction = position + direction * min_distance - camera.eye;
+        camera.eye += correction;
+        if camera.mode == CameraMode::FreeFly {
+            camera.center += correction;
+        }
+        camera.has_changed = true;
+    }
 }
 
+// Planeta siguiente (o anterior) a `selected` en una lista de `count`; sin selección se empieza
+// por un extremo. Un índice que ya no existe (p. ej. tras cargar otra escena) cuenta como ninguno.
+fn cycle_planet(selected: Option<usize>, count: usize, backwards: bool) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+    Some(match selected.filter(|&index| index < count) {
+        Some(index) if backwards => (index + count - 1) % count,
+        Some(index) => (index + 1) % count,
+        None if backwards => count - 1,
+        None => 0,
+    })
+}
 
-fn main() {
-    let mut current_warp_index = 0; // Nuevo índice para el destino warp
+// Índice del planeta más cercano a un punto; es el activo mientras no se elija otro con Tab
+fn nearest_planet(positions: &[Vec3], point: Vec3) -> usize {
+    positions.iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (*a - point).magnitude().total_cmp(&(*b - point).magnitude()))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
 
-    let mut planets = generate_planets();
-    let warp_destinations = define_warp_positions(&planets);
+// Guarda en cada planeta su posición orbital en el instante `time`, la misma que calcula
+// `calculate_planet_transformations` para el render
+fn update_planets(planets: &mut [Planet], time: f32, system_scale: &SystemScale) {
+    let transformations = calculate_planet_transformations(planets, time, system_scale);
+    for (planet, (translation, _, _)) in planets.iter_mut().zip(transformations) {
+        planet.position = translation;
+    }
+}
 
+#[derive(Serialize, Deserialize)]
+pub struct WarpDestination {
+    position: Vec3,
+    target: Vec3,  // El punto al que apunta la cámara
+}
 
-    let image = load_panoramic_image("assets/image/space.png");
-    let (image_width, image_height) = image.dimensions();
+const INSET_WIDTH: usize = 200;
+const INSET_HEIGHT: usize = 150;
+const INSET_MARGIN: usize = 10;
 
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
-    let frame_delay = Duration::from_millis(16);
+// Cámara cenital fija desde la que se observa el frustum de la cámara del juego
+const OVERVIEW_ELEVATION: f32 = 1.2;
+const OVERVIEW_RADIUS: f32 = 90.0;
+const FRUSTUM_COLOR: u32 = 0x00FF66;
 
-    // Crear un framebuffer para el renderizado
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+// Color de la mira del modo de selección; contrasta con el fondo oscuro y con los planetas
+const CROSSHAIR_COLOR: u32 = 0xFF40FF;
 
-    // Crear una ventana para mostrar la salida
-    let mut window = Window::new(
-        "Space Travel",
-        window_width,
-        window_height,
-        WindowOptions::default(),
-    )
-    .unwrap();
+// Punto de vista de la cámara secundaria que se muestra en la esquina
+#[derive(Clone, Copy, PartialEq)]
+enum InsetView {
+    Off,
+    Rear,  // Retrovisor: mira detrás de la nave
+    Sun,   // Vista fija del Sol
+}
 
-    window.set_position(500, 500);
-    window.update();
+impl InsetView {
+    fn next(self) -> Self {
+        match self {
+            InsetView::Off => InsetView::Rear,
+            InsetView::Rear => InsetView::Sun,
+            InsetView::Sun => InsetView::Off,
+        }
+    }
 
-    framebuffer.set_background_color(0x000000);
+    fn camera(self, camera: &Camera) -> Option<Camera> {
+        match self {
+            InsetView::Off => None,
+            InsetView::Rear => {
+                let mut rear = camera.clone();
+                rear.look_at(camera.eye - camera.forward());
+                Some(rear)
+            }
+            InsetView::Sun => Some(Camera::new(
+                Vec3::new(0.0, 6.0, 14.0),
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            )),
+        }
+    }
+}
 
-    // Parámetros de la cámara
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 0.0, 5.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
+// Post-procesos y superposiciones que se activan con las teclas y que `render_frame` aplica
+// después del render de la escena
+struct FrameEffects {
+    god_rays: GodRays,
+    god_rays_enabled: bool,
+    lens_flare: bool,
+    bloom: bool,
+    labels: bool,
+    minimap: bool,
+    hud: bool,
+    tile_heatmap: bool,
+    overview: bool,
+    inset_view: InsetView,
+    anti_alias: AntiAlias,
+}
 
-    let mut current_position = camera.eye;
-    let mut current_target = camera.center;
+impl FrameEffects {
+    // Los valores con los que arranca el programa
+    fn new(god_rays: GodRays) -> Self {
+        FrameEffects {
+            god_rays,
+            god_rays_enabled: false,
+            lens_flare: true,
+            bloom: true,
+            labels: true,
+            minimap: false,
+            hud: true,
+            tile_heatmap: false,
+            overview: false,
+            inset_view: InsetView::Off,
+            anti_alias: AntiAlias::Off,
+        }
+    }
 
-    // Cargar la esfera desde el archivo OBJ
-    let sphere_obj = Obj::load("assets/models/sphere-1.obj").expect("Error al cargar sphere-1.obj");
-    let vertex_array_sphere = sphere_obj.get_vertex_array();
+    // Cámara desde la que se ve el frame: la del juego o, en la vista general, una cenital fija
+    fn overview_camera(&self) -> Option<Camera> {
+        self.overview.then(|| {
+            Camera::from_spherical(Vec3::zeros(), std::f32::consts::PI / 2.0, OVERVIEW_ELEVATION, OVERVIEW_RADIUS)
+        })
+    }
+}
 
-    // Cargar la nave desde el archivo OBJ
-    let ship_obj = Obj::load("assets/models/nave.obj").expect("Error al cargar nave.obj");
-    let vertex_array_ship = ship_obj.get_vertex_array();
+// Framebuffers en los que se compone un frame, con sus estadísticas: el de la imagen final, el
+// grande del SSAA y el de la vista secundaria
+struct FrameTargets {
+    framebuffer: Framebuffer,
+    stats: RenderStats,
+    ssaa_framebuffer: Framebuffer,
+    ssaa_stats: RenderStats,
+    inset_framebuffer: Framebuffer,
+    inset_stats: RenderStats,
+}
 
-    let mut time = 0;
-    let shader_mode = 0;
+impl FrameTargets {
+    fn new(width: usize, height: usize) -> Self {
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.set_background_color(0x000000);
+        let ssaa_framebuffer = Framebuffer::supersampled(width, height, SSAA_FACTOR);
+        FrameTargets {
+            framebuffer,
+            stats: RenderStats::new(width, height),
+            ssaa_stats: RenderStats::new(ssaa_framebuffer.width, ssaa_framebuffer.height),
+            ssaa_framebuffer,
+            inset_framebuffer: Framebuffer::new(INSET_WIDTH, INSET_HEIGHT),
+            inset_stats: RenderStats::new(INSET_WIDTH, INSET_HEIGHT),
+        }
+    }
 
-    while window.is_open() {
-        let delta_time = 1.0 / 60.0; // Tiempo entre frames (aproximado)
+    // Rehace los framebuffers de la imagen y del SSAA para una ventana de `width` x `height`
+    fn resize(&mut self, width: usize, height: usize) {
+        self.framebuffer.resize(width, height);
+        self.ssaa_framebuffer.resize(width * SSAA_FACTOR, height * SSAA_FACTOR);
+        self.stats = RenderStats::new(self.framebuffer.width, self.framebuffer.height);
+        self.ssaa_stats = RenderStats::new(self.ssaa_framebuffer.width, self.ssaa_framebuffer.height);
+    }
 
-        // Manejar la entrada del usuario
-        handle_input(
-            &window,
-            &mut camera,
-            &warp_destinations,
-            delta_time,
-            &mut current_position,
-            &mut current_target,
-            &mut current_warp_index,
-        );
+    // Estadísticas del framebuffer en el que se renderizó la escena con `anti_alias`
+    fn scene_stats(&self, anti_alias: AntiAlias) -> &RenderStats {
+        if anti_alias == AntiAlias::Ssaa { &self.ssaa_stats } else { &self.stats }
+    }
+}
 
-        time += 1;
+// Destino de warp descrito en coordenadas esféricas alrededor del punto al que se mira
+fn warp_around(target: Vec3, azimuth: f32, elevation: f32, radius: f32) -> WarpDestination {
+    let camera = Camera::from_spherical(target, azimuth, elevation, radius);
+    WarpDestination {
+        position: camera.eye,
+        target: camera.center,
+    }
+}
 
-        framebuffer.clear();
+// Los warps 3 y 4 apuntan a la posición de los planetas rocoso y helado (los dos primeros tras el
+// Sol) cuando se definen, así que `planets` debe tener ya sus posiciones (`update_planets`)
+fn define_warp_positions(planets: &[Planet]) -> Vec<WarpDestination> {
+    let half_pi = std::f32::consts::FRAC_PI_2;
+    let position = |index: usize| planets.get(index).map_or(Vec3::zeros(), |planet| planet.position);
+    vec![
+        // Vista general de todos los planetas (por encima del sistema solar, a 45°)
+        warp_around(Vec3::new(0.0, 0.0, 0.0), half_pi, half_pi / 2.0, 70.0),
+        // Warp al Sol
+        warp_around(Vec3::new(0.0, 0.0, 0.0), half_pi, 0.0, 6.0),
+        // Warp al planeta rocoso, un poco por encima de su órbita
+        warp_around(position(1), 0.06, 0.2, 15.0),
+        // Warp al planeta helado, más alejado
+        warp_around(position(2), half_pi, 0.09, 26.0),
+    ]
+}
 
-        update_planets(&mut planets, delta_time);
 
-        // Crear las matrices de transformación para la esfera
-        let translation_sphere = Vec3::new(0.0, 0.0, 0.0);
-        let rotation_sphere = Vec3::new(0.0, 0.0, 0.0);
-        let scale_sphere = 1.0f32;
+// Recursos cargados una sola vez al inicio y compartidos por todos los frames
+struct Assets {
+    sphere_lods: SphereLods,  // La esfera de los planetas en varios niveles de detalle
+    vertex_array_ship: Vec<Vertex>,
+    vertex_array_asteroid: Vec<Vertex>,
+    ship_aabb: (Vec3, Vec3),  // Caja de la nave en coordenadas del objeto, para descartarla si no se ve
+    ship_texture: Option<Arc<RgbImage>>,  // Solo si nave.mtl tiene textura y el modelo tiene UVs
+    terrain_normal_map: Arc<RgbImage>,
+    background: Background,
+    noise: Arc<FastNoiseLite>,
+}
 
-        let model_matrix_sphere =
-            create_model_matrix(translation_sphere, scale_sphere, rotation_sphere);
+impl Assets {
+    fn load(background: Background) -> Self {
+        // Cargar la esfera desde el archivo OBJ
+        let sphere_obj = Obj::load("assets/models/sphere-1.obj").expect("Error al cargar sphere-1.obj");
+
+        // Cargar la nave desde el archivo OBJ
+        let ship_obj = Obj::load("assets/models/nave.obj").expect("Error al cargar nave.obj");
+
+        Assets {
+            sphere_lods: SphereLods::new(sphere_obj.get_vertex_array()),
+            vertex_array_ship: ship_obj.get_vertex_array(),
+            vertex_array_asteroid: asteroid_mesh(BELT_SEED),
+            ship_aabb: ship_obj.aabb(),
+            ship_texture: ship_obj.diffuse_texture()
+                .filter(|_| ship_obj.has_texcoords())
+                .map(|texture| Arc::new(texture.to_rgb8())),
+            terrain_normal_map: Arc::new(terrain_normal_map(TERRAIN_NORMAL_MAP_WIDTH, TERRAIN_NORMAL_MAP_HEIGHT)),
+            background,
+            noise: Arc::new(create_noise()),
+        }
+    }
+}
 
-        // Crear las matrices de transformación para la nave
+// Ajustes de renderizado elegidos en tiempo de ejecución, comunes a todas las vistas
+struct RenderOptions {
+    lights: Arc<Vec<StarLight>>,
+    background_sampling: BackgroundSampling,
+    debug_normals: bool,
+    brightness: f32,
+    budget: RenderBudget,
+    backface_culling: bool,
+    show_orbits: bool,
+    flat_shading: bool,
+    gamma: f32,
+    render_mode: RenderMode,
+    fog_color: Color,
+    fog_density: f32,
+    raster_tiles: usize,
+    tone_mapping: ToneMapping,
+    eclipse_shadows: bool,  // Sombras suaves de unos cuerpos sobre otros (F3); cuestan por fragmento
+    spotlight_cone: f32,    // Semiapertura en radianes del foco de la nave (F1); 0 lo apaga
+    lod_crossfade: bool,    // Fundir las mallas de dos niveles de detalle en lugar de saltar entre ellas
+}
 
-        // Calcular la posición de la nave en relación con la cámara
-        let camera_forward = (camera.center - camera.eye).normalize(); // Dirección en la que mira la cámara
-        let offset = camera_forward * 1.5; // Posición de la nave, 2 unidades delante de la cámara
-        let translation_ship = camera.eye + camera_forward * 1.5 + Vec3::new(0.0, -0.5, 0.0);
+// Radio de la estrella principal, la luz cuyos eclipses se calculan; None si no hay estrellas
+fn eclipse_light(scene: &Scene) -> Option<f32> {
+    scene.planets.iter()
+        .find(|planet| planet.shader_mode == STAR_SHADER_MODE)
+        .map(|star| scene.system_scale.scale(star) * SPHERE_MODEL_RADIUS)
+}
 
-        let rotation_ship = Vec3::new(0.0, 0.0, 0.0); // Rotación animada
-        let scale_ship = 0.05f32;
+// Centro y radio de cada planeta y luna, los posibles causantes de un eclipse
+fn eclipse_occluders(scene: &Scene) -> Vec<(Vec3, f32)> {
+    let mut occluders = Vec::new();
+    for (planet, position) in scene.planets.iter().zip(planet_positions(scene)) {
+        if planet.shader_mode == STAR_SHADER_MODE {
+            continue;
+        }
+        let scale = scene.system_scale.scale(planet);
+        occluders.push((position, scale * SPHERE_MODEL_RADIUS));
+        for moon in &planet.moons {
+            let (moon_position, _, moon_scale) = moon.transformation(planet, position, scale, scene.time);
+            occluders.push((moon_position, moon_scale * SPHERE_MODEL_RADIUS));
+        }
+    }
+    occluders
+}
 
-        let model_matrix_ship = create_model_matrix(translation_ship, scale_ship, rotation_ship);
+// Tamaño aparente de un objeto de radio `radius` en `position` visto desde `eye`; el presupuesto
+// de render descarta primero los de menor valor
+fn screen_importance(position: Vec3, radius: f32, eye: Vec3) -> f32 {
+    radius / (position - eye).magnitude().max(1e-3)
+}
 
-        let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-        let projection_matrix =
-            create_perspective_matrix(window_width as f32, window_height as f32);
-        let viewport_matrix =
-            create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
+// Alabeo y cabeceo de la nave por radián que gira la cámara en un frame, y sus límites
+const SHIP_BANK_GAIN: f32 = 10.0;
+const SHIP_PITCH_GAIN: f32 = 6.0;
+const SHIP_MAX_BANK: f32 = 0.6;
+const SHIP_MAX_PITCH: f32 = 0.3;
+
+// Fracción del camino hacia el ángulo objetivo que recorre la nave en cada frame de 60 Hz
+const SHIP_EASING: f32 = 0.12;
+
+// Inclinación animada de la nave: se alabea al girar la cámara a los lados y cabecea al mirar
+// arriba o abajo, y vuelve poco a poco a nivelarse cuando la cámara deja de girar
+#[derive(Default)]
+struct ShipState {
+    roll: f32,   // Radianes alrededor de la dirección de vista; positivo hacia la derecha
+    pitch: f32,  // Radianes alrededor del eje derecho de la cámara; positivo con el morro arriba
+    previous_forward: Option<Vec3>,
+}
 
-        // Preparar las uniformes para el shader
-        let uniforms_sphere = Uniforms {
-            model_matrix: model_matrix_sphere,
-            view_matrix,
-            projection_matrix,
-            viewport_matrix,
-            time,
-            noise: create_noise(),
-            shader_mode,
+impl ShipState {
+    // Acerca el alabeo y el cabeceo a los que corresponden al giro de `camera` desde el frame
+    // anterior, que duró `frames` frames de 60 Hz
+    fn update(&mut self, camera: &Camera, frames: f32) {
+        let forward = camera.forward();
+        let up = camera.up.normalize();
+        let (yaw, pitch) = match self.previous_forward.replace(forward) {
+            Some(previous) => (
+                // Giro alrededor de `up` (positivo hacia la izquierda) y cambio de elevación
+                up.dot(&previous.cross(&forward)).atan2(previous.dot(&forward)),
+                forward.dot(&up).clamp(-1.0, 1.0).asin() - previous.dot(&up).clamp(-1.0, 1.0).asin(),
+            ),
+            None => (0.0, 0.0),
         };
 
-        // Preparar las uniformes para la nave
-        let uniforms_ship = Uniforms {
-            model_matrix: model_matrix_ship,
-            view_matrix,
-            projection_matrix,
-            viewport_matrix,
-            time,
-            noise: create_noise(),
-            shader_mode: 8,  
-        };
+        // Las ganancias son por frame de 60 Hz: con frames más largos el giro se reparte entre ellos
+        let frames = frames.max(f32::EPSILON);
+        let target_roll = (-yaw / frames * SHIP_BANK_GAIN).clamp(-SHIP_MAX_BANK, SHIP_MAX_BANK);
+        let target_pitch = (pitch / frames * SHIP_PITCH_GAIN).clamp(-SHIP_MAX_PITCH, SHIP_MAX_PITCH);
+        let easing = 1.0 - (1.0 - SHIP_EASING).powf(frames);
+        self.roll += (target_roll - self.roll) * easing;
+        self.pitch += (target_pitch - self.pitch) * easing;
+    }
 
-        // Renderizar la esfera
-        render(
-            &mut framebuffer,
-            &uniforms_sphere,
-            &vertex_array_sphere,
-            &image,
-            image_width,
-            image_height,
-        );
+    // Rotación en el espacio del modelo de la nave (sus ejes coinciden con los del mundo): alabeo
+    // alrededor de la vista de `camera` y cabeceo alrededor de su eje derecho
+    fn rotation(&self, camera: &Camera) -> Mat4 {
+        nalgebra_glm::rotation(self.roll, &camera.forward()) * nalgebra_glm::rotation(self.pitch, &camera.right())
+    }
+}
+
+// Renderiza todos los objetos de la escena vistos desde `view_camera`.
+// La nave siempre se coloca delante de la cámara principal de la escena, inclinada según `ship`.
+fn render_scene(
+    framebuffer: &mut Framebuffer,
+    scene: &Scene,
+    view_camera: &Camera,
+    assets: &Assets,
+    stats: &mut RenderStats,
+    options: &RenderOptions,
+    ship: &ShipState,
+) {
+    let vertex_array_sphere = assets.sphere_lods.full();
+    let vertex_array_ship = &assets.vertex_array_ship;
+    // Los shaders animan sus efectos con un contador entero de frames simulados
+    let time = scene.time as u32;
+    let camera = &scene.camera;
+
+    // Crear las matrices de transformación para la esfera
+    let translation_sphere = Vec3::new(0.0, 0.0, 0.0);
+    let rotation_sphere = Vec3::new(0.0, 0.0, 0.0);
+    let scale_sphere = 1.0f32;
+
+    let model_matrix_sphere =
+        create_model_matrix(translation_sphere, scale_sphere, rotation_sphere);
+
+    // Crear las matrices de transformación para la nave
+
+    // Calcular la posición de la nave en relación con la cámara
+    let camera_forward = camera.forward(); // Dirección en la que mira la cámara
+    let translation_ship = camera.eye + camera_forward * 1.5 + Vec3::new(0.0, -0.5, 0.0);
+
+    // El foco de la nave sale de ella hacia donde mira la cámara
+    let (spotlight_position, spotlight_dir) = (translation_ship, camera_forward);
+
+    let rotation_ship = Vec3::new(0.0, 0.0, 0.0);
+    let scale_ship = 0.05f32;
+
+    // La escala es uniforme, así que la inclinación puede aplicarse en el espacio del modelo
+    let model_matrix_ship = create_model_matrix(translation_ship, scale_ship, rotation_ship) * ship.rotation(camera);
+
+    let view_matrix = create_view_matrix(view_camera.eye, view_camera.center, view_camera.up);
+    let projection_matrix =
+        create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32, view_camera.fov);
+    let viewport_matrix =
+        create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+    let view_projection = projection_matrix * view_matrix;
+
+    // El Sol (la primera estrella) es la fuente de luz de los shaders de planeta
+    let light_position = options.lights.first().map_or(Vec3::zeros(), |light| light.position);
+
+    // Preparar las uniformes para el shader; las de los demás objetos parten de estas
+    let uniforms_sphere = Uniforms {
+        model_matrix: model_matrix_sphere,
+        normal_matrix: create_normal_matrix(&model_matrix_sphere),
+        view_matrix,
+        projection_matrix,
+        viewport_matrix,
+        time,
+        noise: assets.noise.clone(),
+        shader_mode: scene.shader_mode,
+        noise_zoom: 1.0,
+        cloud_coverage: DEFAULT_CLOUD_COVERAGE,
+        lights: options.lights.clone(),
+        star_color: None,
+        star_temperature: 0.0,
+        debug_normals: options.debug_normals,
+        brightness: options.brightness,
+        camera_position: view_camera.eye,
+        light_position,
+        spotlight_position,
+        spotlight_dir,
+        spotlight_cone: options.spotlight_cone,
+        backface_culling: options.backface_culling,
+        flat_shading: options.flat_shading,
+        render_mode: options.render_mode,
+        fog_color: options.fog_color,
+        fog_density: options.fog_density,
+        raster_tiles: options.raster_tiles,
+        tone_mapping: options.tone_mapping,
+        ring_shadow: None,
+        eclipse_shadow: None,
+        opacity: 1.0,
+        gamma: options.gamma,
+        texture: None,
+        normal_map: None,
+    };
+
+    // Preparar las uniformes para la nave
+    let uniforms_ship = Uniforms {
+        model_matrix: model_matrix_ship,
+        normal_matrix: create_normal_matrix(&model_matrix_ship),
+        shader_mode: if assets.ship_texture.is_some() { 10 } else { 8 },
+        texture: assets.ship_texture.clone(),
+        ..uniforms_sphere.clone()
+    };
+
+    // El fondo va primero; los objetos lo tapan con la prueba de profundidad
+    match &assets.background {
+        Background::Image(image) => draw_background(framebuffer, image, options.background_sampling, options.gamma),
+        Background::Procedural(starfield) => draw_starfield(framebuffer, starfield, time),
+    }
+
+    // Renderizar la esfera
+    render(
+        framebuffer,
+        &uniforms_sphere,
+        vertex_array_sphere,
+        stats,
+    );
 
-        // Renderizar la nave
+    // Renderizar la nave. Es alargada, así que su caja la descarta mejor que una esfera envolvente
+    // cuando no cae en la vista (desde la vista general o el retrovisor)
+    let (ship_min, ship_max) = assets.ship_aabb;
+    if aabb_outside_frustum(&view_projection, transform_aabb(ship_min, ship_max, &model_matrix_ship)) {
+        stats.culled_objects += 1;
+    } else {
         render(
-            &mut framebuffer,
+            framebuffer,
             &uniforms_ship,
-            &vertex_array_ship,
-            &image,
-            image_width,
-            image_height,
+            vertex_array_ship,
+            stats,
         );
+    }
 
-        // Obtener las transformaciones para los planetas
-        let transformations = calculate_planet_transformations(&planets, time);
-        for (planet, (translation, rotation, scale)) in planets.iter().zip(transformations) {
-            let model_matrix = create_model_matrix(translation, scale, rotation);
-
-            let uniforms = Uniforms {
-                model_matrix,
-                view_matrix,
-                projection_matrix,
-                viewport_matrix,
-                time,
-                noise: create_noise(),
-                shader_mode: planet.shader_mode,
-            };
+    // Órbitas antes que los planetas; en modo n-cuerpos las trayectorias ya no son circunferencias
+    if options.show_orbits && !scene.nbody {
+        for planet in &scene.planets {
+            let orbit_radius = scene.system_scale.orbit_radius(planet);
+            if orbit_radius > 0.0 {
+                draw_orbit(framebuffer, &uniforms_sphere, orbit_radius, planet.eccentricity, ORBIT_SEGMENTS);
+            }
+        }
+    }
+
+    // Obtener las transformaciones para los planetas
+    let transformations = calculate_planet_transformations(&scene.planets, scene.time, &scene.system_scale);
+    let positions = planet_positions(scene);
+    let mut render_list: Vec<_> = scene.planets.iter().zip(transformations).zip(positions).collect();
+
+    // Con presupuesto, los planetas más grandes en pantalla se dibujan primero
+    if options.budget.is_limited() {
+        let importance = |((_, (_, _, scale)), translation): &((&Planet, (Vec3, Vec3, f32)), Vec3)| {
+            screen_importance(*translation, *scale * SPHERE_MODEL_RADIUS, view_camera.eye)
+        };
+        render_list.sort_by(|a, b| importance(b).total_cmp(&importance(a)));
+    }
+
+    // Cuerpos que pueden eclipsar a otros: planetas y lunas, sin las estrellas
+    let eclipse_light = options.eclipse_shadows.then(|| eclipse_light(scene)).flatten();
+    let occluders = if eclipse_light.is_some() { eclipse_occluders(scene) } else { Vec::new() };
+    let eclipse_shadow = |receiver: Vec3| {
+        eclipse_light.and_then(|light_radius| EclipseShadow::new(receiver, &occluders, light_position, light_radius))
+    };
+
+    let planet_count = render_list.len();
+    let mut ring_meshes = Vec::new();  // Se dibujan al final por ser translúcidos
+    for (index, ((planet, (_, rotation, scale)), translation)) in render_list.into_iter().enumerate() {
+        if stats.over_budget(&options.budget) {
+            stats.skipped_objects += planet_count - index;
+            break;
+        }
 
+        let model_matrix = create_tilted_model_matrix(translation, scale, planet.axial_tilt, rotation.y);
+
+        // Los anillos están en el ecuador: siguen la inclinación del eje del planeta
+        let rings = planet.rings().map(|(inner_radius, outer_radius)| {
+            let ring_tilt = create_model_matrix(Vec3::zeros(), 1.0, Vec3::new(RING_TILT, 0.0, 0.0));
+            let ring_matrix = create_model_matrix(translation, scale, planet.axial_tilt) * ring_tilt;
+            (ring_matrix, inner_radius * SPHERE_MODEL_RADIUS, outer_radius * SPHERE_MODEL_RADIUS)
+        });
+
+        let uniforms = Uniforms {
+            model_matrix,
+            normal_matrix: create_normal_matrix(&model_matrix),
+            shader_mode: planet.shader_mode,
+            noise_zoom: planet.noise_zoom,
+            cloud_coverage: planet.cloud_coverage,
+            star_color: planet.star_color,
+            star_temperature: planet.star_temperature,
+            ring_shadow: rings.map(|(ring_matrix, inner_radius, outer_radius)| RingShadow::new(&ring_matrix, inner_radius, outer_radius)),
+            eclipse_shadow: eclipse_shadow(translation),
+            normal_map: Some(assets.terrain_normal_map.clone()),  // Solo lo usa el terreno rocoso
+            ..uniforms_sphere.clone()
+        };
 
-            render(
-                &mut framebuffer,
+        // Un planeta fuera del frustum no se transforma ni se rasteriza; sus lunas y anillos se
+        // comprueban aparte porque pueden verse aunque el planeta no
+        if sphere_outside_frustum(&view_projection, translation, scale * SPHERE_MODEL_RADIUS) {
+            stats.culled_objects += 1;
+        } else {
+            render_sphere(
+                framebuffer,
                 &uniforms,
-                &vertex_array_sphere, // Usa la esfera como modelo base para los planetas
-                &image,
-                image_width,
-                image_height,
+                // Esfera con menos triángulos cuanto más pequeño se ve el planeta
+                assets.sphere_lods.select_lod((translation - view_camera.eye).magnitude(), scale, &planet.lod, options.lod_crossfade),
+                translation,
+                scale * SPHERE_MODEL_RADIUS,
+                stats,
             );
         }
 
-        // Actualizar la ventana con el contenido del framebuffer
-        window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
-            .unwrap();
+        if let Some((ring_matrix, inner_radius, outer_radius)) = rings {
+            if !sphere_outside_frustum(&view_projection, translation, scale * outer_radius) {
+                ring_meshes.push((translation, ring_matrix, ring_mesh(inner_radius, outer_radius, RING_SEGMENTS)));
+            }
+        }
+
+        // Las lunas siguen la posición actual del planeta, también en el modo n-cuerpos
+        for moon in &planet.moons {
+            let (moon_translation, moon_rotation, moon_scale) = moon.transformation(planet, translation, scale, scene.time);
+            if sphere_outside_frustum(&view_projection, moon_translation, moon_scale * SPHERE_MODEL_RADIUS) {
+                stats.culled_objects += 1;
+                continue;
+            }
+
+            let moon_model_matrix = create_model_matrix(moon_translation, moon_scale, moon_rotation);
+            let uniforms_moon = Uniforms {
+                model_matrix: moon_model_matrix,
+                normal_matrix: create_normal_matrix(&moon_model_matrix),
+                shader_mode: moon.shader_mode,
+                noise_zoom: 1.0,
+                star_color: None,
+                star_temperature: 0.0,
+                eclipse_shadow: eclipse_shadow(moon_translation),
+                ..uniforms.clone()
+            };
 
-        std::thread::sleep(frame_delay);
+            render_sphere(
+                framebuffer,
+                &uniforms_moon,
+                assets.sphere_lods.select_lod((moon_translation - view_camera.eye).magnitude(), moon_scale, &moon.lod, options.lod_crossfade),
+                moon_translation,
+                moon_scale * SPHERE_MODEL_RADIUS,
+                stats,
+            );
+        }
     }
-}
 
-fn handle_input(
-    window: &Window,
-    camera: &mut Camera,
-    warp_destinations: &[WarpDestination],
-    delta_time: f32,
-    current_position: &mut Vec3,
-    current_target: &mut Vec3,
-    current_warp_index: &mut usize, 
-) {
-    let movement_speed = 1.0;
-    let rotation_speed = std::f32::consts::PI / 50.0;
-    let zoom_speed = 0.1;
-    let keys = [Key::Key1, Key::Key2, Key::Key3, Key::Key4];
+    // Núcleo del cometa: una esfera pequeña con el shader helado
+    if let Some(comet) = &scene.comet {
+        let comet_translation = comet.position(scene.time);
+        if sphere_outside_frustum(&view_projection, comet_translation, comet.scale * SPHERE_MODEL_RADIUS) {
+            stats.culled_objects += 1;
+        } else {
+            let comet_model_matrix = create_model_matrix(comet_translation, comet.scale, Vec3::zeros());
+            let uniforms_comet = Uniforms {
+                model_matrix: comet_model_matrix,
+                normal_matrix: create_normal_matrix(&comet_model_matrix),
+                shader_mode: 4,
+                ..uniforms_sphere.clone()
+            };
 
-    // Camera orbit controls
-    if window.is_key_down(Key::Left) {
-        camera.orbit(rotation_speed, 0.0);
+            render_sphere(
+                framebuffer,
+                &uniforms_comet,
+                assets.sphere_lods.select_lod((comet_translation - view_camera.eye).magnitude(), comet.scale, &comet.lod, options.lod_crossfade),
+                comet_translation,
+                comet.scale * SPHERE_MODEL_RADIUS,
+                stats,
+            );
+        }
     }
-    if window.is_key_down(Key::Right) {
-        camera.orbit(-rotation_speed, 0.0);
+
+    // Cinturón de asteroides: una sola malla instanciada con una matriz por asteroide
+    if !scene.asteroids.is_empty() {
+        let mut uniforms_asteroids = Uniforms {
+            model_matrix: Mat4::identity(),
+            normal_matrix: Mat3::identity(),
+            shader_mode: 9,
+            ..uniforms_sphere.clone()
+        };
+
+        let mut instances = asteroid_instances(&scene.asteroids, scene.time);
+        if options.budget.is_limited() {
+            // La escala de cada asteroide es la longitud de la primera columna de su matriz
+            let importance = |matrix: &Mat4| {
+                let position = Vec3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]);
+                let scale = Vec3::new(matrix[(0, 0)], matrix[(1, 0)], matrix[(2, 0)]).magnitude();
+                screen_importance(position, scale, view_camera.eye)
+            };
+            instances.sort_by(|a, b| importance(b).total_cmp(&importance(a)));
+        }
+
+        render_instanced(
+            framebuffer,
+            &mut uniforms_asteroids,
+            &assets.vertex_array_asteroid,
+            &instances,
+            stats,
+            &options.budget,
+        );
+    }
+
+    // Anillos: después de todo lo opaco y del más lejano al más cercano para que se mezclen bien
+    ring_meshes.sort_by(|(a, _, _), (b, _, _)| (b - view_camera.eye).magnitude().total_cmp(&(a - view_camera.eye).magnitude()));
+    for (_, model_matrix, vertex_array_ring) in ring_meshes {
+        let uniforms_ring = Uniforms {
+            model_matrix,
+            normal_matrix: create_normal_matrix(&model_matrix),
+            shader_mode: RING_SHADER_MODE,
+            backface_culling: false,  // El disco se ve por las dos caras
+            ..uniforms_sphere.clone()
+        };
+
+        render_transparent(framebuffer, &uniforms_ring, &vertex_array_ring, stats);
+    }
+
+    // Estelas de los planetas: translúcidas, después de lo opaco
+    for (planet, position) in scene.planets.iter().zip(planet_positions(scene)) {
+        draw_trail(framebuffer, &uniforms_sphere, position, &planet.trail);
+    }
+
+    // Cola del cometa: aditiva, así que no importa el orden de las partículas
+    if let Some(comet) = &scene.comet {
+        draw_tail(framebuffer, &uniforms_sphere, &comet.tail(scene.time));
+    }
+}
+
+fn default_scene(planet_seed: u64) -> Scene {
+    let mut planets = generate_planets(planet_seed);
+    update_planets(&mut planets, 0.0, &SystemScale::default());
+    let warp_destinations = define_warp_positions(&planets);
+
+    // Parámetros de la cámara
+    let camera = Camera::new(
+        Vec3::new(0.0, 0.0, 5.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+
+    let mut scene = Scene::new(planets, warp_destinations, camera);
+    scene.asteroids = generate_asteroid_belt(DEFAULT_ASTEROID_COUNT, BELT_INNER_RADIUS, BELT_OUTER_RADIUS, BELT_SEED);
+    scene.comet = Some(Comet::default());
+    scene
+}
+
+// Límites de la escala de tiempo (potencias de 2, para que se muestren exactas en el título)
+const MIN_TIME_SCALE: f32 = 1.0 / 64.0;
+const MAX_TIME_SCALE: f32 = 16.0;
+
+// Radianes de giro de la cámara por píxel que se mueve el ratón
+const MOUSE_SENSITIVITY: f32 = 0.005;
+
+// Texto del HUD y su sombra, a esta distancia en píxeles de la esquina superior izquierda
+const HUD_COLOR: u32 = 0xE0E0E0;
+const HUD_SHADOW_COLOR: u32 = 0x000000;
+const HUD_MARGIN: usize = 6;
+
+// Etiquetas con el nombre de los planetas (F8): separación sobre la superficie en unidades del
+// mundo y distancia hasta la que se ven con el brillo completo; más lejos se atenúan hasta el mínimo
+const LABEL_OFFSET: f32 = 0.4;
+const LABEL_FULL_DISTANCE: f32 = 25.0;
+const LABEL_MIN_BRIGHTNESS: f32 = 0.3;
+
+// Unidades que avanza la cámara de vuelo libre por frame de 60 Hz
+const FREE_FLY_SPEED: f32 = 0.5;
+
+// Duración del viaje animado hasta un destino de warp, en segundos
+const WARP_DURATION: f32 = 1.5;
+
+// Resplandor (F7): brillo a partir del que resplandece un píxel, radio del desenfoque en
+// píxeles e intensidad con la que se suma
+const BLOOM_THRESHOLD: f32 = 0.5;
+const BLOOM_RADIUS: usize = 32;
+const BLOOM_INTENSITY: f32 = 2.5;
+
+// Archivo opcional con teclas personalizadas, en el directorio desde el que se ejecuta
+const KEY_BINDINGS_FILE: &str = "keys.ron";
+
+// Archivo de planetas que se vigila cuando no se pasa `--planets`, en el mismo directorio
+const PLANETS_FILE: &str = "planets.ron";
+
+// Intervalo objetivo entre frames (~60 fps)
+const FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+// Factor aplicado al zoom de ruido en cada pulsación de Z / X
+const NOISE_ZOOM_STEP: f32 = 1.1;
+
+// Factor y límites del brillo global que se ajusta con + / -
+const BRIGHTNESS_STEP: f32 = 1.1;
+const MIN_BRIGHTNESS: f32 = 0.1;
+const MAX_BRIGHTNESS: f32 = 8.0;
+
+// Grados por frame de 60 Hz que se abre o cierra el campo de visión al mantener su tecla
+const FOV_SPEED: f32 = 0.5;
+
+// Paso de integración del modo n-cuerpos, en las mismas unidades que `time` (frames)
+const NBODY_TIME_STEP: f32 = 1.0;
+
+// Alterna entre órbitas analíticas y gravedad newtoniana entre todos los cuerpos
+fn toggle_nbody(scene: &mut Scene) {
+    scene.nbody = !scene.nbody;
+
+    if scene.nbody {
+        start_nbody(scene);
+        println!("Modo n-cuerpos activado (energía inicial {:.6})", scene.nbody_initial_energy);
+    } else {
+        let energy = total_energy(&scene.planets);
+        let drift = (energy - scene.nbody_initial_energy) / scene.nbody_initial_energy.abs();
+        println!("Modo n-cuerpos desactivado (energía final {:.6}, deriva {:.3}%)", energy, drift * 100.0);
+    }
+}
+
+// Coloca los planetas en su posición orbital actual con la velocidad de una órbita circular, como
+// punto de partida de la integración n-cuerpos
+fn start_nbody(scene: &mut Scene) {
+    let positions: Vec<Vec3> = calculate_planet_transformations(&scene.planets, scene.time, &scene.system_scale)
+        .into_iter()
+        .map(|(translation, _, _)| translation)
+        .collect();
+    init_nbody(&mut scene.planets, &positions);
+    scene.nbody_initial_energy = total_energy(&scene.planets);
+}
+
+// Vuelve a leer los planetas de `path` (R o al guardar el archivo vigilado). Cada planeta
+// nuevo conserva la anomalía media que tenía ahora el que ocupaba su índice, de modo que los que
+// no cambian siguen donde estaban; los que sobran empiezan en su `orbit_phase`. Si el archivo no
+// es válido se avisa y se conservan los planetas actuales.
+fn reload_planets(scene: &mut Scene, path: &str, binary: bool) {
+    let mut planets = match load_planets(path) {
+        Ok(planets) => planets,
+        Err(err) => {
+            eprintln!("Aviso: no se pudieron recargar los planetas de {} ({}); se conservan los actuales", path, err);
+            return;
+        }
+    };
+    if binary {
+        planets.push(companion_star());
+    }
+
+    for (planet, old) in planets.iter_mut().zip(&scene.planets) {
+        let anomaly = old.orbit_speed() * scene.time + old.orbit_phase;
+        planet.orbit_phase = anomaly - planet.orbit_speed() * scene.time;
+    }
+    scene.planets = planets;
+
+    // En modo n-cuerpos la integración vuelve a empezar desde las órbitas de los planetas nuevos
+    if scene.nbody {
+        start_nbody(scene);
+    }
+    println!("Planetas recargados de {}", path);
+}
+
+// Guarda el estado actual de la escena en un archivo JSON con marca de tiempo
+fn dump_scene(scene: &Scene) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let path = format!("scene_{}.json", timestamp);
+
+    match scene.save_json(&path) {
+        Ok(()) => println!("Escena guardada en {}", path),
+        Err(err) => eprintln!("No se pudo guardar la escena en {}: {}", path, err),
+    }
+}
+
+// Nombre con marca de tiempo para las capturas de la ventana (P)
+fn screenshot_path() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    format!("screenshot_{}.png", timestamp)
+}
+
+// Guarda la imagen compuesta en un PNG en `path` e informa del resultado por consola
+fn save_screenshot(framebuffer: &Framebuffer, path: &str) -> ImageResult<()> {
+    let result = framebuffer.save_png(path);
+    match &result {
+        Ok(()) => println!("Captura guardada en {}", path),
+        Err(err) => eprintln!("No se pudo guardar la captura en {}: {}", path, err),
+    }
+    result
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // `--planet-seed n` cambia la disposición inicial de los planetas integrados
+    let planet_seed = match planet_seed_from_args(&args) {
+        Ok(seed) => seed,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // `--scene archivo.json` carga una escena guardada previamente con F5
+    let mut scene = match arg_value(&args, "--scene") {
+        Some(path) => Scene::load_json(path).unwrap_or_else(|err| {
+            eprintln!("No se pudo cargar la escena {}: {}", path, err);
+            default_scene(planet_seed)
+        }),
+        None => default_scene(planet_seed),
+    };
+    // Una escala de tiempo guardada fuera de los límites de las teclas se lleva al más cercano
+    scene.time_scale = scene.time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+
+    // `--asteroids N` regenera el cinturón con N asteroides (0 lo desactiva)
+    if let Some(count) = arg_value(&args, "--asteroids") {
+        match count.parse::<usize>() {
+            Ok(count) => {
+                scene.asteroids = generate_asteroid_belt(count, BELT_INNER_RADIUS, BELT_OUTER_RADIUS, BELT_SEED);
+            }
+            Err(_) => {
+                eprintln!("--asteroids: '{}' no es un número válido", count);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // `--planets archivo` sustituye los planetas integrados por los de un archivo RON o JSON
+    if let Some(path) = arg_value(&args, "--planets") {
+        scene.planets = load_planets(path).unwrap_or_else(|err| {
+            eprintln!("Aviso: no se pudieron cargar los planetas de {} ({}); se usan los integrados", path, err);
+            generate_planets(planet_seed)
+        });
+    }
+
+    // `keys.ron`, si existe, cambia las teclas de las acciones que nombra
+    let key_bindings = if Path::new(KEY_BINDINGS_FILE).exists() {
+        load_key_bindings(KEY_BINDINGS_FILE).unwrap_or_else(|err| {
+            eprintln!("Aviso: no se pudieron cargar las teclas de {} ({}); se usan las de por defecto", KEY_BINDINGS_FILE, err);
+            KeyBindings::default()
+        })
+    } else {
+        KeyBindings::default()
+    };
+
+    // `--binary` añade una segunda estrella que orbita al Sol e ilumina los planetas
+    if args.iter().any(|arg| arg == "--binary") {
+        scene.planets.push(companion_star());
+    }
+
+    // `--eye x,y,z`, `--center x,y,z` y `--up x,y,z` sobrescriben la pose inicial de la cámara
+    scene.camera = match camera_from_args(&args, &scene.camera) {
+        Ok(camera) => camera,
+        Err(err) => {
+            eprintln!("Argumentos de cámara inválidos: {}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let god_rays = match god_rays_from_args(&args) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // `--fog d` fija la densidad de la niebla por distancia; 0 la desactiva
+    let fog_density = match fog_density_from_args(&args) {
+        Ok(density) => density,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // `--spotlight grados` enciende el foco de la nave con ese cono; F1 lo alterna
+    let spotlight_cone = match spotlight_cone_from_args(&args) {
+        Ok(degrees) => degrees.map(f32::to_radians),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let spotlight_angle = spotlight_cone.unwrap_or(DEFAULT_SPOTLIGHT_CONE.to_radians());
+
+    // `--gamma g` codifica el color final de la geometría con la curva c^(1/g)
+    let gamma = match gamma_from_args(&args) {
+        Ok(gamma) => gamma,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let raster_tiles = match raster_tiles_from_args(&args) {
+        Ok(tiles) => tiles,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let tone_mapping = match tone_mapping_from_args(&args) {
+        Ok(mapping) => mapping,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    // Sin `--tone-map` se usa la curva guardada con la escena, que por defecto es ninguna
+    let tone_mapping = if arg_value(&args, "--tone-map").is_some() { tone_mapping } else { scene.tone_mapping };
+
+    let follow_smoothing = match follow_smoothing_from_args(&args) {
+        Ok(smoothing) => smoothing,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // `--triangle-budget N` / `--fragment-budget N` limitan el trabajo por frame
+    let budget = match budget_from_args(&args) {
+        Ok(budget) => budget,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // `--background archivo` cambia la panorámica del fondo; admite imágenes HDR (.hdr, .exr)
+    let background = match arg_value(&args, "--background") {
+        Some(PROCEDURAL_BACKGROUND) => match starfield_from_args(&args) {
+            Ok(starfield) => Background::Procedural(starfield),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(2);
+            }
+        },
+        path => {
+            let image = load_panoramic_image(path.unwrap_or(DEFAULT_BACKGROUND));
+
+            // `--linear-background` pasa el fondo a espacio lineal para que se codifique con la
+            // misma gamma que la geometría
+            let image = if args.iter().any(|arg| arg == "--linear-background") {
+                linearize(image, gamma)
+            } else {
+                image
+            };
+            Background::Image(PanoramicTexture::new(image))
+        }
+    };
+    let assets = Assets::load(background);
+
+    let mut render_options = RenderOptions {
+        lights: Arc::new(Vec::new()),
+        background_sampling: BackgroundSampling::Trilinear,
+        debug_normals: false,
+        brightness: scene.brightness.clamp(MIN_BRIGHTNESS, MAX_BRIGHTNESS),
+        budget,
+        backface_culling: true,
+        show_orbits: true,
+        flat_shading: false,
+        gamma,
+        render_mode: RenderMode::Filled,
+        fog_color: FOG_COLOR,
+        fog_density,
+        raster_tiles,
+        tone_mapping,
+        // `--eclipses` enciende desde el principio las sombras de eclipse (F3)
+        eclipse_shadows: args.iter().any(|arg| arg == "--eclipses"),
+        spotlight_cone: spotlight_cone.unwrap_or(0.0),
+        // `--lod-crossfade` funde los niveles de detalle de las esferas en vez de cambiarlos de golpe
+        lod_crossfade: args.iter().any(|arg| arg == "--lod-crossfade"),
+    };
+    let mut effects = FrameEffects::new(god_rays);
+
+    let window_width = 800;
+    let window_height = 600;
+    let framebuffer_width = 800;
+    let framebuffer_height = 600;
+
+    // Framebuffers para el renderizado, incluidos el del SSAA y el de la vista secundaria
+    let mut targets = FrameTargets::new(framebuffer_width, framebuffer_height);
+
+    // `--headless` renderiza `--frames n` frames en PNG dentro de `--out dir` sin abrir ventana
+    if args.iter().any(|arg| arg == "--headless") {
+        let frames = match headless_frames_from_args(&args) {
+            Ok(frames) => frames,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(2);
+            }
+        };
+        let out_dir = arg_value(&args, "--out").unwrap_or(".");
+
+        // Las imágenes son de la escena, sin el texto del HUD
+        effects.hud = false;
+        if let Err(err) = run_headless(&mut scene, &assets, &mut render_options, &effects, &mut targets, frames, out_dir) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Crear una ventana para mostrar la salida
+    let mut window = Window::new(
+        "Space Travel",
+        window_width,
+        window_height,
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
+    )
+    .unwrap();
+
+    window.set_position(500, 500);
+    window.update();
+
+    // El título de la ventana hace de HUD para los valores ajustables
+    let mut window_title = String::from("Space Travel");
+
+    // Frames simulados que avanza `scene.time` en cada frame real (1 salvo en escenas guardadas)
+    let mut time_scale: f32 = scene.time_scale;
+    let mut paused = false;
+
+    // Posición del ratón en el frame anterior mientras se arrastra con el botón derecho
+    let mut previous_mouse: Option<(f32, f32)> = None;
+
+    // El archivo de `--planets` (o `planets.ron`, si existe) se vuelve a cargar al guardarlo o con R
+    let planets_path = arg_value(&args, "--planets")
+        .or_else(|| Path::new(PLANETS_FILE).exists().then_some(PLANETS_FILE));
+    let mut planets_watcher = planets_path.map(FileWatcher::new);
+    let binary = args.iter().any(|arg| arg == "--binary");
+
+    // Viaje en curso hacia un destino de warp (teclas 1 a 4)
+    let mut warp: Option<WarpAnimator> = None;
+
+    // Recorrido automático por los destinos de warp (F11); cualquier control manual lo cancela
+    let mut autopilot: Option<Autopilot> = None;
+
+    // Planeta elegido con Tab / Shift+Tab; mientras no se elija ninguno se usa el más cercano
+    let mut selected_planet: Option<usize> = None;
+
+    // Planeta al que sigue la cámara (F); se fija al activarlo aunque luego cambie el planeta activo
+    let mut followed_planet: Option<usize> = None;
+
+    // Modo de selección con el ratón (K): muestra una mira y un clic elige el planeta bajo ella
+    let mut picking = false;
+    let mut mouse_was_down = false;
+
+    let mut ship_state = ShipState::default();
+    let mut shadows_enabled = false;
+
+    // `--no-vsync-sim` desactiva la espera entre frames para medir el rendimiento máximo
+    let mut frame_pacer = FramePacer::new(FRAME_INTERVAL, !args.iter().any(|arg| arg == "--no-vsync-sim"));
+
+    while window.is_open() {
+        // Tiempo real desde el frame anterior: la simulación y los controles avanzan según él, así
+        // que van a la misma velocidad aunque la frecuencia de frames cambie
+        let delta_time = frame_pacer.delta_time();
+        let frames = delta_time * SIMULATION_FRAMES_PER_SECOND;
+
+        // Si la ventana cambia de tamaño se rehacen los framebuffers; la perspectiva, el viewport
+        // y el fondo se calculan en cada frame a partir del tamaño del framebuffer
+        let (window_width, window_height) = window.get_size();
+        if window_width > 0 && window_height > 0 && (window_width, window_height) != (targets.framebuffer.width, targets.framebuffer.height) {
+            targets.resize(window_width, window_height);
+        }
+
+        // Manejar la entrada del usuario; lo que haya movido la cámara antes no cuenta
+        scene.camera.check_if_changed();
+        let mut warp_index = handle_input(
+            &window,
+            &key_bindings,
+            &mut scene.camera,
+            &scene.warp_destinations,
+            delta_time,
+            &mut scene.current_warp_index,
+            &mut previous_mouse,
+        );
+
+        // El piloto automático se cancela en cuanto los controles mueven la cámara o piden un warp
+        if scene.camera.check_if_changed() || warp_index.is_some() {
+            autopilot = None;
+        }
+        if window.is_key_pressed(key_bindings.autopilot, KeyRepeat::No) {
+            autopilot = match autopilot {
+                Some(_) => None,
+                None if !scene.warp_destinations.is_empty() => {
+                    let first = (scene.current_warp_index + 1) % scene.warp_destinations.len();
+                    warp_index = Some(first);
+                    Some(Autopilot::new(first))
+                }
+                None => None,
+            };
+        }
+        if let Some(pilot) = autopilot.as_mut() {
+            if let Some(index) = pilot.step(&mut scene.camera, scene.warp_destinations.len(), warp.is_some() || warp_index.is_some(), delta_time) {
+                warp_index = Some(index);
+            }
+        }
+
+        // Un warp nuevo parte de la pose actual, aunque otro esté a medio camino
+        if let Some(index) = warp_index {
+            scene.current_warp_index = index;
+            let destination = &scene.warp_destinations[index];
+            warp = Some(WarpAnimator::new(scene.camera.eye, scene.camera.center, destination.position, destination.target, WARP_DURATION));
+            followed_planet = None;
+        }
+        if let Some(animator) = warp.as_mut() {
+            let (eye, center) = animator.step(delta_time);
+            if scene.camera.mode == CameraMode::FreeFly {
+                // En vuelo libre el centro va a una unidad delante del ojo: se traslada con él y
+                // solo se gira hacia el punto de la animación
+                scene.camera.center += eye - scene.camera.eye;
+                scene.camera.eye = eye;
+                scene.camera.look_at(center);
+            } else {
+                scene.camera.eye = eye;
+                scene.camera.center = center;
+            }
+            scene.camera.has_changed = true;
+            if animator.is_finished() {
+                warp = None;
+            }
+        }
+
+        if window.is_key_pressed(key_bindings.save_scene, KeyRepeat::No) {
+            scene.time_scale = time_scale;
+            scene.tone_mapping = render_options.tone_mapping;
+            scene.brightness = render_options.brightness;
+            dump_scene(&scene);
+        }
+
+        let reload_requested = window.is_key_pressed(key_bindings.reload_planets, KeyRepeat::No);
+        if let Some(watcher) = planets_watcher.as_mut() {
+            if watcher.poll() || reload_requested {
+                reload_planets(&mut scene, watcher.path(), binary);
+            }
+        } else if reload_requested {
+            println!("No hay archivo de planetas que recargar (ver --planets o {})", PLANETS_FILE);
+        }
+
+        // La captura se guarda al final del frame, con la imagen ya compuesta
+        let take_screenshot = window.is_key_pressed(key_bindings.screenshot, KeyRepeat::No);
+
+        if window.is_key_pressed(key_bindings.inset_view, KeyRepeat::No) {
+            effects.inset_view = effects.inset_view.next();
+        }
+
+        if window.is_key_pressed(key_bindings.shadows, KeyRepeat::No) {
+            shadows_enabled = !shadows_enabled;
+        }
+
+        if window.is_key_pressed(key_bindings.eclipses, KeyRepeat::No) {
+            render_options.eclipse_shadows = !render_options.eclipse_shadows;
+        }
+
+        if window.is_key_pressed(key_bindings.spotlight, KeyRepeat::No) {
+            render_options.spotlight_cone = if render_options.spotlight_cone > 0.0 { 0.0 } else { spotlight_angle };
+        }
+
+        if window.is_key_pressed(key_bindings.background_sampling, KeyRepeat::No) {
+            render_options.background_sampling = render_options.background_sampling.next();
+        }
+
+        if window.is_key_pressed(key_bindings.debug_normals, KeyRepeat::No) {
+            render_options.debug_normals = !render_options.debug_normals;
+        }
+
+        if window.is_key_pressed(key_bindings.overview, KeyRepeat::No) {
+            effects.overview = !effects.overview;
+        }
+
+        // `real_scale` alterna la vista a escala real; `exaggerate_more` y `exaggerate_less` aumentan o reducen el tamaño de los planetas en ella
+        if window.is_key_pressed(key_bindings.real_scale, KeyRepeat::No) {
+            scene.system_scale.toggle();
+        }
+        if window.is_key_pressed(key_bindings.exaggerate_more, KeyRepeat::Yes) {
+            scene.system_scale.exaggerate(EXAGGERATION_STEP);
+        }
+        if window.is_key_pressed(key_bindings.exaggerate_less, KeyRepeat::Yes) {
+            scene.system_scale.exaggerate(1.0 / EXAGGERATION_STEP);
+        }
+
+        if window.is_key_pressed(key_bindings.picking, KeyRepeat::No) {
+            picking = !picking;
+        }
+
+        // Espacio pausa la simulación; , / . reducen a la mitad / duplican la velocidad del tiempo
+        if window.is_key_pressed(key_bindings.pause, KeyRepeat::No) {
+            paused = !paused;
+        }
+        if window.is_key_pressed(key_bindings.slower, KeyRepeat::Yes) {
+            time_scale = (time_scale / 2.0).max(MIN_TIME_SCALE);
+        }
+        if window.is_key_pressed(key_bindings.faster, KeyRepeat::Yes) {
+            time_scale = (time_scale * 2.0).min(MAX_TIME_SCALE);
+        }
+
+        if window.is_key_pressed(key_bindings.orbits, KeyRepeat::No) {
+            render_options.show_orbits = !render_options.show_orbits;
+        }
+
+        if window.is_key_pressed(key_bindings.backface_culling, KeyRepeat::No) {
+            render_options.backface_culling = !render_options.backface_culling;
+        }
+
+        if window.is_key_pressed(key_bindings.camera_mode, KeyRepeat::No) {
+            scene.camera.toggle_mode();
+        }
+
+        if window.is_key_pressed(key_bindings.flat_shading, KeyRepeat::No) {
+            render_options.flat_shading = !render_options.flat_shading;
+        }
+
+        if window.is_key_pressed(key_bindings.wireframe, KeyRepeat::No) {
+            render_options.render_mode = match render_options.render_mode {
+                RenderMode::Filled => RenderMode::Wireframe,
+                RenderMode::Wireframe => RenderMode::Filled,
+            };
+        }
+
+        if window.is_key_pressed(key_bindings.god_rays, KeyRepeat::No) {
+            effects.god_rays_enabled = !effects.god_rays_enabled;
+        }
+
+        if window.is_key_pressed(key_bindings.anti_alias, KeyRepeat::No) {
+            effects.anti_alias = effects.anti_alias.next();
+        }
+
+        if window.is_key_pressed(key_bindings.bloom, KeyRepeat::No) {
+            effects.bloom = !effects.bloom;
+        }
+
+        if window.is_key_pressed(key_bindings.lens_flare, KeyRepeat::No) {
+            effects.lens_flare = !effects.lens_flare;
+        }
+
+        if window.is_key_pressed(key_bindings.labels, KeyRepeat::No) {
+            effects.labels = !effects.labels;
+        }
+
+        if window.is_key_pressed(key_bindings.minimap, KeyRepeat::No) {
+            effects.minimap = !effects.minimap;
+        }
+
+        if window.is_key_pressed(key_bindings.tile_heatmap, KeyRepeat::No) {
+            effects.tile_heatmap = !effects.tile_heatmap;
+        }
+
+        if window.is_key_pressed(key_bindings.nbody, KeyRepeat::No) {
+            toggle_nbody(&mut scene);
+        }
+
+        // + / - ajustan el brillo global
+        if window.is_key_pressed(key_bindings.brightness_up, KeyRepeat::Yes) || window.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes) {
+            render_options.brightness = (render_options.brightness * BRIGHTNESS_STEP).min(MAX_BRIGHTNESS);
+        }
+        if window.is_key_pressed(key_bindings.brightness_down, KeyRepeat::Yes) || window.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes) {
+            render_options.brightness = (render_options.brightness / BRIGHTNESS_STEP).max(MIN_BRIGHTNESS);
+        }
+
+        // Tab elige el siguiente planeta y Shift+Tab el anterior, dando la vuelta en los extremos
+        if window.is_key_pressed(key_bindings.next_planet, KeyRepeat::Yes) {
+            let backwards = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+            selected_planet = cycle_planet(selected_planet, scene.planets.len(), backwards);
+        }
+
+        // Z / X ajustan el tamaño de los detalles de superficie del planeta activo
+        let focused = selected_planet
+            .filter(|&index| index < scene.planets.len())
+            .unwrap_or_else(|| nearest_planet(&planet_positions(&scene), scene.camera.eye));
+        if window.is_key_pressed(key_bindings.follow, KeyRepeat::No) {
+            followed_planet = if followed_planet.is_some() { None } else { Some(focused) };
+        }
+        if window.is_key_pressed(key_bindings.noise_zoom_in, KeyRepeat::Yes) {
+            scene.planets[focused].noise_zoom *= NOISE_ZOOM_STEP;
+        }
+        if window.is_key_pressed(key_bindings.noise_zoom_out, KeyRepeat::Yes) {
+            scene.planets[focused].noise_zoom /= NOISE_ZOOM_STEP;
+        }
+
+        let planet = &scene.planets[focused];
+        let mut title = format!(
+            "Space Travel - tiempo x{}{} - planeta {}/{}{} (órbita r={:.1} {}, rotación {}, escala {:.2}) - zoom de superficie x{:.2} - brillo x{:.2} - {} - frames tardíos: {}",
+            time_scale,
+            if paused { " (pausa)" } else { "" },
+            focused + 1,
+            scene.planets.len(),
+            if selected_planet.is_some() { "" } else { " (más cercano)" },
+            scene.system_scale.orbit_radius(planet),
+            format_period(planet.orbital_period(), "estática"),
+            format_period(planet.rotation_period(), "sin giro"),
+            scene.system_scale.scale(planet),
+            planet.noise_zoom,
+            render_options.brightness,
+            effects.anti_alias.label(),
+            frame_pacer.late_frames,
+        );
+        if render_options.budget.is_limited() {
+            // Objetos omitidos en el frame anterior, en el framebuffer en que se renderizó
+            let skipped = targets.scene_stats(effects.anti_alias).skipped_objects;
+            title.push_str(&format!(" - omitidos por presupuesto: {}", skipped));
+        }
+        let culled = targets.scene_stats(effects.anti_alias).culled_objects;
+        if culled > 0 {
+            title.push_str(&format!(" - fuera de la vista: {}", culled));
+        }
+        if title != window_title {
+            window.set_title(&title);
+            window_title = title;
+        }
+
+        advance_scene(&mut scene, delta_time, time_scale, paused);
+
+        // La cámara de seguimiento se mueve después de los planetas para no ir un frame por detrás
+        if let Some(index) = followed_planet.filter(|&index| index < scene.planets.len()) {
+            let target = planet_positions(&scene)[index];
+            scene.camera.follow(target, follow_smoothing.powf(frames));
+        }
+
+        settle_scene(&mut scene);
+        ship_state.update(&scene.camera, frames);
+
+        // Las sombras entre planetas requieren renderizar la escena desde el Sol
+        render_options.lights = Arc::new(star_lights(&scene, shadows_enabled, assets.sphere_lods.full()));
+
+        // La mira sigue al ratón; si está fuera de la ventana se queda en el centro
+        let (aim_x, aim_y) = window
+            .get_mouse_pos(MouseMode::Discard)
+            .unwrap_or((targets.framebuffer.width as f32 / 2.0, targets.framebuffer.height as f32 / 2.0));
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if picking && mouse_down && !mouse_was_down {
+            // Se selecciona con la misma cámara con la que se ve el frame
+            let overview_camera = effects.overview_camera();
+            let view_camera = overview_camera.as_ref().unwrap_or(&scene.camera);
+            if let Some(index) = pick_planet(&scene, view_camera, targets.framebuffer.width, targets.framebuffer.height, aim_x, aim_y) {
+                selected_planet = Some(index);
+            }
+        }
+        mouse_was_down = mouse_down;
+
+        render_frame(&mut targets, &scene, &assets, &render_options, &effects, &ship_state, focused);
+
+        if picking {
+            draw_crosshair(&mut targets.framebuffer, aim_x as isize, aim_y as isize, CROSSHAIR_COLOR);
+        }
+
+        if take_screenshot {
+            let _ = save_screenshot(&targets.framebuffer, &screenshot_path());
+        }
+
+        // Actualizar la ventana con el contenido del framebuffer
+        window
+            .update_with_buffer(&targets.framebuffer.buffer, targets.framebuffer.width, targets.framebuffer.height)
+            .unwrap();
+
+        frame_pacer.wait();
     }
-    if window.is_key_down(Key::W) {
+}
+
+// Avanza la simulación `delta_time` segundos reales: la transición de la vista a escala, el
+// tiempo (multiplicado por `time_scale`, salvo en pausa) y las posiciones de los planetas
+fn advance_scene(scene: &mut Scene, delta_time: f32, time_scale: f32, paused: bool) {
+    let frames = delta_time * SIMULATION_FRAMES_PER_SECOND;
+    scene.system_scale.step(frames);
+
+    if !paused {
+        scene.time += time_scale * frames;
+
+        if scene.nbody {
+            // Un frame largo se integra en varios pasos para no perder estabilidad
+            let steps = frames.ceil().max(1.0) as usize;
+            for _ in 0..steps {
+                integrate_nbody(&mut scene.planets, NBODY_TIME_STEP * time_scale * frames / steps as f32);
+            }
+        }
+    }
+    // También en pausa: la vista a escala real puede seguir cambiando los radios de las órbitas
+    if !scene.nbody {
+        update_planets(&mut scene.planets, scene.time, &scene.system_scale);
+    }
+}
+
+// Va después de todo lo que mueve la cámara o los planetas en el frame: saca la cámara de los
+// planetas y añade las posiciones finales a las estelas
+fn settle_scene(scene: &mut Scene) {
+    let positions = planet_positions(scene);
+    resolve_camera_collisions(&mut scene.camera, &scene.planets, &positions, &scene.system_scale);
+    for (planet, &position) in scene.planets.iter_mut().zip(&positions) {
+        planet.trail.record(position, scene.time, planet.trail_length);
+    }
+}
+
+// Compone un frame en `targets.framebuffer`: la escena (en el framebuffer grande si hay SSAA), los
+// post-procesos y superposiciones de `effects`, la vista secundaria y el HUD con el planeta
+// `focused`. Lo usan la ventana y el modo sin ventana.
+fn render_frame(
+    targets: &mut FrameTargets,
+    scene: &Scene,
+    assets: &Assets,
+    options: &RenderOptions,
+    effects: &FrameEffects,
+    ship: &ShipState,
+    focused: usize,
+) {
+    // En la vista general se observa la escena desde fuera; la entrada sigue moviendo la cámara del juego
+    let overview_camera = effects.overview_camera();
+    let view_camera = overview_camera.as_ref().unwrap_or(&scene.camera);
+
+    // Con SSAA la escena se renderiza en el framebuffer grande y luego se reduce
+    let (window_width, window_height) = (targets.framebuffer.width as f32, targets.framebuffer.height as f32);
+    {
+        let (target, target_stats) = if effects.anti_alias == AntiAlias::Ssaa {
+            (&mut targets.ssaa_framebuffer, &mut targets.ssaa_stats)
+        } else {
+            (&mut targets.framebuffer, &mut targets.stats)
+        };
+
+        target.clear();
+        target_stats.reset();
+        render_scene(
+            target,
+            scene,
+            view_camera,
+            assets,
+            target_stats,
+            options,
+            ship,
+        );
+
+        if effects.god_rays_enabled {
+            apply_god_rays(target, scene, view_camera, &effects.god_rays);
+        }
+
+        // Usa el z-buffer de la escena para saber qué parte del Sol se ve
+        if effects.lens_flare {
+            apply_sun_flare(target, scene, view_camera);
+        }
+
+        if effects.overview {
+            // El frustum corresponde a la resolución de la ventana, no a la del render SSAA
+            let corners = frustum_corners(&scene.camera, window_width, window_height);
+            draw_frustum(target, &corners, view_camera, FRUSTUM_COLOR);
+        }
+    }
+
+    let framebuffer = &mut targets.framebuffer;
+    match effects.anti_alias {
+        AntiAlias::Off => {}
+        AntiAlias::Fxaa => apply_fxaa(framebuffer),
+        AntiAlias::Ssaa => {
+            // La rejilla de tiles corresponde a la resolución del render, no a la de la ventana
+            if effects.tile_heatmap {
+                draw_tile_heatmap(&mut targets.ssaa_framebuffer, &targets.ssaa_stats);
+            }
+            resolve_ssaa(&targets.ssaa_framebuffer, framebuffer);
+        }
+    }
+
+    // El resplandor se calcula sobre la imagen final a resolución de la ventana, con el fondo
+    if effects.bloom {
+        framebuffer.apply_bloom(BLOOM_THRESHOLD, BLOOM_RADIUS, BLOOM_INTENSITY);
+    }
+
+    if effects.tile_heatmap && effects.anti_alias != AntiAlias::Ssaa {
+        draw_tile_heatmap(framebuffer, &targets.stats);
+    }
+
+    if effects.labels {
+        draw_planet_labels(framebuffer, scene, view_camera);
+    }
+
+    // Vista secundaria en la esquina superior derecha
+    if let Some(inset_camera) = effects.inset_view.camera(&scene.camera) {
+        targets.inset_framebuffer.clear();
+        targets.inset_stats.reset();
+        render_scene(
+            &mut targets.inset_framebuffer,
+            scene,
+            &inset_camera,
+            assets,
+            &mut targets.inset_stats,
+            options,
+            ship,
+        );
+        framebuffer.blit(&targets.inset_framebuffer, framebuffer.width.saturating_sub(INSET_WIDTH + INSET_MARGIN), INSET_MARGIN, 0x808080);
+    }
+
+    // El minimapa muestra siempre la cámara del juego, también en la vista general
+    if effects.minimap {
+        draw_minimap(framebuffer, scene, &scene.camera);
+    }
+
+    // HUD al final para que quede encima de todo
+    if effects.hud {
+        draw_hud(framebuffer, scene, focused);
+    }
+}
+
+// Modo sin ventana: avanza la simulación `frames` frames de 60 Hz con la escala de tiempo de la
+// escena, sin entrada del usuario, y guarda cada uno como `frame_NNNN.png` en `out_dir`, que se
+// crea si no existe
+fn run_headless(
+    scene: &mut Scene,
+    assets: &Assets,
+    options: &mut RenderOptions,
+    effects: &FrameEffects,
+    targets: &mut FrameTargets,
+    frames: usize,
+    out_dir: &str,
+) -> Result<(), String> {
+    std::fs::create_dir_all(out_dir).map_err(|err| format!("No se pudo crear el directorio {}: {}", out_dir, err))?;
+
+    let mut ship_state = ShipState::default();
+    for frame in 0..frames {
+        advance_scene(scene, 1.0 / SIMULATION_FRAMES_PER_SECOND, scene.time_scale, false);
+        settle_scene(scene);
+        ship_state.update(&scene.camera, 1.0);
+        options.lights = Arc::new(star_lights(scene, false, assets.sphere_lods.full()));
+
+        let focused = nearest_planet(&planet_positions(scene), scene.camera.eye);
+        render_frame(targets, scene, assets, options, effects, &ship_state, focused);
+
+        let path = Path::new(out_dir).join(format!("frame_{:04}.png", frame));
+        if save_screenshot(&targets.framebuffer, &path.to_string_lossy()).is_err() {
+            return Err(format!("Render sin ventana interrumpido en el frame {}", frame));
+        }
+    }
+    Ok(())
+}
+
+// Texto de la esquina superior izquierda: destino de warp actual, planeta activo y posición de
+// la cámara. Lleva una sombra de un píxel para leerse sobre fondos claros.
+fn draw_hud(framebuffer: &mut Framebuffer, scene: &Scene, focused: usize) {
+    let planet = &scene.planets[focused];
+    let name = if planet.name.is_empty() { format!("planeta {}", focused + 1) } else { planet.name.clone() };
+    let eye = scene.camera.eye;
+    let text = format!(
+        "warp {}/{}\n{}\neye {:.1} {:.1} {:.1}",
+        scene.current_warp_index + 1,
+        scene.warp_destinations.len(),
+        name,
+        eye.x,
+        eye.y,
+        eye.z,
+    );
+
+    framebuffer.draw_text(HUD_MARGIN + 1, HUD_MARGIN + 1, &text, HUD_SHADOW_COLOR);
+    framebuffer.draw_text(HUD_MARGIN, HUD_MARGIN, &text, HUD_COLOR);
+}
+
+// Nombre de cada planeta centrado sobre él, en la dirección "arriba" de la cámara para que se lea
+// recto aunque esta se incline. Los planetas detrás de la cámara no llevan etiqueta y las de los
+// lejanos se atenúan con la distancia.
+fn draw_planet_labels(framebuffer: &mut Framebuffer, scene: &Scene, camera: &Camera) {
+    let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+    let projection_matrix = create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32, camera.fov);
+    let viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+    let up = camera.basis_change(&Vec3::new(0.0, 1.0, 0.0));
+
+    for (index, (planet, position)) in scene.planets.iter().zip(planet_positions(scene)).enumerate() {
+        let radius = scene.system_scale.scale(planet) * SPHERE_MODEL_RADIUS;
+        let Some(anchor) = world_to_screen(position + up * (radius + LABEL_OFFSET), &view_matrix, &projection_matrix, &viewport_matrix) else {
+            continue;
+        };
+
+        let name = if planet.name.is_empty() { format!("planeta {}", index + 1) } else { planet.name.clone() };
+        let left = anchor.x - (name.chars().count() * GLYPH_ADVANCE) as f32 / 2.0;
+        let top = anchor.y - GLYPH_HEIGHT as f32;
+        if left < 0.0 || top < 0.0 {
+            continue;
+        }
+
+        let distance = (position - camera.eye).magnitude();
+        let brightness = (LABEL_FULL_DISTANCE / distance).clamp(LABEL_MIN_BRIGHTNESS, 1.0);
+        let color = (Color::from_hex(HUD_COLOR) * brightness).to_hex();
+        framebuffer.draw_text(left as usize + 1, top as usize + 1, &name, HUD_SHADOW_COLOR);
+        framebuffer.draw_text(left as usize, top as usize, &name, color);
+    }
+}
+
+// Aplica los controles de cámara. Devuelve el índice del destino de warp si se pulsó una de
+// sus teclas; el viaje hasta él lo anima `main`.
+fn handle_input(
+    window: &Window,
+    key_bindings: &KeyBindings,
+    camera: &mut Camera,
+    warp_destinations: &[WarpDestination],
+    delta_time: f32,
+    current_warp_index: &mut usize, 
+    previous_mouse: &mut Option<(f32, f32)>,
+) -> Option<usize> {
+    // Las velocidades de los controles son por frame de 60 Hz
+    let frames = delta_time * SIMULATION_FRAMES_PER_SECOND;
+    let roll_speed = std::f32::consts::PI / 90.0 * frames;
+    let fov_speed = FOV_SPEED * frames;
+    let keys = key_bindings.warp_keys();
+
+    if camera.mode == CameraMode::FreeFly {
+        handle_free_fly_input(window, key_bindings, camera, previous_mouse, frames);
+    } else {
+        handle_orbit_input(window, key_bindings, camera, previous_mouse, frames);
+    }
+
+    // Camera roll controls
+    if window.is_key_down(key_bindings.roll_left) {
+        camera.roll(roll_speed);
+    }
+    if window.is_key_down(key_bindings.roll_right) {
+        camera.roll(-roll_speed);
+    }
+    if window.is_key_pressed(key_bindings.reset_roll, KeyRepeat::No) {
+        camera.reset_roll();
+    }
+
+    // Zoom de lente: cambia el campo de visión sin mover la cámara
+    if window.is_key_down(key_bindings.fov_narrow) {
+        camera.widen_fov(-fov_speed);
+    }
+    if window.is_key_down(key_bindings.fov_wide) {
+        camera.widen_fov(fov_speed);
+    }
+
+    // Detectar teclas para activar el warp
+    let mut warp = None;
+    for (i, key) in keys.iter().enumerate() {
+        if window.is_key_pressed(*key, KeyRepeat::No) && !warp_destinations.is_empty() {
+            *current_warp_index = i.min(warp_destinations.len() - 1); // Prevenir desbordamientos
+            warp = Some(*current_warp_index);
+        }
+    }
+    warp
+}
+
+// Controles de la cámara en órbita alrededor de su centro; el frame duró `frames` frames de 60 Hz
+fn handle_orbit_input(window: &Window, key_bindings: &KeyBindings, camera: &mut Camera, previous_mouse: &mut Option<(f32, f32)>, frames: f32) {
+    let movement_speed = 1.0 * frames;
+    let rotation_speed = std::f32::consts::PI / 50.0 * frames;
+    let zoom_speed = 0.1 * frames;
+
+    // Camera orbit controls
+    if window.is_key_down(key_bindings.orbit_left) {
+        camera.orbit(rotation_speed, 0.0);
+    }
+    if window.is_key_down(key_bindings.orbit_right) {
+        camera.orbit(-rotation_speed, 0.0);
+    }
+    if window.is_key_down(key_bindings.orbit_up) {
         camera.orbit(0.0, -rotation_speed);
     }
-    if window.is_key_down(Key::S) {
+    if window.is_key_down(key_bindings.orbit_down) {
         camera.orbit(0.0, rotation_speed);
     }
 
+    // Mouse-look: arrastrar con el botón derecho orbita la cámara (la inclinación la limita `orbit`)
+    let mouse = window.get_mouse_pos(MouseMode::Pass);
+    if window.get_mouse_down(MouseButton::Right) {
+        if let (Some((x, y)), Some((previous_x, previous_y))) = (mouse, *previous_mouse) {
+            camera.orbit(-(x - previous_x) * MOUSE_SENSITIVITY, (y - previous_y) * MOUSE_SENSITIVITY);
+        }
+        *previous_mouse = mouse;
+    } else {
+        *previous_mouse = None;
+    }
+
     // Camera movement controls
     let mut movement = Vec3::new(0.0, 0.0, 0.0);
-    if window.is_key_down(Key::A) {
+    if window.is_key_down(key_bindings.move_left) {
         movement.x -= movement_speed;
     }
-    if window.is_key_down(Key::D) {
+    if window.is_key_down(key_bindings.move_right) {
         movement.x += movement_speed;
     }
-    if window.is_key_down(Key::Q) {
+    if window.is_key_down(key_bindings.move_up) {
         movement.y += movement_speed;
     }
-    if window.is_key_down(Key::E) {
+    if window.is_key_down(key_bindings.move_down) {
         movement.y -= movement_speed;
     }
     if movement.magnitude() > 0.0 {
@@ -534,27 +2484,305 @@ fn handle_input(
     }
 
     // Camera zoom controls
-    if window.is_key_down(Key::Up) {
+    if window.is_key_down(key_bindings.zoom_in) {
         camera.zoom(zoom_speed);
     }
-    if window.is_key_down(Key::Down) {
+    if window.is_key_down(key_bindings.zoom_out) {
         camera.zoom(-zoom_speed);
     }
+}
 
-    // Detectar teclas para activar el warp
-    for (i, key) in keys.iter().enumerate() {
-        if window.is_key_pressed(*key, KeyRepeat::No) {
-            *current_warp_index = i.min(warp_destinations.len() - 1); // Prevenir desbordamientos
+// Controles de vuelo libre: WASD desplazan la cámara en su propia base, Q/E la suben y bajan,
+// y las flechas o el arrastre con el botón derecho giran la vista. El frame duró `frames` frames de 60 Hz.
+fn handle_free_fly_input(window: &Window, key_bindings: &KeyBindings, camera: &mut Camera, previous_mouse: &mut Option<(f32, f32)>, frames: f32) {
+    let rotation_speed = std::f32::consts::PI / 100.0 * frames;
+    let fly_speed = FREE_FLY_SPEED * frames;
 
-            // Salto instantáneo al destino
-            let target_position = warp_destinations[*current_warp_index].position;
-            let target_target = warp_destinations[*current_warp_index].target;
+    let mut movement = Vec3::new(0.0, 0.0, 0.0);
+    if window.is_key_down(key_bindings.move_forward) {
+        movement.z += fly_speed;
+    }
+    if window.is_key_down(key_bindings.move_backward) {
+        movement.z -= fly_speed;
+    }
+    if window.is_key_down(key_bindings.move_right) {
+        movement.x += fly_speed;
+    }
+    if window.is_key_down(key_bindings.move_left) {
+        movement.x -= fly_speed;
+    }
+    if window.is_key_down(key_bindings.move_up) {
+        movement.y += fly_speed;
+    }
+    if window.is_key_down(key_bindings.move_down) {
+        movement.y -= fly_speed;
+    }
+    if movement.magnitude() > 0.0 {
+        camera.fly(movement);
+    }
+
+    let mut yaw = 0.0;
+    let mut pitch = 0.0;
+    if window.is_key_down(key_bindings.look_left) {
+        yaw += rotation_speed;
+    }
+    if window.is_key_down(key_bindings.look_right) {
+        yaw -= rotation_speed;
+    }
+    if window.is_key_down(key_bindings.look_up) {
+        pitch += rotation_speed;
+    }
+    if window.is_key_down(key_bindings.look_down) {
+        pitch -= rotation_speed;
+    }
+
+    let mouse = window.get_mouse_pos(MouseMode::Pass);
+    if window.get_mouse_down(MouseButton::Right) {
+        if let (Some((x, y)), Some((previous_x, previous_y))) = (mouse, *previous_mouse) {
+            yaw -= (x - previous_x) * MOUSE_SENSITIVITY;
+            pitch -= (y - previous_y) * MOUSE_SENSITIVITY;
+        }
+        *previous_mouse = mouse;
+    } else {
+        *previous_mouse = None;
+    }
+
+    if yaw != 0.0 || pitch != 0.0 {
+        camera.look(yaw, pitch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli::DEFAULT_PLANET_SEED;
+    use nalgebra_glm::Vec2;
+    use shaders::vertex_shader;
+
+    // Uniformes de una esfera helada en el origen vista desde `eye`, sin niebla ni post-procesos
+    fn test_uniforms(width: usize, height: usize, eye: Vec3) -> Uniforms {
+        let model_matrix = create_model_matrix(Vec3::zeros(), 1.0, Vec3::zeros());
+        Uniforms {
+            model_matrix,
+            normal_matrix: create_normal_matrix(&model_matrix),
+            view_matrix: create_view_matrix(eye, Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0)),
+            projection_matrix: create_perspective_matrix(width as f32, height as f32, 45.0),
+            viewport_matrix: create_viewport_matrix(width as f32, height as f32),
+            time: 0,
+            noise: Arc::new(create_noise()),
+            shader_mode: 4,
+            noise_zoom: 1.0,
+            cloud_coverage: 0.0,
+            lights: Arc::new(Vec::new()),
+            star_color: None,
+            star_temperature: 0.0,
+            debug_normals: false,
+            brightness: 1.0,
+            camera_position: eye,
+            light_position: Vec3::new(10.0, 10.0, 10.0),
+            spotlight_position: Vec3::zeros(),
+            spotlight_dir: Vec3::zeros(),
+            spotlight_cone: 0.0,
+            backface_culling: true,
+            texture: None,
+            normal_map: None,
+            flat_shading: false,
+            gamma: 1.0,
+            render_mode: RenderMode::Filled,
+            fog_color: FOG_COLOR,
+            fog_density: 0.0,
+            raster_tiles: 1,
+            tone_mapping: ToneMapping::None,
+            ring_shadow: None,
+            eclipse_shadow: None,
+            opacity: 1.0,
+        }
+    }
+
+    // Cada luna, en varios instantes, queda fuera del planeta sin llegar a tocarlo
+    fn assert_moons_outside_parents(planets: &[Planet]) {
+        for planet in planets {
+            let parent_radius = planet.scale * SPHERE_MODEL_RADIUS;
+            for moon in &planet.moons {
+                for time in [0.0, 17.0, 250.0, 9999.0] {
+                    let (position, _, scale) = moon.transformation(planet, planet.position, planet.scale, time);
+                    let distance = (position - planet.position).magnitude();
+                    assert!((distance - moon.orbit_radius).abs() < 1e-3);
+                    assert!(distance - scale * SPHERE_MODEL_RADIUS > parent_radius, "una luna de {} se mete en el planeta", planet.name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn instances_inside_the_frustum_skip_clipping_unchanged() {
+        let mut uniforms = test_uniforms(64, 48, Vec3::new(0.0, 0.0, 10.0));
+        let mesh = asteroid_mesh(BELT_SEED);
+        let model_matrix = create_model_matrix(Vec3::new(1.0, 0.5, 0.0), 0.5, Vec3::new(0.3, 1.2, 0.0));
+        uniforms.model_matrix = model_matrix;
+        uniforms.normal_matrix = create_normal_matrix(&model_matrix);
+
+        let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+        let mesh_radius = mesh.iter().map(|vertex| vertex.position.magnitude()).fold(0.0, f32::max);
+        assert!(sphere_inside_frustum(&view_projection, Vec3::new(1.0, 0.5, 0.0), mesh_radius * 0.5));
+
+        let (mut clipped, mut projected) = (Vec::new(), Vec::new());
+        assemble_triangles(&mesh, &uniforms, &mut clipped);
+        project_triangles(&mesh, &uniforms, &mut projected);
+        assert_eq!(clipped.len(), projected.len());
+        for (a, b) in clipped.iter().zip(&projected) {
+            for (a, b) in a.iter().zip(b) {
+                assert_eq!(a.transformed_position, b.transformed_position);
+                assert_eq!(a.clip_w, b.clip_w);
+            }
+        }
+    }
+
+    #[test]
+    fn builtin_moon_placement() {
+        let planets = generate_planets(DEFAULT_PLANET_SEED);
+        let earth = planets.iter().find(|planet| planet.name == "earth").expect("falta la Tierra");
+        let moon = &earth.moons[0];
+        let parent = Vec3::new(10.0, 0.0, -3.0);
+
+        // Empieza en +X del planeta y gira en el plano de la órbita
+        let (position, rotation, scale) = moon.transformation(earth, parent, earth.scale, 0.0);
+        assert!((position - (parent + Vec3::new(2.5, 0.0, 0.0))).magnitude() < 1e-5);
+        assert!(rotation.magnitude() < 1e-6);
+        assert!((scale - 0.6).abs() < 1e-6);
+
+        let (position, _, _) = moon.transformation(earth, parent, earth.scale, 100.0);
+        let angle = 0.05f32 * 100.0;
+        assert!((position - (parent + Vec3::new(2.5 * angle.cos(), 0.0, 2.5 * angle.sin()))).magnitude() < 1e-4);
+
+        // Con el planeta dibujado al doble, la órbita y la luna crecen igual
+        let (position, _, scale) = moon.transformation(earth, parent, earth.scale * 2.0, 0.0);
+        assert!((position - (parent + Vec3::new(5.0, 0.0, 0.0))).magnitude() < 1e-5);
+        assert!((scale - 1.2).abs() < 1e-6);
+
+        assert_moons_outside_parents(&planets);
+    }
+
+    #[test]
+    fn configured_moons_orbit_outside_their_planets() {
+        let planets = load_planets("assets/planets/solar_system.ron").expect("solar_system.ron no se puede leer");
+        assert!(planets.iter().any(|planet| !planet.moons.is_empty()));
+        assert_moons_outside_parents(&planets);
+    }
 
-            camera.eye = target_position;
-            camera.center = target_target;
+    #[test]
+    fn warps_target_the_initial_planet_positions() {
+        for seed in [DEFAULT_PLANET_SEED, 11] {
+            let scene = default_scene(seed);
+            let positions = calculate_planet_transformations(&scene.planets, 0.0, &SystemScale::default());
+            for (warp, planet) in [(2, 1), (3, 2)] {
+                assert!((scene.warp_destinations[warp].target - positions[planet].0).magnitude() < 1e-4);
+            }
+        }
+    }
 
-            *current_position = camera.eye;    // Actualizar la posición actual
-            *current_target = camera.center;  // Actualizar el objetivo actual
+    #[test]
+    fn vertex_on_the_camera_plane_emits_no_fragments() {
+        let eye = Vec3::new(0.0, 0.0, 5.0);
+        let uniforms = test_uniforms(64, 48, eye);
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let front = [Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0)]
+            .map(|position| vertex_shader(&Vertex::new(position, normal, Vec2::zeros()), &uniforms));
+        let apex = vertex_shader(&Vertex::new(Vec3::new(0.0, 1.0, 0.0), normal, Vec2::zeros()), &uniforms);
+        assert!(!triangle(&front[0], &front[1], &apex).is_empty());
+
+        // En el plano de la cámara w es 0, y un poco por delante ronda 1e-6 (mayor que f32::EPSILON)
+        for z in [eye.z, 4.999999] {
+            let vertex = Vertex::new(Vec3::new(0.5, 1.0, z), normal, Vec2::zeros());
+            let (_, clip) = vertex_to_clip(&vertex, &uniforms);
+            assert!(clip.w.abs() < 1e-5);
+
+            let projected = vertex_shader(&vertex, &uniforms);
+            assert!(projected.degenerate);
+            assert!(projected.transformed_position.iter().all(|coordinate| coordinate.is_finite()));
+            assert!(triangle(&front[0], &front[1], &projected).is_empty());
         }
     }
+
+    #[test]
+    fn tiled_render_matches_the_sequential_one() {
+        let (width, height) = (96, 72);
+        let eye = Vec3::new(0.0, 1.0, 4.0);
+        let sphere = lod::sphere_mesh(16, 24);
+        // Dos esferas que se solapan para que el z-buffer decida entre ellas en las franjas
+        let models = [
+            create_model_matrix(Vec3::zeros(), 0.9, Vec3::zeros()),
+            create_model_matrix(Vec3::new(0.6, 0.2, 0.8), 0.5, Vec3::new(0.3, 0.0, 0.0)),
+        ];
+
+        let draw = |raster_tiles: usize| {
+            let mut framebuffer = Framebuffer::new(width, height);
+            let mut stats = RenderStats::new(width, height);
+            for model_matrix in models {
+                let uniforms = Uniforms {
+                    model_matrix,
+                    normal_matrix: create_normal_matrix(&model_matrix),
+                    raster_tiles,
+                    ..test_uniforms(width, height, eye)
+                };
+                render(&mut framebuffer, &uniforms, &sphere, &mut stats);
+            }
+            framebuffer
+        };
+
+        let sequential = draw(1);
+        assert!(sequential.zbuffer.iter().any(|depth| depth.is_finite()));
+        // Más franjas que filas de alguna y un número que no divide la altura
+        for raster_tiles in [2, 5, 8, height + 3] {
+            let tiled = draw(raster_tiles);
+            assert!(tiled.buffer == sequential.buffer, "el color cambia con {} franjas", raster_tiles);
+            assert!(tiled.zbuffer == sequential.zbuffer, "la profundidad cambia con {} franjas", raster_tiles);
+        }
+    }
+
+    #[test]
+    fn saved_scene_keeps_its_time_scale() {
+        let path = std::env::temp_dir().join("space_travel_time_scale_scene.json");
+        let path = path.to_str().unwrap();
+        let mut scene = default_scene(DEFAULT_PLANET_SEED);
+        scene.time_scale = 4.0;
+        scene.save_json(path).unwrap();
+        assert_eq!(Scene::load_json(path).unwrap().time_scale, 4.0);
+
+        // Las escenas de la versión 1 no la guardaban y se cargan a velocidad normal
+        let mut json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("time_scale");
+        fields.insert("version".to_string(), 1.into());
+        std::fs::write(path, json.to_string()).unwrap();
+        let loaded = Scene::load_json(path);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.unwrap().time_scale, 1.0);
+    }
+
+    #[test]
+    fn saved_scene_keeps_its_tone_mapping_and_brightness() {
+        let path = std::env::temp_dir().join("space_travel_tone_mapping_scene.json");
+        let path = path.to_str().unwrap();
+        let mut scene = default_scene(DEFAULT_PLANET_SEED);
+        scene.tone_mapping = ToneMapping::Aces;
+        scene.brightness = 1.5;
+        scene.save_json(path).unwrap();
+        let loaded = Scene::load_json(path).unwrap();
+        assert!(loaded.tone_mapping == ToneMapping::Aces);
+        assert_eq!(loaded.brightness, 1.5);
+
+        // Sin los campos de la versión 3 se carga con los valores de siempre
+        let mut json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("tone_mapping");
+        fields.remove("brightness");
+        fields.insert("version".to_string(), 2.into());
+        std::fs::write(path, json.to_string()).unwrap();
+        let loaded = Scene::load_json(path);
+        std::fs::remove_file(path).unwrap();
+        let loaded = loaded.unwrap();
+        assert!(loaded.tone_mapping == ToneMapping::None);
+        assert_eq!(loaded.brightness, 1.0);
+    }
 }
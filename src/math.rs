@@ -0,0 +1,186 @@
+use nalgebra_glm::{Vec3, Vec4, Mat3, Mat4, look_at, mat4_to_mat3, ortho, perspective};
+use std::f32::consts::PI;
+
+pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
+    let (sin_x, cos_x) = rotation.x.sin_cos();
+    let (sin_y, cos_y) = rotation.y.sin_cos();
+    let (sin_z, cos_z) = rotation.z.sin_cos();
+
+    let rotation_matrix_x = Mat4::new(
+        1.0,  0.0,    0.0,   0.0,
+        0.0,  cos_x, -sin_x, 0.0,
+        0.0,  sin_x,  cos_x, 0.0,
+        0.0,  0.0,    0.0,   1.0,
+    );
+
+    let rotation_matrix_y = Mat4::new(
+        cos_y,  0.0,  sin_y, 0.0,
+        0.0,    1.0,  0.0,   0.0,
+        -sin_y, 0.0,  cos_y, 0.0,
+        0.0,    0.0,  0.0,   1.0,
+    );
+
+    let rotation_matrix_z = Mat4::new(
+        cos_z, -sin_z, 0.0, 0.0,
+        sin_z,  cos_z, 0.0, 0.0,
+        0.0,    0.0,  1.0, 0.0,
+        0.0,    0.0,  0.0, 1.0,
+    );
+
+    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+
+    let transform_matrix = Mat4::new(
+        scale, 0.0,   0.0,   translation.x,
+        0.0,   scale, 0.0,   translation.y,
+        0.0,   0.0,   scale, translation.z,
+        0.0,   0.0,   0.0,   1.0,
+    );
+
+    transform_matrix * rotation_matrix
+}
+
+// Modelo de un cuerpo que gira `spin` radianes alrededor de su propio eje Y después de inclinarlo
+// con `axial_tilt` (ángulos en radianes alrededor de X, Y y Z, como en `create_model_matrix`): el
+// eje de giro queda inclinado y fijo en el espacio mientras el cuerpo orbita. Sin inclinación es
+// lo mismo que `create_model_matrix` con la rotación (0, spin, 0).
+pub fn create_tilted_model_matrix(translation: Vec3, scale: f32, axial_tilt: Vec3, spin: f32) -> Mat4 {
+    create_model_matrix(translation, scale, axial_tilt) * create_model_matrix(Vec3::zeros(), 1.0, Vec3::new(0.0, spin, 0.0))
+}
+
+// Matriz que lleva las normales del modelo al mundo: la inversa de la traspuesta de la parte
+// 3x3 de `model_matrix`. Con escalas no uniformes las normales no se transforman como posiciones.
+pub fn create_normal_matrix(model_matrix: &Mat4) -> Mat3 {
+    mat4_to_mat3(model_matrix).transpose().try_inverse().unwrap_or(Mat3::identity())
+}
+
+pub fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
+    look_at(&eye, &center, &up)
+}
+
+// Distancia de la cámara al plano cercano de la proyección en perspectiva
+pub const NEAR_PLANE: f32 = 0.1;
+
+// Proyección con un campo de visión vertical de `fov` grados
+pub fn create_perspective_matrix(window_width: f32, window_height: f32, fov: f32) -> Mat4 {
+    let fov = fov * PI / 180.0;
+    let aspect_ratio = window_width / window_height;
+    let far = 1000.0;
+
+    // nalgebra-glm invierte el orden de GLM: primero la relación de aspecto y luego el ángulo
+    perspective(aspect_ratio, fov, NEAR_PLANE, far)
+}
+
+// Proyección ortográfica que lleva el cuadrado de lado 2·`half_extent` centrado en el eje de la
+// vista a todo el NDC, sin reducir con la distancia
+pub fn create_orthographic_matrix(half_extent: f32, near: f32, far: f32) -> Mat4 {
+    ortho(-half_extent, half_extent, -half_extent, half_extent, near, far)
+}
+
+pub fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
+    Mat4::new(
+        width / 2.0, 0.0, 0.0, width / 2.0,
+        0.0, -height / 2.0, 0.0, height / 2.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0
+    )
+}
+
+// Caja alineada a los ejes del mundo que contiene la caja del objeto (min, max) transformada
+// por `model_matrix`. Es conservadora: si el modelo gira, la caja resultante crece.
+pub fn transform_aabb(min: Vec3, max: Vec3, model_matrix: &Mat4) -> (Vec3, Vec3) {
+    let mut world_min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut world_max = Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+    for corner in 0..8 {
+        let local = Vec4::new(
+            if corner & 1 == 0 { min.x } else { max.x },
+            if corner & 2 == 0 { min.y } else { max.y },
+            if corner & 4 == 0 { min.z } else { max.z },
+            1.0,
+        );
+        let world = model_matrix * local;
+        let world = Vec3::new(world.x, world.y, world.z);
+        world_min = world_min.inf(&world);
+        world_max = world_max.sup(&world);
+    }
+
+    (world_min, world_max)
+}
+
+// Proyecta un punto del mundo a coordenadas de pantalla (z es la profundidad del zbuffer).
+// Devuelve None si el punto está detrás de la cámara.
+pub fn world_to_screen(point: Vec3, view_matrix: &Mat4, projection_matrix: &Mat4, viewport_matrix: &Mat4) -> Option<Vec3> {
+    let clip = projection_matrix * view_matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+
+    let screen = viewport_matrix * Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    Some(Vec3::new(screen.x, screen.y, screen.z))
+}
+
+// Coordenadas de la imagen panorámica (equirectangular) que corresponden a una longitud
+// (-180 a 180) y una latitud (-90 a 90) en grados
+pub fn project_to_image(x_angle: f32, y_angle: f32, image_width: u32, image_height: u32) -> (u32, u32) {
+    // Convierte los ángulos a un rango de 0 a 1
+    let x_normalized = (x_angle + 180.0) / 360.0; // -180 a 180 -> 0 a 1
+    let y_normalized = (y_angle + 90.0) / 180.0; // -90 a 90 -> 0 a 1
+
+    // Calcula las coordenadas de la imagen
+    let x_pixel = (x_normalized * image_width as f32) as u32;
+    let y_pixel = (y_normalized * image_height as f32) as u32;
+
+    (x_pixel, y_pixel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_aabb_contains_the_moved_box() {
+        let (min, max) = (Vec3::new(-1.0, -2.0, -3.0), Vec3::new(1.0, 2.0, 3.0));
+
+        // Trasladar y escalar mueve las esquinas tal cual
+        let model_matrix = create_model_matrix(Vec3::new(10.0, 0.0, -5.0), 2.0, Vec3::zeros());
+        assert_eq!(transform_aabb(min, max, &model_matrix), (Vec3::new(8.0, -4.0, -11.0), Vec3::new(12.0, 4.0, 1.0)));
+
+        // Un giro de 90° sobre Y intercambia la extensión en X y en Z
+        let model_matrix = create_model_matrix(Vec3::zeros(), 1.0, Vec3::new(0.0, PI / 2.0, 0.0));
+        let (world_min, world_max) = transform_aabb(min, max, &model_matrix);
+        assert!((world_min - Vec3::new(-3.0, -2.0, -1.0)).magnitude() < 1e-5);
+        assert!((world_max - Vec3::new(3.0, 2.0, 1.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn identity_inputs_give_the_identity_model_matrix() {
+        assert_eq!(create_model_matrix(Vec3::zeros(), 1.0, Vec3::zeros()), Mat4::identity());
+    }
+
+    #[test]
+    fn mvp_chain_lands_on_the_expected_pixel() {
+        let (width, height) = (800.0, 600.0);
+        let view_matrix = create_view_matrix(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let projection_matrix = create_perspective_matrix(width, height, 45.0);
+        let viewport_matrix = create_viewport_matrix(width, height);
+        let mvp = viewport_matrix * projection_matrix * view_matrix;
+
+        // A mitad de camino entre el centro y el borde superior del campo de visión (22.5° sobre
+        // el eje a 5 unidades), dado en el espacio de un modelo trasladado una unidad hacia arriba
+        let model_matrix = create_model_matrix(Vec3::new(0.0, 1.0, 0.0), 1.0, Vec3::zeros());
+        let half_height = 5.0 * (22.5f32).to_radians().tan() / 2.0;
+        let clip = mvp * model_matrix * Vec4::new(0.0, half_height - 1.0, 0.0, 1.0);
+        let pixel = (clip.x / clip.w, clip.y / clip.w);
+        assert!((pixel.0 - 400.0).abs() < 1e-3 && (pixel.1 - 150.0).abs() < 1e-3, "{:?}", pixel);
+
+        // El centro de la vista cae en el centro de la pantalla
+        let center = world_to_screen(Vec3::zeros(), &view_matrix, &projection_matrix, &viewport_matrix).unwrap();
+        assert!((center.x - 400.0).abs() < 1e-3 && (center.y - 300.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn equator_and_prime_meridian_map_to_the_image_center() {
+        assert_eq!(project_to_image(0.0, 0.0, 2048, 1024), (1024, 512));
+        assert_eq!(project_to_image(-180.0, -90.0, 2048, 1024), (0, 0));
+    }
+}
@@ -0,0 +1,131 @@
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::math::{create_orthographic_matrix, create_view_matrix, create_viewport_matrix, world_to_screen};
+use crate::orbits::{ellipse_point, MAX_ECCENTRICITY};
+use crate::planet_positions;
+use crate::scene::Scene;
+use crate::shadow::STAR_SHADER_MODE;
+
+// Lado del recuadro y separación con la esquina inferior izquierda, en píxeles
+const MINIMAP_SIZE: usize = 160;
+const MINIMAP_MARGIN: usize = 10;
+
+// Fracción del recuadro que ocupa la órbita más grande, para que los puntos no toquen el borde
+const MINIMAP_FILL: f32 = 0.9;
+
+// Extensión mínima (en unidades del mundo) para que un sistema sin órbitas no se amplíe sin fin
+const MIN_EXTENT: f32 = 5.0;
+
+// El fondo del recuadro oscurece la escena en vez de taparla
+const MINIMAP_SHADE: f32 = 0.35;
+
+const MINIMAP_SEGMENTS: usize = 64;
+const MINIMAP_BORDER_COLOR: u32 = 0x808080;
+const MINIMAP_ORBIT_COLOR: u32 = 0x505050;
+const MINIMAP_PLANET_COLOR: u32 = 0xE0E0E0;
+const MINIMAP_STAR_COLOR: u32 = 0xFFD060;
+const MINIMAP_CAMERA_COLOR: u32 = 0x00FF66;
+
+// Radio de los puntos de los planetas y longitud de la flecha de la cámara, en píxeles
+const PLANET_DOT_RADIUS: isize = 2;
+const CAMERA_ARROW_LENGTH: f32 = 10.0;
+
+// Vista cenital esquemática del sistema en la esquina inferior izquierda: la órbita de cada
+// planeta, un punto en su posición actual y una flecha con la posición y la dirección de la
+// cámara. Se proyecta con su propia cámara ortográfica mirando hacia -Y, con -Z hacia arriba,
+// y se ajusta a la órbita más grande; la cámara se queda en el borde si está más lejos.
+pub fn draw_minimap(framebuffer: &mut Framebuffer, scene: &Scene, camera: &Camera) {
+    let size = MINIMAP_SIZE.min(framebuffer.width.min(framebuffer.height).saturating_sub(2 * MINIMAP_MARGIN));
+    if size < 2 {
+        return;
+    }
+    let (left, top) = (MINIMAP_MARGIN, framebuffer.height - size - MINIMAP_MARGIN);
+
+    let extent = scene.planets.iter()
+        .map(|planet| scene.system_scale.orbit_radius(planet) * (1.0 + planet.eccentricity.clamp(0.0, MAX_ECCENTRICITY)))
+        .fold(MIN_EXTENT, f32::max) / MINIMAP_FILL;
+    let view_matrix = create_view_matrix(Vec3::new(0.0, extent, 0.0), Vec3::zeros(), Vec3::new(0.0, 0.0, -1.0));
+    let projection_matrix = create_orthographic_matrix(extent, 0.0, 2.0 * extent);
+    let viewport_matrix = create_viewport_matrix(size as f32, size as f32);
+    // La proyección ortográfica no tiene puntos detrás de la cámara; se aplana la altura para que
+    // nada quede fuera de los planos cercano y lejano
+    let to_pixel = |point: Vec3| {
+        let screen = world_to_screen(Vec3::new(point.x, 0.0, point.z), &view_matrix, &projection_matrix, &viewport_matrix)
+            .unwrap_or_default();
+        Vec2::new(left as f32 + screen.x, top as f32 + screen.y)
+    };
+
+    for y in top..top + size {
+        for x in left..left + size {
+            let index = y * framebuffer.width + x;
+            framebuffer.buffer[index] = (Color::from_hex(framebuffer.buffer[index]) * MINIMAP_SHADE).to_hex();
+        }
+    }
+
+    let inside = |point: Vec2| {
+        point.x >= left as f32 && point.y >= top as f32 && point.x < (left + size) as f32 && point.y < (top + size) as f32
+    };
+    let line = |framebuffer: &mut Framebuffer, start: Vec2, end: Vec2, color: u32| {
+        // Los segmentos se recortan al recuadro descartándolos enteros; basta con que las
+        // órbitas quepan, y la cámara se lleva al borde antes de dibujarla
+        if inside(start) && inside(end) {
+            framebuffer.line(start.x as isize, start.y as isize, end.x as isize, end.y as isize, color);
+        }
+    };
+
+    let step = 2.0 * std::f32::consts::PI / MINIMAP_SEGMENTS as f32;
+    for planet in &scene.planets {
+        let orbit_radius = scene.system_scale.orbit_radius(planet);
+        if orbit_radius <= 0.0 || scene.nbody {
+            continue;
+        }
+        let eccentricity = planet.eccentricity.clamp(0.0, MAX_ECCENTRICITY);
+        for segment in 0..MINIMAP_SEGMENTS {
+            let start = to_pixel(ellipse_point(orbit_radius, eccentricity, segment as f32 * step));
+            let end = to_pixel(ellipse_point(orbit_radius, eccentricity, (segment + 1) as f32 * step));
+            line(framebuffer, start, end, MINIMAP_ORBIT_COLOR);
+        }
+    }
+
+    for (planet, position) in scene.planets.iter().zip(planet_positions(scene)) {
+        let center = to_pixel(position);
+        if !inside(center) {
+            continue;
+        }
+        let color = if planet.shader_mode == STAR_SHADER_MODE { MINIMAP_STAR_COLOR } else { MINIMAP_PLANET_COLOR };
+        for dy in -PLANET_DOT_RADIUS..=PLANET_DOT_RADIUS {
+            for dx in -PLANET_DOT_RADIUS..=PLANET_DOT_RADIUS {
+                if dx * dx + dy * dy <= PLANET_DOT_RADIUS * PLANET_DOT_RADIUS {
+                    framebuffer.set_pixel((center.x as isize + dx) as usize, (center.y as isize + dy) as usize, color);
+                }
+            }
+        }
+    }
+
+    // La cámara: su posición llevada al borde del recuadro si queda fuera y una flecha hacia
+    // donde mira, proyectada en el plano de las órbitas
+    let margin = CAMERA_ARROW_LENGTH;
+    let eye = to_pixel(camera.eye);
+    let eye = Vec2::new(
+        eye.x.clamp(left as f32 + margin, (left + size) as f32 - 1.0 - margin),
+        eye.y.clamp(top as f32 + margin, (top + size) as f32 - 1.0 - margin),
+    );
+    let forward = to_pixel(camera.center) - to_pixel(camera.eye);
+    let forward = if forward.magnitude() > f32::EPSILON { forward.normalize() } else { Vec2::new(0.0, -1.0) };
+    let tip = eye + forward * CAMERA_ARROW_LENGTH;
+    let side = Vec2::new(-forward.y, forward.x) * (CAMERA_ARROW_LENGTH * 0.4);
+    let back = eye - forward * (CAMERA_ARROW_LENGTH * 0.3);
+    line(framebuffer, back + side, tip, MINIMAP_CAMERA_COLOR);
+    line(framebuffer, back - side, tip, MINIMAP_CAMERA_COLOR);
+    line(framebuffer, back + side, back - side, MINIMAP_CAMERA_COLOR);
+
+    let (right, bottom) = ((left + size - 1) as isize, (top + size - 1) as isize);
+    let (left, top) = (left as isize, top as isize);
+    framebuffer.line(left, top, right, top, MINIMAP_BORDER_COLOR);
+    framebuffer.line(right, top, right, bottom, MINIMAP_BORDER_COLOR);
+    framebuffer.line(right, bottom, left, bottom, MINIMAP_BORDER_COLOR);
+    framebuffer.line(left, bottom, left, top, MINIMAP_BORDER_COLOR);
+}
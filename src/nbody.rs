@@ -0,0 +1,109 @@
+use nalgebra_glm::Vec3;
+use crate::Planet;
+
+// Constante gravitacional en unidades de la simulación (distancia de escena, tiempo en frames)
+pub const GRAVITATIONAL_CONSTANT: f32 = 1.0;
+
+// Suavizado para evitar aceleraciones infinitas cuando dos cuerpos se acercan demasiado
+const SOFTENING: f32 = 0.5;
+
+// Prepara el modo n-cuerpos: coloca cada planeta en su posición orbital actual
+// y le da la velocidad de una órbita circular alrededor del cuerpo más masivo
+pub fn init_nbody(planets: &mut [Planet], positions: &[Vec3]) {
+    for (planet, position) in planets.iter_mut().zip(positions) {
+        planet.position = *position;
+        planet.velocity = Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    let Some(sun_index) = (0..planets.len()).max_by(|&a, &b| planets[a].mass.total_cmp(&planets[b].mass)) else {
+        return;
+    };
+    let sun_position = planets[sun_index].position;
+    let sun_mass = planets[sun_index].mass;
+
+    for (i, planet) in planets.iter_mut().enumerate() {
+        let offset = planet.position - sun_position;
+        let radius = offset.magnitude();
+        if i == sun_index || radius == 0.0 {
+            continue;
+        }
+
+        // Misma dirección de giro que las órbitas analíticas (ángulo creciente en XZ)
+        let tangent = Vec3::new(-offset.z, 0.0, offset.x).normalize();
+        planet.velocity = tangent * (GRAVITATIONAL_CONSTANT * sun_mass / radius).sqrt();
+    }
+}
+
+fn accelerations(planets: &[Planet]) -> Vec<Vec3> {
+    planets.iter().enumerate().map(|(i, planet)| {
+        planets.iter().enumerate()
+            .filter(|&(j, _)| j != i)
+            .fold(Vec3::new(0.0, 0.0, 0.0), |acceleration, (_, other)| {
+                let offset = other.position - planet.position;
+                let distance_sq = offset.magnitude_squared() + SOFTENING * SOFTENING;
+                acceleration + offset * (GRAVITATIONAL_CONSTANT * other.mass / (distance_sq * distance_sq.sqrt()))
+            })
+    }).collect()
+}
+
+// Avanza la simulación un paso `dt` con integración velocity-Verlet
+pub fn integrate_nbody(planets: &mut [Planet], dt: f32) {
+    let initial = accelerations(planets);
+    for (planet, acceleration) in planets.iter_mut().zip(&initial) {
+        planet.position += planet.velocity * dt + acceleration * (0.5 * dt * dt);
+    }
+
+    let updated = accelerations(planets);
+    for ((planet, a0), a1) in planets.iter_mut().zip(&initial).zip(&updated) {
+        planet.velocity += (a0 + a1) * (0.5 * dt);
+    }
+}
+
+// Energía total (cinética + potencial); debería mantenerse aproximadamente constante
+pub fn total_energy(planets: &[Planet]) -> f32 {
+    let kinetic: f32 = planets.iter()
+        .map(|planet| 0.5 * planet.mass * planet.velocity.magnitude_squared())
+        .sum();
+
+    let mut potential = 0.0;
+    for (i, a) in planets.iter().enumerate() {
+        for b in &planets[i + 1..] {
+            let distance = ((b.position - a.position).magnitude_squared() + SOFTENING * SOFTENING).sqrt();
+            potential -= GRAVITATIONAL_CONSTANT * a.mass * b.mass / distance;
+        }
+    }
+
+    kinetic + potential
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::PresetRegistry;
+
+    const STEPS: usize = 5000;
+    // Deriva relativa de energía admitida tras `STEPS` pasos de un frame (Verlet se queda en ~1e-6)
+    const MAX_ENERGY_DRIFT: f32 = 1e-3;
+
+    #[test]
+    fn verlet_keeps_energy_and_orbits_bounded() {
+        let registry = PresetRegistry::with_builtins();
+        let body = |name: &str, orbit_radius: f32, orbit_phase: f32| {
+            Planet::from_preset(registry.get(name).unwrap(), orbit_radius, 0.0, orbit_phase)
+        };
+        let mut planets = vec![body("sun", 0.0, 0.0), body("rocky", 10.0, 0.0), body("icy", 15.0, 2.0)];
+        let positions: Vec<Vec3> = planets.iter().map(|planet| planet.position).collect();
+        init_nbody(&mut planets, &positions);
+
+        let initial_energy = total_energy(&planets);
+        for _ in 0..STEPS {
+            integrate_nbody(&mut planets, 1.0);
+            // La órbita circular inicial solo se deforma por el suavizado y el tirón del otro planeta
+            let radius = (planets[1].position - planets[0].position).magnitude();
+            assert!((9.5..10.5).contains(&radius), "{}", radius);
+        }
+
+        let drift = (total_energy(&planets) - initial_energy).abs() / initial_energy.abs();
+        assert!(drift < MAX_ENERGY_DRIFT, "{}", drift);
+    }
+}
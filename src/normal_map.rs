@@ -0,0 +1,48 @@
+use fastnoise_lite::{FastNoiseLite, FractalType, NoiseType};
+use image::{Rgb, RgbImage};
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Resolución del mapa de normales del terreno; el doble de ancho porque cubre 360° por 180°
+pub const TERRAIN_NORMAL_MAP_WIDTH: u32 = 1024;
+pub const TERRAIN_NORMAL_MAP_HEIGHT: u32 = 512;
+
+const TERRAIN_SEED: i32 = 4242;
+const TERRAIN_FREQUENCY: f32 = 4.0;
+const TERRAIN_OCTAVES: i32 = 4;
+
+// Cuánto inclina las normales la pendiente del relieve; más alto, más marcado
+const TERRAIN_RELIEF: f32 = 3.0;
+
+// Mapa de normales en espacio tangente de un relieve rocoso. La altura se toma de ruido 3D sobre
+// la esfera unidad (u = longitud, v = latitud desde el polo norte), así que no hay costura entre
+// los bordes izquierdo y derecho. Cada píxel guarda la normal (x hacia +u, y hacia +v, z hacia
+// fuera) codificada de [-1, 1] a [0, 255].
+pub fn terrain_normal_map(width: u32, height: u32) -> RgbImage {
+    let mut noise = FastNoiseLite::with_seed(TERRAIN_SEED);
+    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+    noise.set_fractal_type(Some(FractalType::FBm));
+    noise.set_fractal_octaves(Some(TERRAIN_OCTAVES));
+    noise.set_frequency(Some(TERRAIN_FREQUENCY));
+
+    let heights: Vec<f32> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let phi = 2.0 * PI * (x as f32 + 0.5) / width as f32;
+            let theta = PI * (y as f32 + 0.5) / height as f32;
+            noise.get_noise_3d(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+        })
+        .collect();
+    let height_at = |x: u32, y: u32| heights[(y * width + x) as usize];
+
+    RgbImage::from_fn(width, height, |x, y| {
+        // Diferencias centrales: en horizontal se da la vuelta y en vertical se fija a los polos
+        let (left, right) = ((x + width - 1) % width, (x + 1) % width);
+        let (up, down) = (y.saturating_sub(1), (y + 1).min(height - 1));
+        let slope_u = (height_at(right, y) - height_at(left, y)) * 0.5;
+        let slope_v = (height_at(x, down) - height_at(x, up)) * 0.5;
+
+        let normal = Vec3::new(-slope_u * TERRAIN_RELIEF, -slope_v * TERRAIN_RELIEF, 1.0).normalize();
+        let channel = |value: f32| ((value * 0.5 + 0.5) * 255.0 + 0.5) as u8;
+        Rgb([channel(normal.x), channel(normal.y), channel(normal.z)])
+    })
+}
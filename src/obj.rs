@@ -1,9 +1,16 @@
-use tobj;
-use nalgebra_glm::{Vec2, Vec3};
+use std::path::Path;
+use image::DynamicImage;
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::vertex::Vertex;
 
+// Radio de la esfera del modelo (sphere-1.obj) antes de escalarla: la escala de un planeta es su
+// diámetro. Las esferas generadas para los niveles de detalle usan el mismo.
+pub const SPHERE_MODEL_RADIUS: f32 = 0.5;
+
 pub struct Obj {
     meshes: Vec<Mesh>,
+    aabb: (Vec3, Vec3),
+    diffuse_texture: Option<DynamicImage>,  // Textura difusa (`map_Kd`) del primer material que la tenga
 }
 
 struct Mesh {
@@ -11,11 +18,15 @@ struct Mesh {
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
     indices: Vec<u32>,
+    tangents: Vec<Vec4>,  // Por vértice; vacío si la malla no tiene coordenadas de textura
 }
 
 impl Obj {
+    // Con `triangulate` tobj convierte las caras de más de tres vértices (quads, n-gonos) en un
+    // abanico de triángulos desde el primer vértice, y resuelve los índices negativos (relativos a
+    // los vértices leídos hasta esa línea), así que se aceptan modelos sin triangular antes.
     pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
+        let (models, materials) = tobj::load_obj(filename, &tobj::LoadOptions {
             single_index: true,
             triangulate: true,
             ..Default::default()
@@ -23,7 +34,7 @@ impl Obj {
 
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
-            Mesh {
+            let mut mesh = Mesh {
                 vertices: mesh.positions.chunks(3)
                     .map(|v| Vec3::new(v[0], v[1], v[2]))
                     .collect(),
@@ -34,10 +45,30 @@ impl Obj {
                     .map(|t| Vec2::new(t[0], 1.0 - t[1]))
                     .collect(),
                 indices: mesh.indices,
-            }
-        }).collect();
+                tangents: Vec::new(),
+            };
+            mesh.tangents = compute_tangents(&mesh);
+            mesh
+        }).collect::<Vec<Mesh>>();
+
+        let aabb = bounding_box(meshes.iter().flat_map(|mesh| mesh.vertices.iter()));
+        let diffuse_texture = load_diffuse_texture(filename, materials);
+        Ok(Obj { meshes, aabb, diffuse_texture })
+    }
+
+    pub fn diffuse_texture(&self) -> Option<&DynamicImage> {
+        self.diffuse_texture.as_ref()
+    }
+
+    // Sin coordenadas de textura en todas las mallas no se puede aplicar la textura difusa
+    pub fn has_texcoords(&self) -> bool {
+        !self.meshes.is_empty() && self.meshes.iter().all(|mesh| !mesh.texcoords.is_empty())
+    }
 
-        Ok(Obj { meshes })
+    // Esquinas mínima y máxima de la caja alineada a los ejes que contiene el modelo,
+    // en coordenadas del objeto
+    pub fn aabb(&self) -> (Vec3, Vec3) {
+        self.aabb
     }
 
     pub fn get_vertex_array(&self) -> Vec<Vertex> {
@@ -53,10 +84,151 @@ impl Obj {
                     .cloned()
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
-                vertices.push(Vertex::new(position, normal, tex_coords));
+                let mut vertex = Vertex::new(position, normal, tex_coords);
+                vertex.tangent = mesh.tangents.get(index as usize).cloned().unwrap_or(Vec4::zeros());
+                vertices.push(vertex);
             }
         }
 
         vertices
     }
 }
+
+// Tangentes por vértice a partir de las coordenadas de textura: cada triángulo aporta la dirección
+// en la que crecen u y v sobre su superficie y se promedian en los vértices que comparte. La
+// tangente se ortogonaliza respecto a la normal y `w` indica si la bitangente es N × T (1) o su
+// opuesta (-1). Los vértices sin UVs, o con UVs degeneradas, quedan con tangente cero.
+fn compute_tangents(mesh: &Mesh) -> Vec<Vec4> {
+    if mesh.texcoords.len() != mesh.vertices.len() {
+        return Vec::new();
+    }
+
+    let mut tangents = vec![Vec3::zeros(); mesh.vertices.len()];
+    let mut bitangents = vec![Vec3::zeros(); mesh.vertices.len()];
+    for face in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [face[0] as usize, face[1] as usize, face[2] as usize];
+        let (edge_1, edge_2) = (mesh.vertices[b] - mesh.vertices[a], mesh.vertices[c] - mesh.vertices[a]);
+        let (duv_1, duv_2) = (mesh.texcoords[b] - mesh.texcoords[a], mesh.texcoords[c] - mesh.texcoords[a]);
+
+        let determinant = duv_1.x * duv_2.y - duv_2.x * duv_1.y;
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge_1 * duv_2.y - edge_2 * duv_1.y) / determinant;
+        let bitangent = (edge_2 * duv_1.x - edge_1 * duv_2.x) / determinant;
+        for index in [a, b, c] {
+            tangents[index] += tangent;
+            bitangents[index] += bitangent;
+        }
+    }
+
+    tangents.iter().zip(&bitangents).enumerate().map(|(index, (tangent, bitangent))| {
+        let normal = mesh.normals.get(index).cloned().unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+        let tangent = tangent - normal * normal.dot(tangent);
+        if tangent.magnitude() <= f32::EPSILON {
+            return Vec4::zeros();
+        }
+        let tangent = tangent.normalize();
+        let handedness = if normal.cross(&tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+        Vec4::new(tangent.x, tangent.y, tangent.z, handedness)
+    }).collect()
+}
+
+// Carga la textura difusa del .mtl que acompaña al OBJ. Las rutas del .mtl son relativas a la
+// carpeta del OBJ. La textura es opcional: si falta el .mtl, el material no tiene `map_Kd` o la
+// imagen no se puede abrir, el modelo se dibuja con su shader procedural.
+fn load_diffuse_texture(filename: &str, materials: Result<Vec<tobj::Material>, tobj::LoadError>) -> Option<DynamicImage> {
+    let materials = materials.ok()?;
+    let texture = materials.iter().find_map(|material| material.diffuse_texture.as_ref())?;
+    let path = Path::new(filename).parent().unwrap_or(Path::new("")).join(texture);
+
+    match image::open(&path) {
+        Ok(image) => Some(image),
+        Err(err) => {
+            eprintln!("No se pudo cargar la textura {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+fn bounding_box<'a>(points: impl Iterator<Item = &'a Vec3>) -> (Vec3, Vec3) {
+    let mut min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut max = Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for point in points {
+        min = min.inf(point);
+        max = max.sup(point);
+    }
+
+    // Un modelo sin vértices tiene una caja vacía en el origen
+    if min.x > max.x {
+        return (Vec3::zeros(), Vec3::zeros());
+    }
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Escribe `contents` como un .obj temporal y lo carga
+    fn load_inline(name: &str, contents: &str) -> Obj {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        let obj = Obj::load(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        obj.expect("el .obj de prueba no se puede cargar")
+    }
+
+    #[test]
+    fn aabb_matches_mesh_extents() {
+        // Un tetraedro cuyas coordenadas extremas están repartidas entre vértices distintos
+        let obj = load_inline("space_travel_aabb.obj", "\
+v -1.0 0.5 2.0
+v 3.0 -2.0 0.0
+v 0.0 4.0 -1.5
+v 0.5 0.0 6.0
+f 1 2 3
+f 1 2 4
+f 2 3 4
+f 1 3 4
+");
+        assert_eq!(obj.aabb(), (Vec3::new(-1.0, -2.0, -1.5), Vec3::new(3.0, 4.0, 6.0)));
+    }
+
+    #[test]
+    fn quad_faces_with_negative_indices_are_triangulated() {
+        // Cubo de lado 2 con caras de cuatro vértices referidos con índices relativos
+        let obj = load_inline("space_travel_quad_cube.obj", "\
+v -1.0 -1.0 -1.0
+v 1.0 -1.0 -1.0
+v 1.0 1.0 -1.0
+v -1.0 1.0 -1.0
+v -1.0 -1.0 1.0
+v 1.0 -1.0 1.0
+v 1.0 1.0 1.0
+v -1.0 1.0 1.0
+f -8 -7 -6 -5
+f -4 -3 -2 -1
+f -8 -7 -3 -4
+f -7 -6 -2 -3
+f -6 -5 -1 -2
+f -5 -8 -4 -1
+");
+        let vertices = obj.get_vertex_array();
+        assert_eq!(vertices.len() / 3, 12);
+
+        // Cada quad se abre en abanico desde su primer vértice
+        let corner = |x: f32, y: f32, z: f32| Vec3::new(x, y, z);
+        let first_face: Vec<Vec3> = vertices[..6].iter().map(|vertex| vertex.position).collect();
+        assert_eq!(first_face, [
+            corner(-1.0, -1.0, -1.0), corner(1.0, -1.0, -1.0), corner(1.0, 1.0, -1.0),
+            corner(-1.0, -1.0, -1.0), corner(1.0, 1.0, -1.0), corner(-1.0, 1.0, -1.0),
+        ]);
+        let last_face: Vec<Vec3> = vertices[30..].iter().map(|vertex| vertex.position).collect();
+        assert_eq!(last_face, [
+            corner(-1.0, 1.0, -1.0), corner(-1.0, -1.0, -1.0), corner(-1.0, -1.0, 1.0),
+            corner(-1.0, 1.0, -1.0), corner(-1.0, -1.0, 1.0), corner(-1.0, 1.0, 1.0),
+        ]);
+        assert_eq!(obj.aabb(), (corner(-1.0, -1.0, -1.0), corner(1.0, 1.0, 1.0)));
+    }
+}
@@ -0,0 +1,123 @@
+use nalgebra_glm::{Vec3, Vec4};
+
+use crate::framebuffer::Framebuffer;
+use crate::line::line;
+use crate::vertex::Vertex;
+use crate::Uniforms;
+
+// Gris tenue para que las órbitas no compitan con los planetas
+const ORBIT_COLOR: u32 = 0x404040;
+
+// Segmentos por órbita; con menos se notan los vértices en las órbitas grandes
+pub const ORBIT_SEGMENTS: usize = 128;
+
+// Los puntos con w por debajo de este valor están detrás (o casi encima) de la cámara
+pub const MIN_CLIP_W: f32 = 1e-3;
+
+// Las órbitas con excentricidad 1 o mayor no son cerradas
+pub const MAX_ECCENTRICITY: f32 = 0.99;
+
+// Constante k de la tercera ley de Kepler en unidades de la escena: un cuerpo con semieje mayor a
+// avanza k / a^1.5 radianes de anomalía media por frame, así que los interiores van más deprisa
+pub const KEPLER_CONSTANT: f32 = 1.0;
+
+// Velocidad angular media de una órbita de semieje mayor `semi_major_axis` según Kepler; 0 si no
+// hay órbita
+pub fn kepler_orbit_speed(semi_major_axis: f32) -> f32 {
+    if semi_major_axis <= 0.0 {
+        return 0.0;
+    }
+    KEPLER_CONSTANT / semi_major_axis.powf(1.5)
+}
+
+// Iteraciones de Newton para la ecuación de Kepler; de sobra para excentricidades moderadas
+const KEPLER_ITERATIONS: usize = 8;
+
+// Resuelve la ecuación de Kepler M = E - e·sin(E): anomalía excéntrica a partir de la media
+fn eccentric_anomaly(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mut anomaly = mean_anomaly;
+    for _ in 0..KEPLER_ITERATIONS {
+        anomaly -= (anomaly - eccentricity * anomaly.sin() - mean_anomaly) / (1.0 - eccentricity * anomaly.cos());
+    }
+    anomaly
+}
+
+// Punto de una elipse en el plano XZ con el origen (el Sol) en uno de sus focos y el periastro
+// hacia +X, dado por su anomalía excéntrica
+pub fn ellipse_point(semi_major_axis: f32, eccentricity: f32, anomaly: f32) -> Vec3 {
+    let semi_minor_axis = semi_major_axis * (1.0 - eccentricity * eccentricity).sqrt();
+    Vec3::new(semi_major_axis * (anomaly.cos() - eccentricity), 0.0, semi_minor_axis * anomaly.sin())
+}
+
+// Posición en una órbita kepleriana tras recorrer `mean_anomaly` radianes de anomalía media, que
+// avanza de forma uniforme con el tiempo; así el planeta va más deprisa cerca del periastro.
+// Con excentricidad 0 es exactamente la circunferencia de radio `semi_major_axis`.
+pub fn kepler_position(semi_major_axis: f32, eccentricity: f32, mean_anomaly: f32) -> Vec3 {
+    let eccentricity = eccentricity.clamp(0.0, MAX_ECCENTRICITY);
+    ellipse_point(semi_major_axis, eccentricity, eccentric_anomaly(mean_anomaly, eccentricity))
+}
+
+// Dibuja la órbita elíptica de semieje mayor `semi_major_axis` alrededor del origen en el plano
+// XZ (ver `kepler_position`), con prueba de profundidad, usando las matrices de vista,
+// proyección y viewport de `uniforms`.
+pub fn draw_orbit(framebuffer: &mut Framebuffer, uniforms: &Uniforms, semi_major_axis: f32, eccentricity: f32, segments: usize) {
+    let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+    let eccentricity = eccentricity.clamp(0.0, MAX_ECCENTRICITY);
+    // Los segmentos se reparten en anomalía excéntrica, que no necesita resolver Kepler
+    let clip = |anomaly: f32| {
+        let point = ellipse_point(semi_major_axis, eccentricity, anomaly);
+        view_projection * Vec4::new(point.x, point.y, point.z, 1.0)
+    };
+
+    framebuffer.set_current_color(ORBIT_COLOR);
+    let step = 2.0 * std::f32::consts::PI / segments as f32;
+    for segment in 0..segments {
+        let start = clip(segment as f32 * step);
+        let end = clip((segment + 1) as f32 * step);
+
+        let Some((a, b)) = screen_segment(framebuffer, uniforms, start, end) else {
+            continue;
+        };
+
+        for fragment in line(&a, &b) {
+            if fragment.position.x >= 0.0 && fragment.position.y >= 0.0 {
+                framebuffer.point(fragment.position.x as usize, fragment.position.y as usize, fragment.depth);
+            }
+        }
+    }
+}
+
+// Lleva a pantalla un segmento dado en espacio de recorte, quedándose con la parte delante de la
+// cámara. None si queda entero detrás o si en pantalla es tan largo que solo puede venir de un
+// punto casi en el plano de la cámara.
+pub fn screen_segment(framebuffer: &Framebuffer, uniforms: &Uniforms, start: Vec4, end: Vec4) -> Option<(Vertex, Vertex)> {
+    let (start, end) = clip_near(start, end)?;
+
+    let to_vertex = |point: Vec4| {
+        let ndc = point / point.w;
+        let screen = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+        let mut vertex = Vertex::new(Vec3::zeros(), Vec3::zeros(), Default::default());
+        vertex.transformed_position = Vec3::new(screen.x, screen.y, screen.z);
+        vertex
+    };
+    let (a, b) = (to_vertex(start), to_vertex(end));
+
+    let length = (b.transformed_position - a.transformed_position).magnitude();
+    if !length.is_finite() || length > 4.0 * (framebuffer.width + framebuffer.height) as f32 {
+        return None;
+    }
+    Some((a, b))
+}
+
+// Recorta un segmento en espacio de recorte para quedarse con la parte delante de la cámara
+fn clip_near(start: Vec4, end: Vec4) -> Option<(Vec4, Vec4)> {
+    match (start.w >= MIN_CLIP_W, end.w >= MIN_CLIP_W) {
+        (true, true) => Some((start, end)),
+        (false, false) => None,
+        (start_visible, _) => {
+            let t = (MIN_CLIP_W - start.w) / (end.w - start.w);
+            let crossing = start + (end - start) * t;
+            if start_visible { Some((start, crossing)) } else { Some((crossing, end)) }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+// Los últimos microsegundos se esperan activamente: `sleep` puede despertar tarde
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+// Tope del tiempo real que se simula en un frame: tras una pausa larga (ventana arrastrada,
+// depurador) la simulación sigue desde donde estaba en lugar de dar un salto
+const MAX_DELTA_TIME: Duration = Duration::from_millis(250);
+
+// Limita la frecuencia de frames a un intervalo mínimo, descontando lo que tardó el frame en
+// renderizarse, y mide el tiempo real que pasa entre un frame y el siguiente
+pub struct FramePacer {
+    interval: Duration,
+    enabled: bool,
+    deadline: Instant,
+    last_frame: Instant,
+    pub late_frames: u32,  // Frames que terminaron después de su plazo
+}
+
+impl FramePacer {
+    pub fn new(interval: Duration, enabled: bool) -> Self {
+        FramePacer {
+            interval,
+            enabled,
+            deadline: Instant::now() + interval,
+            last_frame: Instant::now(),
+            late_frames: 0,
+        }
+    }
+
+    // Segundos reales desde la llamada anterior (o desde que se creó), como mucho `MAX_DELTA_TIME`.
+    // Se llama una vez al principio de cada frame.
+    pub fn delta_time(&mut self) -> f32 {
+        let now = Instant::now();
+        let elapsed = now - self.last_frame;
+        self.last_frame = now;
+        elapsed.min(MAX_DELTA_TIME).as_secs_f32()
+    }
+
+    // Espera hasta el final del intervalo del frame actual, si queda tiempo, y empieza el siguiente
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        if now > self.deadline {
+            // Frame tardío: no intentamos recuperar el tiempo perdido, solo seguimos desde ahora
+            self.late_frames += 1;
+            self.deadline = now + self.interval;
+            return;
+        }
+
+        if !self.enabled {
+            // Sin espera (benchmarks): el plazo solo sirve para contar frames tardíos
+            self.deadline = now + self.interval;
+            return;
+        }
+
+        let remaining = self.deadline - now;
+        if remaining > SPIN_THRESHOLD {
+            std::thread::sleep(remaining - SPIN_THRESHOLD);
+        }
+        while Instant::now() < self.deadline {
+            std::hint::spin_loop();
+        }
+
+        self.deadline += self.interval;
+    }
+}
@@ -0,0 +1,42 @@
+use nalgebra_glm::Vec3;
+
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::math::{create_perspective_matrix, create_view_matrix, create_viewport_matrix, world_to_screen};
+use crate::obj::SPHERE_MODEL_RADIUS;
+use crate::planet_positions;
+use crate::scene::Scene;
+
+// Brazos de la mira y hueco central, en píxeles
+const CROSSHAIR_SIZE: isize = 10;
+const CROSSHAIR_GAP: isize = 3;
+
+// Planeta cuyo disco en pantalla contiene el punto (x, y); si hay varios, el más cercano a la cámara
+pub fn pick_planet(scene: &Scene, camera: &Camera, width: usize, height: usize, x: f32, y: f32) -> Option<usize> {
+    let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+    let projection_matrix = create_perspective_matrix(width as f32, height as f32, camera.fov);
+    let viewport_matrix = create_viewport_matrix(width as f32, height as f32);
+    let project = |point: Vec3| world_to_screen(point, &view_matrix, &projection_matrix, &viewport_matrix);
+    let right = camera.right();
+
+    scene.planets.iter()
+        .zip(planet_positions(scene))
+        .enumerate()
+        .filter_map(|(index, (planet, position))| {
+            let radius = scene.system_scale.scale(planet) * SPHERE_MODEL_RADIUS;
+            let center = project(position)?;
+            let edge = project(position + right * radius)?;
+            let radius_px = (edge.x - center.x).hypot(edge.y - center.y).max(1.0);
+            ((x - center.x).hypot(y - center.y) <= radius_px).then_some((index, center.z))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+// Mira en forma de cruz con el centro hueco para no tapar el píxel al que se apunta
+pub fn draw_crosshair(framebuffer: &mut Framebuffer, x: isize, y: isize, color: u32) {
+    framebuffer.line(x - CROSSHAIR_SIZE, y, x - CROSSHAIR_GAP, y, color);
+    framebuffer.line(x + CROSSHAIR_GAP, y, x + CROSSHAIR_SIZE, y, color);
+    framebuffer.line(x, y - CROSSHAIR_SIZE, x, y - CROSSHAIR_GAP, color);
+    framebuffer.line(x, y + CROSSHAIR_GAP, x, y + CROSSHAIR_SIZE, color);
+}
@@ -0,0 +1,140 @@
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::scale::RealScale;
+
+// Cobertura de nubes con la que el planeta Tierra se ve como antes de hacerla configurable
+pub const DEFAULT_CLOUD_COVERAGE: f32 = 0.3;
+
+// Apariencia y propiedades físicas de un tipo de planeta, independientes de su órbita
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlanetPreset {
+    pub shader_mode: u8,
+    pub scale: f32,
+    pub rotation_speed: f32,
+    #[serde(default = "default_noise_zoom")]
+    pub noise_zoom: f32,
+    #[serde(default)]
+    pub mass: f32,  // Solo se usa en el modo n-cuerpos
+    #[serde(default = "default_cloud_coverage")]
+    pub cloud_coverage: f32,
+    #[serde(default)]
+    pub star_color: Option<Vec3>,  // Solo estrellas
+    #[serde(default)]
+    pub real: Option<RealScale>,  // Tamaño y órbita del cuerpo real equivalente, para la vista a escala
+}
+
+fn default_noise_zoom() -> f32 {
+    1.0
+}
+
+fn default_cloud_coverage() -> f32 {
+    DEFAULT_CLOUD_COVERAGE
+}
+
+// Presets disponibles por nombre. Se pueden registrar presets propios para reutilizarlos en
+// varios cuerpos; un nombre repetido reemplaza al anterior.
+#[derive(Default)]
+pub struct PresetRegistry {
+    presets: HashMap<String, PlanetPreset>,
+}
+
+impl PresetRegistry {
+    // Registro con un preset por cada shader de planeta
+    pub fn with_builtins() -> Self {
+        let mut registry = PresetRegistry::default();
+        // El último campo es el cuerpo real análogo (radio en km, órbita en UA): Sol, Mercurio,
+        // Júpiter, Urano, Venus y la Tierra. El planeta alienígena no tiene equivalente.
+        let builtins = [
+            ("sun", 1, 4.0, 0.0, 0.5, Some((696_000.0, 0.0))),
+            ("rocky", 2, 2.4, 0.1, 0.00002, Some((2_440.0, 0.387))),
+            ("gas_giant", 3, 3.0, 0.05, 0.00005, Some((69_911.0, 5.2))),
+            ("icy", 4, 1.8, 0.1, 0.00001, Some((25_362.0, 19.2))),
+            ("volcanic", 5, 1.5, 0.01, 0.00002, Some((6_052.0, 0.723))),
+            ("earth", 6, 2.2, 0.1, 0.00003, Some((6_371.0, 1.0))),
+            ("alien", 7, 2.0, 0.08, 0.00002, None),
+        ];
+        for (name, shader_mode, scale, rotation_speed, mass, real) in builtins {
+            registry.register(name, PlanetPreset {
+                shader_mode,
+                scale,
+                rotation_speed,
+                noise_zoom: 1.0,
+                mass,
+                cloud_coverage: DEFAULT_CLOUD_COVERAGE,
+                star_color: None,
+                real: real.map(|(radius_km, orbit_au)| RealScale { radius_km, orbit_au }),
+            });
+        }
+
+        // Estrella compañera azulada para sistemas binarios
+        registry.register("companion", PlanetPreset {
+            shader_mode: 1,
+            scale: 1.2,
+            rotation_speed: 0.0,
+            noise_zoom: 1.0,
+            mass: 0.02,
+            cloud_coverage: DEFAULT_CLOUD_COVERAGE,
+            star_color: Some(Vec3::new(0.55, 0.75, 1.0)),
+            real: None,
+        });
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, preset: PlanetPreset) {
+        self.presets.insert(name.to_string(), preset);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PlanetPreset> {
+        self.presets.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_preset_is_found_by_name() {
+        let registry = PresetRegistry::with_builtins();
+        let earth = registry.get("earth").expect("el preset earth viene incluido");
+
+        assert_eq!(earth.shader_mode, 6);
+        assert_eq!(earth.scale, 2.2);
+        assert_eq!(earth.rotation_speed, 0.1);
+        assert_eq!(earth.cloud_coverage, DEFAULT_CLOUD_COVERAGE);
+        assert!(earth.star_color.is_none());
+        let real = earth.real.as_ref().expect("la Tierra tiene escala real");
+        assert_eq!((real.radius_km, real.orbit_au), (6_371.0, 1.0));
+
+        assert!(registry.get("alien").unwrap().real.is_none());
+        assert!(registry.get("pluto").is_none());
+    }
+
+    #[test]
+    fn registered_preset_replaces_one_with_the_same_name() {
+        let mut registry = PresetRegistry::with_builtins();
+        let custom = PlanetPreset {
+            shader_mode: 4,
+            scale: 0.7,
+            rotation_speed: 0.3,
+            noise_zoom: 2.5,
+            mass: 0.0,
+            cloud_coverage: 0.0,
+            star_color: None,
+            real: None,
+        };
+        registry.register("ice_moon", custom.clone());
+        registry.register("rocky", custom);
+
+        for name in ["ice_moon", "rocky"] {
+            let preset = registry.get(name).unwrap();
+            assert_eq!(preset.shader_mode, 4);
+            assert_eq!(preset.scale, 0.7);
+            assert_eq!(preset.noise_zoom, 2.5);
+            assert!(preset.real.is_none());
+        }
+        assert_eq!(registry.get("gas_giant").unwrap().shader_mode, 3);
+    }
+}
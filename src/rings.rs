@@ -0,0 +1,83 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat4};
+
+use crate::vertex::Vertex;
+
+// Radios de los anillos por defecto, en radios del planeta
+pub const DEFAULT_RING_INNER_RADIUS: f32 = 1.3;
+pub const DEFAULT_RING_OUTER_RADIUS: f32 = 2.3;
+// Hueco estrecho entre el anillo interior y el exterior, como fracción del ancho de los anillos
+pub const RING_GAP: f32 = 0.5;
+
+pub const RING_SEGMENTS: usize = 96;
+
+// Los gigantes gaseosos son los planetas que llevan anillos
+pub const RINGED_SHADER_MODE: u8 = 3;
+
+// Inclinación de los anillos respecto al plano de las órbitas, en radianes
+pub const RING_TILT: f32 = 0.45;
+
+// Corona circular plana en el plano XZ, con `segments` trapecios de dos triángulos. Se ve por
+// las dos caras, así que se dibuja sin descarte de caras traseras. La coordenada de textura v va
+// de 0 en el borde interior a 1 en el exterior.
+pub fn ring_mesh(inner_radius: f32, outer_radius: f32, segments: usize) -> Vec<Vertex> {
+    let normal = Vec3::new(0.0, 1.0, 0.0);
+    let point = |index: usize, radius: f32, v: f32| {
+        let angle = 2.0 * std::f32::consts::PI * index as f32 / segments as f32;
+        let position = Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin());
+        Vertex::new(position, normal, Vec2::new(index as f32 / segments as f32, v))
+    };
+
+    let mut vertices = Vec::with_capacity(segments * 6);
+    for index in 0..segments {
+        let inner_a = point(index, inner_radius, 0.0);
+        let inner_b = point(index + 1, inner_radius, 0.0);
+        let outer_a = point(index, outer_radius, 1.0);
+        let outer_b = point(index + 1, outer_radius, 1.0);
+
+        vertices.extend([inner_a.clone(), outer_a, outer_b.clone()]);
+        vertices.extend([inner_a, outer_b, inner_b]);
+    }
+    vertices
+}
+
+// Anillos de un planeta en el mundo, para calcular la sombra que proyectan sobre él
+#[derive(Clone, Copy)]
+pub struct RingShadow {
+    center: Vec3,
+    normal: Vec3,
+    inner_radius: f32,  // En unidades del mundo
+    outer_radius: f32,
+}
+
+impl RingShadow {
+    // Anillos de `ring_mesh(inner_radius, outer_radius, _)` colocados con `model_matrix`, que
+    // debe tener escala uniforme
+    pub fn new(model_matrix: &Mat4, inner_radius: f32, outer_radius: f32) -> Self {
+        let center = model_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        let axis = model_matrix * Vec4::new(0.0, 1.0, 0.0, 0.0);
+        let scale = Vec3::new(axis.x, axis.y, axis.z).magnitude();
+        RingShadow {
+            center: Vec3::new(center.x, center.y, center.z),
+            normal: Vec3::new(axis.x, axis.y, axis.z) / scale,
+            inner_radius: inner_radius * scale,
+            outer_radius: outer_radius * scale,
+        }
+    }
+
+    // Punto del plano de los anillos que cruza el segmento de `point` a `light_position`, como
+    // coordenada radial: 0 en el borde interior y 1 en el exterior (fuera de los anillos queda
+    // por debajo de 0 o por encima de 1). None si el segmento no corta el plano.
+    pub fn crossing(&self, point: Vec3, light_position: Vec3) -> Option<f32> {
+        let direction = light_position - point;
+        let along = self.normal.dot(&direction);
+        if along.abs() <= f32::EPSILON {
+            return None;
+        }
+        let t = self.normal.dot(&(self.center - point)) / along;
+        if t <= 0.0 || t >= 1.0 {
+            return None;
+        }
+        let radius = (point + direction * t - self.center).magnitude();
+        Some((radius - self.inner_radius) / (self.outer_radius - self.inner_radius))
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::obj::SPHERE_MODEL_RADIUS;
+use crate::shadow::STAR_SHADER_MODE;
+use crate::Planet;
+
+// Unidades de la escena por unidad astronómica: la órbita real de la Tierra coincide con la artística
+const SCENE_UNITS_PER_AU: f32 = 23.8;
+const KM_PER_AU: f32 = 149_597_870.0;
+
+// Fracción de la transición que se recorre en cada frame de 60 Hz (~1.5 s en total)
+const TRANSITION_STEP: f32 = 1.0 / 90.0;
+
+// Límites y factor del aumento de tamaño de los planetas en la vista a escala
+const MIN_EXAGGERATION: f32 = 1.0;
+const MAX_EXAGGERATION: f32 = 1024.0;
+pub const EXAGGERATION_STEP: f32 = 2.0;
+
+// Tamaño y distancia reales del cuerpo del sistema solar al que se parece un planeta
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RealScale {
+    pub radius_km: f32,
+    pub orbit_au: f32,
+}
+
+// Mezcla entre los tamaños y órbitas artísticos y los reales. Los cuerpos sin datos reales
+// (p. ej. la estrella compañera) conservan siempre sus valores artísticos.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct SystemScale {
+    pub realism: f32,       // 0 = artístico, 1 = a escala; avanza poco a poco hacia el objetivo
+    pub to_scale: bool,     // Objetivo de la transición
+    pub exaggeration: f32,  // Multiplica el tamaño real de los planetas (no de las estrellas)
+}
+
+impl Default for SystemScale {
+    fn default() -> Self {
+        SystemScale {
+            realism: 0.0,
+            to_scale: false,
+            exaggeration: MIN_EXAGGERATION,
+        }
+    }
+}
+
+impl SystemScale {
+    pub fn toggle(&mut self) {
+        self.to_scale = !self.to_scale;
+    }
+
+    // Avanza la transición hacia el objetivo lo que corresponde a `frames` frames de 60 Hz
+    pub fn step(&mut self, frames: f32) {
+        let target = if self.to_scale { 1.0 } else { 0.0 };
+        let step = TRANSITION_STEP * frames;
+        self.realism += (target - self.realism).clamp(-step, step);
+    }
+
+    pub fn exaggerate(&mut self, factor: f32) {
+        self.exaggeration = (self.exaggeration * factor).clamp(MIN_EXAGGERATION, MAX_EXAGGERATION);
+    }
+
+    // Mezcla suavizada para que la transición arranque y termine despacio
+    fn blend(&self) -> f32 {
+        self.realism * self.realism * (3.0 - 2.0 * self.realism)
+    }
+
+    pub fn orbit_radius(&self, planet: &Planet) -> f32 {
+        match planet.real {
+            Some(real) => {
+                let real_radius = real.orbit_au * SCENE_UNITS_PER_AU;
+                planet.semi_major_axis() + (real_radius - planet.semi_major_axis()) * self.blend()
+            }
+            None => planet.semi_major_axis(),
+        }
+    }
+
+    // Los tamaños abarcan varios órdenes de magnitud, así que se interpolan en escala logarítmica
+    pub fn scale(&self, planet: &Planet) -> f32 {
+        match planet.real {
+            Some(real) => {
+                let exaggeration = if planet.shader_mode == STAR_SHADER_MODE { 1.0 } else { self.exaggeration };
+                let real_scale = real.radius_km / KM_PER_AU * SCENE_UNITS_PER_AU / SPHERE_MODEL_RADIUS * exaggeration;
+                planet.scale.powf(1.0 - self.blend()) * real_scale.powf(self.blend())
+            }
+            None => planet.scale,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use serde::{Deserialize, Serialize};
+
+use crate::asteroids::Asteroid;
+use crate::camera::Camera;
+use crate::color::ToneMapping;
+use crate::comet::Comet;
+use crate::scale::SystemScale;
+use crate::{Planet, WarpDestination};
+
+// Versión del formato JSON de la escena; incrementarla si cambia su estructura
+pub const SCENE_VERSION: u32 = 3;
+
+// Estado completo de la simulación: suficiente para reproducir exactamente una vista
+#[derive(Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    pub planets: Vec<Planet>,
+    pub camera: Camera,
+    pub warp_destinations: Vec<WarpDestination>,
+    pub current_warp_index: usize,
+    pub time: f32,  // Tiempo simulado en frames; avanza según la escala de tiempo
+    pub shader_mode: u8,
+    #[serde(default)]
+    pub asteroids: Vec<Asteroid>,
+    #[serde(default)]
+    pub nbody: bool,
+    #[serde(default)]
+    pub nbody_initial_energy: f32,
+    #[serde(default)]
+    pub system_scale: SystemScale,
+    #[serde(default)]
+    pub comet: Option<Comet>,
+    #[serde(default = "default_time_scale")]
+    pub time_scale: f32,  // Frames simulados por frame real (versión 2)
+    #[serde(default)]
+    pub tone_mapping: ToneMapping,  // Versión 3
+    #[serde(default = "default_brightness")]
+    pub brightness: f32,  // Exposición con la que se sombrea antes del mapeo de tonos (versión 3)
+}
+
+fn default_time_scale() -> f32 {
+    1.0
+}
+
+fn default_brightness() -> f32 {
+    1.0
+}
+
+impl Scene {
+    pub fn new(planets: Vec<Planet>, warp_destinations: Vec<WarpDestination>, camera: Camera) -> Self {
+        Scene {
+            version: SCENE_VERSION,
+            planets,
+            camera,
+            warp_destinations,
+            current_warp_index: 0,
+            time: 0.0,
+            shader_mode: 0,
+            asteroids: Vec::new(),
+            nbody: false,
+            nbody_initial_energy: 0.0,
+            system_scale: SystemScale::default(),
+            comet: None,
+            time_scale: default_time_scale(),
+            tone_mapping: ToneMapping::default(),
+            brightness: default_brightness(),
+        }
+    }
+
+    pub fn save_json(&self, path: &str) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn load_json(path: &str) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let scene: Scene = serde_json::from_reader(reader)?;
+
+        if scene.version > SCENE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("versión de escena {} no soportada (máxima {})", scene.version, SCENE_VERSION),
+            ));
+        }
+        if scene.planets.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "la escena no tiene planetas"));
+        }
+
+        Ok(scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec3;
+
+    #[test]
+    fn empty_planet_list_is_invalid() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::zeros(), Vec3::y());
+        let path = std::env::temp_dir().join("space_travel_empty_scene.json");
+        let path = path.to_str().unwrap();
+        Scene::new(Vec::new(), Vec::new(), camera).save_json(path).unwrap();
+        let result = Scene::load_json(path);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(result.err().map(|error| error.kind()), Some(io::ErrorKind::InvalidData));
+    }
+}
@@ -1,65 +1,250 @@
 
-use nalgebra_glm::{Vec3, Vec4, Mat3, dot, mat4_to_mat3};
+use nalgebra_glm::{Vec3, Vec4, dot, mat4_to_mat3};
 use crate::vertex::Vertex;
 use crate::Uniforms;
 use crate::fragment::Fragment;
 use crate::color::Color;
+use crate::rings::RING_GAP;
+use crate::lights::blackbody_color;
+use crate::math::NEAR_PLANE;
 
+// Shader de vértices completo, sin recorte: las dos mitades seguidas. El pipeline usa las mitades
+// por separado para recortar en medio; esta versión solo la usan las pruebas con vértices sueltos.
+#[cfg(test)]
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
-    let position = Vec4::new(
-        vertex.position.x,
-        vertex.position.y,
-        vertex.position.z,
-        1.0
-    );
+  let (mut transformed, clip_position) = vertex_to_clip(vertex, uniforms);
+  project_vertex(&mut transformed, clip_position, uniforms);
+  transformed
+}
 
-    let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
+// Primera mitad del shader de vértices: transforma a mundo y a clip space, sin dividir por w.
+// Separarla permite recortar los triángulos contra el plano cercano antes de la división.
+pub fn vertex_to_clip(vertex: &Vertex, uniforms: &Uniforms) -> (Vertex, Vec4) {
+  let position = Vec4::new(
+    vertex.position.x,
+    vertex.position.y,
+    vertex.position.z,
+    1.0
+  );
 
-    let w = transformed.w;
-    let transformed_position = Vec4::new(
-        transformed.x / w,
-        transformed.y / w,
-        transformed.z / w,
-        1.0
-    );
+  let world_position = uniforms.model_matrix * position;
+  let clip_position = uniforms.projection_matrix * uniforms.view_matrix * world_position;
+
+  // La matriz de normales se calcula una vez por objeto, no por vértice (ver `Uniforms`)
+  let transformed_normal = uniforms.normal_matrix * vertex.normal;
+
+  let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
+
+  // La tangente es una dirección sobre la superficie: se transforma con la matriz del modelo
+  let tangent = model_mat3 * Vec3::new(vertex.tangent.x, vertex.tangent.y, vertex.tangent.z);
+  let transformed_tangent = Vec4::new(tangent.x, tangent.y, tangent.z, vertex.tangent.w);
+
+  let transformed = Vertex {
+    position: vertex.position,
+    normal: vertex.normal,
+    tex_coords: vertex.tex_coords,
+    tangent: vertex.tangent,
+    color: vertex.color,
+    transformed_position: Vec3::zeros(),
+    transformed_normal,
+    transformed_tangent,
+    world_position: Vec3::new(world_position.x, world_position.y, world_position.z),
+    clip_w: 1.0,
+    degenerate: false,
+  };
+
+  (transformed, clip_position)
+}
 
-    let screen_position = uniforms.viewport_matrix * transformed_position;
+// Tras recortar contra el plano cercano, w vale al menos `NEAR_PLANE` en perspectiva (y 1 en las
+// proyecciones ortográficas). Un |w| mucho menor solo puede venir de un vértice sobre el plano de
+// la cámara, que al dividir daría coordenadas enormes o NaN/inf.
+const MIN_CLIP_W: f32 = NEAR_PLANE * 1e-3;
 
-    let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
-    let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
+// w con el que dividir una posición en clip space, o None si es degenerada
+fn divisor_w(w: f32) -> Option<f32> {
+  (w.is_finite() && w.abs() >= MIN_CLIP_W).then_some(w)
+}
+
+// Segunda mitad: división de perspectiva y paso a coordenadas de pantalla
+pub fn project_vertex(vertex: &mut Vertex, clip_position: Vec4, uniforms: &Uniforms) {
+  let divisor = divisor_w(clip_position.w);
+  let degenerate = divisor.is_none();
+  let w = divisor.unwrap_or(1.0);
+  let ndc_position = Vec4::new(
+    clip_position.x / w,
+    clip_position.y / w,
+    clip_position.z / w,
+    1.0
+  );
 
-    let transformed_normal = normal_matrix * vertex.normal;
+  let screen_position = uniforms.viewport_matrix * ndc_position;
 
-    Vertex {
-        position: vertex.position,
-        normal: vertex.normal,
-        tex_coords: vertex.tex_coords,
-        color: vertex.color,
-        transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
-        transformed_normal: transformed_normal
-    }
+  vertex.transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+  vertex.clip_w = w;
+  vertex.degenerate = degenerate;
 }
 
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+// Intensidad de la luz de las estrellas secundarias respecto a su color (0 a 1)
+const COMPANION_LIGHT_STRENGTH: f32 = 0.45;
+
+// Intensidad del foco de la nave sobre los planetas (1 = blanco sin atenuar) y fracción interior
+// de su cono que recibe toda la luz; desde ahí hasta el borde se apaga suavemente
+const SPOTLIGHT_STRENGTH: f32 = 0.6;
+const SPOTLIGHT_INNER: f32 = 0.7;
+
+// Brillo del borde de la nave (Fresnel); el exponente lo concentra en los ángulos rasantes
+const RIM_STRENGTH: f32 = 0.6;
+const RIM_POWER: i32 = 4;
+
+// Anchura del paso del día a la noche, medida en intensidad difusa (0 = terminador)
+const EARTH_TERMINATOR_WIDTH: f32 = 0.3;
+// Brillo máximo de las luces de las ciudades (1 = el color de la luz sin atenuar)
+const CITY_LIGHT_STRENGTH: f32 = 0.8;
+
+// Emisión extra del centro de las estrellas (`BlendMode::Additive`): supera el rango de la
+// pantalla y el mapeo de tonos la convierte en un degradado desde el núcleo en lugar de un disco
+// recortado. El exponente la concentra en la parte que mira a la cámara.
+const STAR_CORE_EMISSION: f32 = 2.0;
+const STAR_CORE_POWER: i32 = 4;
+
+// Temperatura de las manchas respecto a la de la estrella, para su color oscuro; con un
+// `star_color` fijo, cuánto más saturadas que él son
+const STAR_SPOT_TEMPERATURE: f32 = 0.6;
+const STAR_SPOT_SATURATION: f32 = 1.3;
+
+// Giro de la paleta del planeta alienígena, en grados por unidad de `uniforms.time` (un frame a
+// 60 Hz): da la vuelta completa en un minuto
+const ALIEN_HUE_SPEED: f32 = 0.1;
+
+// Brillo propio de la lava y de la capa rosa del planeta alienígena, que se suma al color ya
+// sombreado de su superficie (`BlendMode::Additive`): no lo tapan las sombras, se ve también en
+// la cara de noche y puede pasar de 255
+const LAVA_EMISSION: f32 = 0.5;
+const ALIEN_EMISSION: f32 = 0.5;
+
+// Exponente del halo atmosférico: cuanto mayor, más fino es el anillo junto a la silueta
+const ATMOSPHERE_POWER: i32 = 3;
+
+// Cómo se escribe en el framebuffer el color de un fragmento que pasa la prueba de profundidad
+#[derive(Clone, Copy, Debug, Default)]
+pub enum BlendMode {
+  #[default]
+  Opaque,           // Sustituye el color y la profundidad
+  Alpha,            // Se mezcla con lo de detrás según `fragment_alpha`, sin escribir la profundidad
+  Additive(Color),  // Se escribe como un opaco y después se le suma esta emisión, saturando
+}
+
+pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, BlendMode) {
+  // Depuración: la normal en espacio de mundo como color, sin iluminación ni sombras
+  if uniforms.debug_normals {
+    return (normal_color(&fragment.normal), BlendMode::Opaque);
+  }
+
+  let (color, blend_mode) = shade(fragment, uniforms);
+
+  // Solo los planetas (modos 2 a 7) reciben luz y sombras de las estrellas
+  if !(2..=7).contains(&uniforms.shader_mode) {
+      return (color, blend_mode);
+  }
+
+  // La primera estrella es la luz que ya incluye cada shader de planeta: solo aporta su sombra.
+  // Si también hay sombras de eclipse manda la más oscura de las dos, que ven los mismos cuerpos.
+  let mut lights = uniforms.lights.iter();
+  let shadow_visibility = lights.next()
+      .and_then(|light| light.shadow_map.as_ref())
+      .map_or(1.0, |shadow_map| shadow_map.visibility(fragment.world_position));
+  let eclipse_visibility = uniforms.eclipse_shadow
+      .map_or(1.0, |eclipse| eclipse.visibility(fragment.world_position, uniforms.light_position));
+  let mut lit_color = color * shadow_visibility.min(eclipse_visibility);
+
+  // Los anillos del propio planeta también le dan sombra a la luz principal
+  lit_color = lit_color * (1.0 - ring_shadow(fragment, uniforms));
+
+  // Las demás estrellas suman una luz difusa de su color en la cara que las mira
+  let normal = fragment.normal.normalize();
+  for light in lights {
+      let to_light = (light.position - fragment.world_position).normalize();
+      let diffuse = dot(&normal, &to_light).max(0.0) * COMPANION_LIGHT_STRENGTH;
+      let lit_fraction = light.shadow_map.as_ref().map_or(1.0, |shadow_map| shadow_map.lit_fraction(fragment.world_position));
+      let amount = diffuse * lit_fraction * 255.0;
+      lit_color = lit_color + Color::from_f32(light.color.x * amount, light.color.y * amount, light.color.z * amount);
+  }
+
+  lit_color = lit_color + Color::from_f32(255.0, 245.0, 225.0) * spotlight(fragment, uniforms);
+
+  (lit_color, blend_mode)
+}
+
+// Luz del foco de la nave que llega a un fragmento (0 a `SPOTLIGHT_STRENGTH`): difusa en la cara
+// que lo mira y atenuada según el ángulo con el eje del cono. Sin atenuación por distancia, así que
+// el círculo se ve también sobre los planetas lejanos.
+fn spotlight(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  if uniforms.spotlight_cone <= 0.0 {
+    return 0.0;
+  }
+
+  let to_fragment = (fragment.world_position - uniforms.spotlight_position).normalize();
+  let cos_angle = dot(&to_fragment, &uniforms.spotlight_dir);
+  let (cos_outer, cos_inner) = (uniforms.spotlight_cone.cos(), (uniforms.spotlight_cone * SPOTLIGHT_INNER).cos());
+  if cos_angle <= cos_outer {
+    return 0.0;
+  }
+
+  let t = ((cos_angle - cos_outer) / (cos_inner - cos_outer)).min(1.0);
+  let falloff = t * t * (3.0 - 2.0 * t);
+  let diffuse = dot(&fragment.normal.normalize(), &-to_fragment).max(0.0);
+  diffuse * falloff * SPOTLIGHT_STRENGTH
+}
+
+// Mapea cada componente de la normal de [-1, 1] a [0, 255]
+fn normal_color(normal: &Vec3) -> Color {
+  let n = normal.normalize();
+  let channel = |value: f32| (value * 0.5 + 0.5) * 255.0;
+  Color::from_f32(channel(n.x), channel(n.y), channel(n.z)).clamp()
+}
+
+fn shade(fragment: &Fragment, uniforms: &Uniforms) -> (Color, BlendMode) {
+  let opaque = |color: Color| (color, BlendMode::Opaque);
   match uniforms.shader_mode {
-      1 => star_shader(fragment, uniforms),        // Sol
-      2 => broken_terrain_shader(fragment, uniforms), // Planeta rocoso
-      3 => gas_giant_shader(fragment, uniforms),  // Gigante gaseoso
-      4 => icy_planet_shader(fragment, uniforms), // Planeta helado
-      5 => volcanic_planet_shader(fragment, uniforms), // Planeta volcánico
-      6 => earth_like_planet_shader(fragment, uniforms), // Planeta Tierra
-      7 => alien_planet_shader(fragment, uniforms), // Planeta Alienigena
-      8 => spaceship_shader(fragment, uniforms), // Nave
-      _ => Color::new(0, 0, 0) // Shader por defecto (negro)
+      1 => star_shader(fragment, uniforms),                // Sol (el núcleo emite)
+      2 => opaque(broken_terrain_shader(fragment, uniforms)), // Planeta rocoso
+      3 => opaque(gas_giant_shader(fragment, uniforms)),  // Gigante gaseoso
+      4 => opaque(icy_planet_shader(fragment, uniforms)), // Planeta helado
+      5 => volcanic_planet_shader(fragment, uniforms), // Planeta volcánico (la lava brilla)
+      6 => opaque(earth_like_planet_shader(fragment, uniforms)), // Planeta Tierra
+      7 => alien_planet_shader(fragment, uniforms), // Planeta Alienigena (con emisión)
+      8 => opaque(spaceship_shader(fragment, uniforms)), // Nave
+      9 => opaque(asteroid_shader(fragment, uniforms)), // Asteroide
+      10 => opaque(textured_shader(fragment, uniforms)), // Nave con la textura de su .mtl
+      RING_SHADER_MODE => (ring_shader(fragment, uniforms), BlendMode::Alpha), // Anillos (translúcidos)
+      _ => opaque(fragment.color) // Shader por defecto: el gris iluminado del rasterizador
   }
 }
 
-fn star_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let bright_color = Color::new(255, 223, 0); // Bright yellow
-  let dark_color = Color::new(255, 69, 0); // Fiery orange-red
+// La superficie se sombrea como un opaco y el núcleo se le suma como emisión
+fn star_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, BlendMode) {
+  // Paleta del Sol, o una derivada de la temperatura o del color propio de la estrella
+  let to_color = |color: Vec3| Color::from_f32(color.x * 255.0, color.y * 255.0, color.z * 255.0);
+  let (bright_color, dark_color) = match uniforms.star_color {
+      // Las zonas oscuras son las más frías de la superficie: el mismo cuerpo negro, más rojo
+      _ if uniforms.star_temperature > 0.0 => (
+          to_color(blackbody_color(uniforms.star_temperature)),
+          to_color(blackbody_color(uniforms.star_temperature * STAR_SPOT_TEMPERATURE)),
+      ),
+      Some(star) => {
+          // Las manchas, más frías, tienen un color más oscuro y más intenso
+          let bright = to_color(star).clamp();
+          (bright, bright.saturate(STAR_SPOT_SATURATION) * 0.45)
+      }
+      None => (
+          Color::new(255, 223, 0), // Bright yellow
+          Color::new(255, 69, 0),  // Fiery orange-red
+      ),
+  };
 
   // Pulsating noise-based effect
-  let zoom = 300.0;
+  let zoom = 300.0 * uniforms.noise_zoom;
   let t = uniforms.time as f32 * 0.05;
   let noise_value = uniforms.noise.get_noise_3d(
       fragment.vertex_position.x * zoom,
@@ -70,7 +255,8 @@ fn star_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let intensity = (t * 2.0).sin() * 0.3 + 0.7;
   let color = dark_color.lerp(&bright_color, noise_value) * intensity;
 
-  color * fragment.intensity
+  let core = color * (STAR_CORE_EMISSION * fragment.intensity.powi(STAR_CORE_POWER));
+  (color * fragment.intensity, BlendMode::Additive(core))
 }
 
 pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -80,15 +266,10 @@ pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let color_layer_3 = Color::new(255,200, 26); // Amarillo claro
   let color_layer_4 = Color::new(255, 179, 34); // Naranja pálido
 
-  // Colores para los anillos
-  let ring_color = Color::new(238, 238, 214); // Color gris claro para los anillos
-
   // Parámetros de zoom para diferentes niveles de detalle
-  let zoom_planet = 10.0; // Zoom para las capas del planeta
-  let zoom_ring = 5.0;    // Zoom para los anillos
+  let zoom_planet = 10.0 * uniforms.noise_zoom; // Zoom para las capas del planeta
   let x = fragment.vertex_position.x;
   let y = fragment.vertex_position.y;
-  let t = uniforms.time as f32 * 0.5; // Movimiento en el tiempo
   let ox = 25.0;    
   let oy = 25.0;    
 
@@ -108,33 +289,71 @@ pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   // Añadir variación de ruido para el efecto de nubes
   let cloud_noise = uniforms.noise.get_noise_2d(x * zoom_planet + 100.0, y * zoom_planet + 100.0).abs() * 0.3;
   let cloud_color = layer_color.lerp(&Color::new(255, 255, 255), cloud_noise * 0.1); // Menos mezcla con blanco
-  let planet_color = cloud_color * (fragment.intensity * 3.0).clamp(0.0, 1.0);
-
-  // Añadir anillos con movimiento
-  let ring_distance = 0.05; // Posición del anillo
-  let ring_noise_value = uniforms.noise.get_noise_2d(
-      x * zoom_ring + 50.0 + t,
-      y * zoom_ring + 50.0 + t,
-  ).abs() * 0.5;
-
-  // Crear la apariencia de anillos
-  let ring_intensity = if (fragment.vertex_position.y - ring_distance).abs() < 0.05 {
-      ring_color.lerp(&Color::black(), ring_noise_value * 0.7)
-  } else {
-      Color::black() // No hay anillo aquí
-  };
 
-  // Mezcla el color del planeta con los anillos
-  let final_color = planet_color.blend(ring_intensity, 0.4);
+  // Los anillos son una malla aparte y translúcida (`ring_shader`)
+  cloud_color * (fragment.intensity * 3.0).clamp(0.0, 1.0)
+}
+
+// Modo de los anillos; es el único cuyo color lleva transparencia (`fragment_alpha`)
+pub const RING_SHADER_MODE: u8 = 11;
 
-  // Devuelve el color final del fragmento
-  final_color
+// Desplazamientos en la coordenada radial de los anillos con los que se suaviza su sombra, y
+// cuánto oscurece la sombra donde los anillos son opacos
+const RING_SHADOW_SAMPLES: [f32; 5] = [-0.04, -0.02, 0.0, 0.02, 0.04];
+const RING_SHADOW_STRENGTH: f32 = 1.0;
+
+// Bandas claras y oscuras según la coordenada radial (v de la textura: 0 en el borde interior y 1
+// en el exterior). El disco tiene normal +Y en coordenadas del objeto, así que se ilumina por las
+// dos caras.
+fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let ring_color = Color::new(238, 238, 214); // Gris claro
+  let dust_color = Color::new(150, 130, 100); // Polvo más oscuro
+
+  let band = uniforms.noise.get_noise_2d(fragment.tex_coords.y * 200.0, 0.0).abs();
+  let base_color = ring_color.lerp(&dust_color, band);
+
+  let light_dir = light_direction(fragment, uniforms);
+  let diffuse_intensity = dot(&fragment.normal.normalize(), &light_dir).abs();
+  base_color * (0.5 + 0.5 * diffuse_intensity)
+}
+
+// Opacidad del fragmento: 1 salvo en los anillos (`ring_opacity`)
+pub fn fragment_alpha(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  if uniforms.shader_mode != RING_SHADER_MODE {
+    return uniforms.opacity;
+  }
+  ring_opacity(fragment.tex_coords.y, uniforms) * uniforms.opacity
+}
+
+// Opacidad de los anillos en la coordenada radial `v` (0 en el borde interior, 1 en el exterior):
+// las bandas dejan huecos casi vacíos (como la división de Cassini) y el borde exterior se
+// desvanece. Fuera de los anillos es 0.
+fn ring_opacity(v: f32, uniforms: &Uniforms) -> f32 {
+  if !(0.0..=1.0).contains(&v) {
+    return 0.0;
+  }
+  let density = uniforms.noise.get_noise_2d(v * 75.0, 100.0) * 0.5 + 0.5;
+  let gap = smoothstep(0.0, 0.03, (v - RING_GAP).abs());
+  let fade = 1.0 - smoothstep(0.8, 1.0, v);
+  (0.35 + 0.5 * density) * gap * fade
+}
+
+// Fracción de la luz de la estrella principal que tapan los anillos del planeta en un punto del
+// planeta. Se promedia la opacidad en unas coordenadas alrededor del cruce con los anillos para
+// que el borde de la sombra sea suave.
+fn ring_shadow(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  let Some(crossing) = uniforms.ring_shadow.and_then(|rings| rings.crossing(fragment.world_position, uniforms.light_position)) else {
+    return 0.0;
+  };
+  let opacity: f32 = RING_SHADOW_SAMPLES.iter().map(|offset| ring_opacity(crossing + offset, uniforms)).sum();
+  opacity / RING_SHADOW_SAMPLES.len() as f32 * RING_SHADOW_STRENGTH
 }
 
 pub fn icy_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   // Generar ruido para la base y los detalles
-  let base_noise = uniforms.noise.get_noise_2d(fragment.vertex_position.x * 5.0, fragment.vertex_position.y * 5.0);
-  let detail_noise = uniforms.noise.get_noise_2d(fragment.vertex_position.x * 10.0, fragment.vertex_position.y * 10.0);
+  let zoom = 5.0 * uniforms.noise_zoom;
+  let base_noise = uniforms.noise.get_noise_2d(fragment.vertex_position.x * zoom, fragment.vertex_position.y * zoom);
+  let detail_noise = uniforms.noise.get_noise_2d(fragment.vertex_position.x * zoom * 2.0, fragment.vertex_position.y * zoom * 2.0);
   
   // Colores base y de resaltado
   let ice_color = Color::new(173, 216, 230); // Azul claro
@@ -149,7 +368,7 @@ pub fn icy_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let color_with_detail = base_color.lerp(&shadow_color, detail_variation);
   
   // Añadir iluminación especular
-  let light_dir = Vec3::new(1.0, 1.0, 0.5).normalize();
+  let light_dir = light_direction(fragment, uniforms);
   let normal = fragment.normal.normalize();
   let diffuse_intensity = dot(&normal, &light_dir).max(0.0);
   
@@ -164,10 +383,13 @@ pub fn icy_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let translucency = (base_noise * 0.5 + 0.5).clamp(0.0, 1.0); // Ajustar la translucidez
   let final_color = lit_color.lerp(&Color::new(255, 255, 255), translucency * 0.2); // Añadir un ligero brillo
 
-  final_color * fragment.intensity
+  // Halo atmosférico tenue: el planeta helado apenas tiene atmósfera
+  let view_dir = (uniforms.camera_position - fragment.world_position).normalize();
+  atmosphere_glow(&normal, &view_dir, final_color * fragment.intensity, 0.35)
 }
 
-fn volcanic_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+// Las manchas de lava suman su propio brillo a la roca, sin dejar ver lo que hay detrás
+fn volcanic_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, BlendMode) {
   // Colores base
   let bright_color = Color::new(255, 240, 0); // Color brillante (lava)
   let dark_color = Color::new(130, 20, 0);    // Color oscuro (rojo-naranja)
@@ -190,7 +412,7 @@ fn volcanic_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let pulsate = (t * base_frequency).sin() * pulsate_amplitude;
 
   // Aplicar ruido a las coordenadas con sutil pulsación en el eje z
-  let zoom = 1000.0; // Factor de zoom constante
+  let zoom = 1000.0 * uniforms.noise_zoom; // Factor de zoom ajustable desde el teclado
   let noise_value1 = uniforms.noise.get_noise_3d(
       position.x * zoom,
       position.y * zoom,
@@ -216,18 +438,16 @@ fn volcanic_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
   // Aumentar la intensidad de la lava en ciertas áreas
   let lava_threshold = 0.5; // Umbral para determinar si hay lava visible
-  let output_color = if lava_noise_value > lava_threshold {
-      final_color.lerp(&bright_color, 0.5) // Añadir un brillo de lava donde hay actividad volcánica
+  if lava_noise_value > lava_threshold {
+      (final_color, BlendMode::Additive(bright_color * LAVA_EMISSION)) // Añadir un brillo de lava donde hay actividad volcánica
   } else {
-      final_color
-  };
-
-  output_color
+      (final_color, BlendMode::Opaque)
+  }
 }
 
 
 fn earth_like_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let zoom = 170.0;
+  let zoom = 170.0 * uniforms.noise_zoom;
   let ox = 700.0;
   let oy = 600.0;
   let x = fragment.vertex_position.x;
@@ -256,7 +476,7 @@ fn earth_like_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   };
 
   // Iluminación del terreno
-  let light_dir = Vec3::new(1.0, 1.0, 0.5).normalize();
+  let light_dir = light_direction(fragment, uniforms);
   let normal = fragment.normal.normalize();
   let diffuse_intensity = dot(&normal, &light_dir).max(0.0);
   let lit_color = base_color * (0.4 + 0.6 * diffuse_intensity); // Luz ambiente + luz difusa
@@ -272,18 +492,89 @@ fn earth_like_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   // Obtener el valor de ruido modificado para las nubes
   let cloud_noise_value = uniforms.noise.get_noise_2d(oval_x, oval_y);
 
-  // Umbral ajustado para nubes más densas
-  let cloud_threshold = 0.45; // Umbral más bajo para mayor densidad de nubes
+  let coverage = weather_coverage(uniforms.cloud_coverage, uniforms.time as f32);
+  let cloud_alpha = cloud_alpha(coverage, cloud_noise_value);
   let cloud_color = Color::new(255, 255, 255); // Blanco para las nubes
 
-  // Superponer nubes si el ruido excede el umbral, con transparencia
-  let final_color = if cloud_noise_value > cloud_threshold {
-      lit_color.blend(cloud_color, 0.5) // Mezcla con el color base con 50% de opacidad
+  // Superponer las nubes con transparencia (hasta 50% de opacidad)
+  let clouded_color = lit_color.blend(cloud_color, 0.5 * cloud_alpha);
+
+  // Dispersión atmosférica: azul en el lado diurno, anaranjado cerca del terminador y oscuro de noche
+  let sun_angle = dot(&normal, &light_dir); // Sin recortar: negativo en el lado nocturno
+  let scattered_color = atmospheric_scattering(clouded_color, sun_angle);
+
+  // Lado nocturno: la luz ambiente no llega, así que se funde hacia un azul marino oscuro en una
+  // banda suave que empieza antes de que la luz difusa se anule
+  let night_color = Color::new(10, 14, 40);
+  let night_weight = 1.0 - smoothstep(0.0, EARTH_TERMINATOR_WIDTH, diffuse_intensity);
+  let final_color = scattered_color.lerp(&night_color, night_weight * 0.9);
+
+  // Luces de ciudades: puntos amarillos emisivos sobre tierra firme, solo de noche y atenuados
+  // por las nubes. Se suman tras la iluminación porque no dependen de ella.
+  let city_lights = if noise_value >= land_threshold {
+      let city_noise = uniforms.noise.get_noise_2d(x * zoom * 40.0 - ox, y * zoom * 40.0 - oy);
+      let glow = smoothstep(0.6, 0.8, city_noise) * night_weight * (1.0 - cloud_alpha * 0.6);
+      Color::new(255, 210, 110) * (glow * CITY_LIGHT_STRENGTH)
   } else {
-      lit_color
+      Color::black()
   };
 
-  final_color * fragment.intensity
+  let view_dir = (uniforms.camera_position - fragment.world_position).normalize();
+  atmosphere_glow(&normal, &view_dir, final_color * fragment.intensity + city_lights, 0.7)
+}
+
+// Dirección desde el fragmento hacia la estrella principal (`uniforms.light_position`)
+fn light_direction(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+  (uniforms.light_position - fragment.world_position).normalize()
+}
+
+// Halo atmosférico (Fresnel): aclara `color` hacia un azul celeste donde la normal se ve de
+// canto. Es máximo en la silueta y se anula en el centro del disco; `strength` de 0 a 1.
+fn atmosphere_glow(normal: &Vec3, view_dir: &Vec3, color: Color, strength: f32) -> Color {
+  let glow_color = Color::new(120, 180, 255);
+  let fresnel = (1.0 - dot(normal, view_dir).max(0.0)).powi(ATMOSPHERE_POWER);
+  color + glow_color * (fresnel * strength)
+}
+
+// Interpolación suave de Hermite entre dos bordes, como en GLSL
+// Cobertura de nubes del planeta con una variación lenta (el clima). Los extremos no varían:
+// 0 es siempre despejado y 1 siempre cubierto.
+fn weather_coverage(coverage: f32, time: f32) -> f32 {
+  let coverage = coverage.clamp(0.0, 1.0);
+  let weather = (time * 0.003).sin() * 0.6 * coverage * (1.0 - coverage);
+  (coverage + weather).clamp(0.0, 1.0)
+}
+
+// Opacidad de las nubes para un valor de ruido. El ruido va de -1 a 1: con cobertura 0 la banda
+// que suaviza el borde de las nubes queda entera por encima del máximo y con 1 por debajo del
+// mínimo, así que los extremos son exactamente despejado y cubierto.
+fn cloud_alpha(coverage: f32, noise: f32) -> f32 {
+  let threshold = 1.1 - 2.2 * coverage;
+  smoothstep(threshold - 0.05, threshold + 0.05, noise)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+  let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+  t * t * (3.0 - 2.0 * t)
+}
+
+// Tinte atmosférico en función del coseno entre la normal y la dirección de la luz
+fn atmospheric_scattering(color: Color, sun_angle: f32) -> Color {
+  let day_tint = Color::new(110, 170, 255);    // Cielo azul
+  let sunset_tint = Color::new(255, 150, 50);  // Dorado del atardecer
+  let night_tint = Color::new(5, 8, 25);       // Azul marino nocturno
+
+  // Banda dorada centrada en el terminador (sun_angle ≈ 0)
+  let sunset_weight = 1.0 - smoothstep(0.0, 0.35, sun_angle.abs());
+  // Lado diurno: ligero velo azul que crece con la altura del sol
+  let day_weight = smoothstep(0.0, 1.0, sun_angle) * 0.15;
+  // Lado nocturno: oscurece progresivamente tras cruzar el terminador
+  let night_weight = smoothstep(0.0, -0.4, sun_angle);
+
+  color
+      .lerp(&day_tint, day_weight)
+      .lerp(&sunset_tint, sunset_weight * 0.45)
+      .lerp(&night_tint, night_weight * 0.85)
 }
 
 
@@ -293,8 +584,8 @@ pub fn broken_terrain_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color
   let dirt_color = Color::new(100, 70, 40); // Color de la tierra
 
   // Parámetros de zoom
-  let zoom_2d = 10.0;
-  let crack_zoom = 30.0;
+  let zoom_2d = 10.0 * uniforms.noise_zoom;
+  let crack_zoom = 30.0 * uniforms.noise_zoom;
 
   // Generar ruido para el terreno
   let ox = 0.0; // Desplazamiento en x, puedes ajustarlo según sea necesario
@@ -321,41 +612,70 @@ pub fn broken_terrain_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color
   let crack_intensity = crack_noise_value.abs().clamp(0.0, 1.0);
   let final_color = terrain_color.lerp(&crack_color, crack_intensity);
 
-  // Aplicar intensidad del fragmento
-  final_color * fragment.intensity
+  // Relieve del mapa de normales iluminado por el Sol
+  let normal = perturbed_normal(fragment, uniforms);
+  let diffuse_intensity = dot(&normal, &light_direction(fragment, uniforms)).max(0.0);
+  final_color * (0.3 + 0.7 * diffuse_intensity)
 }
 
-fn alien_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let zoom = 50.0;
+// Normal del fragmento perturbada con `uniforms.normal_map` en espacio tangente (vecino más
+// cercano, repitiéndose fuera de 0 a 1). Sin mapa o sin tangente se usa la normal geométrica.
+fn perturbed_normal(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+  let normal = fragment.normal.normalize();
+  let Some(normal_map) = uniforms.normal_map.as_ref() else {
+    return normal;
+  };
+
+  // Gram-Schmidt: la tangente interpolada deja de ser perpendicular a la normal
+  let tangent = Vec3::new(fragment.tangent.x, fragment.tangent.y, fragment.tangent.z);
+  let tangent = tangent - normal * dot(&normal, &tangent);
+  if tangent.magnitude() <= f32::EPSILON {
+    return normal;
+  }
+  let tangent = tangent.normalize();
+  let bitangent = normal.cross(&tangent) * fragment.tangent.w.signum();
+
+  let (width, height) = normal_map.dimensions();
+  let x = ((fragment.tex_coords.x.rem_euclid(1.0) * width as f32) as u32).min(width - 1);
+  let y = ((fragment.tex_coords.y.rem_euclid(1.0) * height as f32) as u32).min(height - 1);
+  let [r, g, b] = normal_map.get_pixel(x, y).0.map(|channel| channel as f32 / 255.0 * 2.0 - 1.0);
+
+  (tangent * r + bitangent * g + normal * b).normalize()
+}
+
+// La capa más emisiva (rosa) suma su propio brillo a la superficie iluminada
+fn alien_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, BlendMode) {
+  let zoom = 50.0 * uniforms.noise_zoom;
   let x = fragment.vertex_position.x;
   let y = fragment.vertex_position.y;
 
   // Ruido para la superficie del planeta
   let noise_value = uniforms.noise.get_noise_2d(x * zoom, y * zoom);
 
-  // Colores vibrantes para las diferentes capas del planeta
-  let base_color = Color::new(75, 0, 130); // Púrpura oscuro
-  let secondary_color = Color::new(0, 255, 255); // Cian brillante
-  let tertiary_color = Color::new(243, 22, 206); 
+  // Colores vibrantes para las diferentes capas del planeta; la paleta gira de tono con el tiempo
+  let hue_shift = uniforms.time as f32 * ALIEN_HUE_SPEED;
+  let base_color = Color::new(75, 0, 130).shift_hue(hue_shift); // Púrpura oscuro
+  let secondary_color = Color::new(0, 255, 255).shift_hue(hue_shift); // Cian brillante
+  let tertiary_color = Color::new(243, 22, 206).shift_hue(hue_shift);
 
   // Selección del color base según el ruido, creando capas en el planeta
-  let planet_color = if noise_value < 0.3 {
-      base_color
+  let (planet_color, blend_mode) = if noise_value < 0.3 {
+      (base_color, BlendMode::Opaque)
   } else if noise_value < 0.6 {
-      secondary_color
+      (secondary_color, BlendMode::Opaque)
   } else {
-      tertiary_color
+      (tertiary_color, BlendMode::Additive(tertiary_color * ALIEN_EMISSION))
   };
 
   // Efecto de emisión ajustado
   let emission_strength = (0.5 + noise_value * 1.5).clamp(0.0, 1.0); // Intensidad de la emisión
-  let emission_color = Color::new(255, 20, 147) * emission_strength; // Color de emisión (rosa brillante)
+  let emission_color = Color::new(255, 20, 147).shift_hue(hue_shift) * emission_strength; // Color de emisión (rosa brillante)
 
   // Mezcla del color del planeta con el color de emisión
   let final_color = planet_color * 0.7 + emission_color * 0.3;
 
   // Configuración de la iluminación
-  let light_dir = Vec3::new(1.0, 1.0, 0.5).normalize();
+  let light_dir = light_direction(fragment, uniforms);
   let normal = fragment.normal.normalize();
   let diffuse_intensity = dot(&normal, &light_dir).max(0.0);
 
@@ -366,18 +686,17 @@ fn alien_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let lit_color = final_color * (ambient_intensity + diffuse_factor * diffuse_intensity);
 
   // Devolver el color final del fragmento
-  lit_color * fragment.intensity
+  (lit_color * fragment.intensity, blend_mode)
 }
 
 
 fn spaceship_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Colores base del material
     let base_color = Color::new(200, 200, 200); // Gris claro
-    let highlight_color = Color::new(255, 255, 255); // Blanco para los reflejos
     let shadow_color = Color::new(120, 120, 120); // Gris oscuro para las sombras
 
     // Generar ruido para agregar variaciones sutiles al material
-    let zoom = 15.0; // Zoom para el ruido
+    let zoom = 15.0 * uniforms.noise_zoom; // Zoom para el ruido
     let noise_value = uniforms.noise.get_noise_2d(
         fragment.vertex_position.x * zoom,
         fragment.vertex_position.y * zoom,
@@ -386,22 +705,113 @@ fn spaceship_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Agregar variación al color base
     let color_variation = base_color.lerp(&shadow_color, noise_value * 0.1);
 
+    shade_metal(color_variation, fragment, uniforms)
+}
+
+// Muestrea la textura difusa en las coordenadas de textura interpoladas (vecino más cercano,
+// repitiéndose fuera de 0 a 1) y la ilumina como el metal de la nave. Sin textura se usa el
+// material procedural.
+fn textured_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let Some(texture) = uniforms.texture.as_ref() else {
+        return spaceship_shader(fragment, uniforms);
+    };
+
+    let (width, height) = texture.dimensions();
+    let u = fragment.tex_coords.x.rem_euclid(1.0);
+    let v = fragment.tex_coords.y.rem_euclid(1.0);
+    let x = ((u * width as f32) as u32).min(width - 1);
+    let y = ((v * height as f32) as u32).min(height - 1);
+    let [r, g, b] = texture.get_pixel(x, y).0;
+
+    shade_metal(Color::new(r, g, b), fragment, uniforms)
+}
+
+// Iluminación difusa, especular y de borde de un material metálico con color `base_color`
+fn shade_metal(base_color: Color, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let highlight_color = Color::new(255, 255, 255); // Blanco para los reflejos
+
     // Iluminación del material
-    let light_dir = Vec3::new(1.0, 1.0, 0.5).normalize(); // Dirección de la luz
+    let light_dir = light_direction(fragment, uniforms); // Dirección hacia el Sol
     let normal = fragment.normal.normalize(); // Normal del fragmento
     let diffuse_intensity = dot(&normal, &light_dir).max(0.0); // Intensidad difusa
 
     // Brillo especular para simular un material metálico suave
-    let view_dir = Vec3::new(0.0, 0.0, 1.0); // Dirección de la cámara
+    let view_dir = (uniforms.camera_position - fragment.world_position).normalize(); // Dirección hacia la cámara
     let reflect_dir = 2.0 * dot(&normal, &light_dir) * normal - light_dir; // Reflexión de la luz
-    let specular_intensity = dot(&reflect_dir, &view_dir).max(0.0).powi(16); // Brillo especular
+    // Sin reflejo en las caras que no reciben luz del Sol
+    let specular_intensity = if diffuse_intensity > 0.0 { dot(&reflect_dir, &view_dir).max(0.0).powi(16) } else { 0.0 };
     let specular_color = highlight_color * specular_intensity;
 
+    // Borde iluminado: crece donde la superficie se ve de canto y recorta la silueta contra el fondo
+    let rim_intensity = (1.0 - dot(&normal, &view_dir).max(0.0)).powi(RIM_POWER) * RIM_STRENGTH;
+    let rim_color = Color::new(180, 210, 255) * rim_intensity;
+
     // Combinar iluminación difusa y especular con el color base
-    let lit_color = color_variation * (0.4 + 0.6 * diffuse_intensity) + specular_color;
+    let lit_color = base_color * (0.4 + 0.6 * diffuse_intensity) + specular_color;
+
+    // Ajustar la intensidad del color según la iluminación del fragmento; el borde se suma
+    // después para que también recorte las caras que quedan a oscuras
+    lit_color * fragment.intensity + rim_color
+}
+
+fn asteroid_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  // Roca gris con manchas oscuras
+  let rock_color = Color::new(120, 110, 100);
+  let dark_color = Color::new(60, 55, 50);
+
+  let zoom = 8.0 * uniforms.noise_zoom;
+  let noise_value = uniforms.noise.get_noise_3d(
+      fragment.vertex_position.x * zoom,
+      fragment.vertex_position.y * zoom,
+      fragment.vertex_position.z * zoom,
+  );
+  let base_color = rock_color.lerp(&dark_color, noise_value * 0.5 + 0.5);
+
+  let light_dir = light_direction(fragment, uniforms);
+  let normal = fragment.normal.normalize();
+  let diffuse_intensity = dot(&normal, &light_dir).max(0.0);
+
+  base_color * (0.3 + 0.7 * diffuse_intensity) * fragment.intensity
+}
 
-    // Ajustar la intensidad del color según la iluminación del fragmento
-    let final_color = lit_color * fragment.intensity;
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-    final_color
+  #[test]
+  fn near_zero_w_is_degenerate() {
+    // Por encima de f32::EPSILON pero muy lejos del plano cercano: antes pasaba y la división
+    // llevaba el vértice a millones de píxeles
+    for w in [0.0, -0.0, 1e-6, -1e-6, 1e-5, f32::NAN, f32::INFINITY] {
+      assert_eq!(divisor_w(w), None, "w = {w}");
+    }
+    for w in [NEAR_PLANE, -NEAR_PLANE, 1.0, 500.0] {
+      assert_eq!(divisor_w(w), Some(w));
+    }
+  }
+
+  #[test]
+  fn cloud_coverage_goes_from_clear_to_overcast() {
+    let noises: Vec<f32> = (0..=40).map(|i| -1.0 + i as f32 * 0.05).collect();
+    for &noise in &noises {
+      assert_eq!(cloud_alpha(0.0, noise), 0.0, "ruido {noise}");
+      assert_eq!(cloud_alpha(1.0, noise), 1.0, "ruido {noise}");
+
+      // Más cobertura nunca quita nubes
+      let mut previous = 0.0;
+      for step in 0..=20 {
+        let alpha = cloud_alpha(step as f32 / 20.0, noise);
+        assert!(alpha >= previous - 1e-6, "ruido {noise}, cobertura {}", step as f32 / 20.0);
+        previous = alpha;
+      }
+    }
+  }
+
+  #[test]
+  fn weather_leaves_clear_and_overcast_unchanged() {
+    for time in [0.0, 100.0, 523.6, 1000.0, 1e5] {
+      assert_eq!(weather_coverage(0.0, time), 0.0);
+      assert_eq!(weather_coverage(1.0, time), 1.0);
+    }
+  }
 }
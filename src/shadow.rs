@@ -0,0 +1,171 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4, look_at, perspective};
+use std::f32::consts::PI;
+
+use crate::framebuffer::Framebuffer;
+use crate::scene::Scene;
+use crate::triangle::triangle;
+use crate::vertex::Vertex;
+use crate::math::{create_tilted_model_matrix, create_viewport_matrix};
+use crate::{calculate_planet_transformations, planet_positions};
+
+pub const SHADOW_MAP_SIZE: usize = 256;
+const SHADOW_NEAR: f32 = 0.5;
+const SHADOW_FAR: f32 = 500.0;
+
+// Sesgo para evitar el "shadow acne": constante más una parte proporcional a la distancia
+const SHADOW_BIAS: f32 = 0.05;
+const SHADOW_BIAS_PER_UNIT: f32 = 0.02;
+
+// Fracción de luz que conserva un fragmento completamente en sombra
+const SHADOW_AMBIENT: f32 = 0.25;
+
+pub const STAR_SHADER_MODE: u8 = 1;
+
+// Una cara del cubo de sombras: una cámara de 90° desde la luz y su buffer de distancias
+struct ShadowFace {
+    light_view_proj: Mat4,
+    depth: Framebuffer,
+}
+
+// Mapa de sombras cúbico alrededor de una luz puntual (el Sol ilumina en todas direcciones).
+// Cada cara guarda en su zbuffer la distancia lineal a la luz de la superficie más cercana.
+pub struct ShadowMap {
+    light_position: Vec3,
+    viewport_matrix: Mat4,
+    faces: Vec<ShadowFace>,
+}
+
+// Ejes de las seis caras en el orden +X, -X, +Y, -Y, +Z, -Z, con su vector `up`
+fn face_axes() -> [(Vec3, Vec3); 6] {
+    [
+        (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+        (Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+        (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+        (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0)),
+    ]
+}
+
+// Cara del cubo hacia la que apunta una dirección (eje dominante)
+fn face_index(direction: &Vec3) -> usize {
+    let (x, y, z) = (direction.x.abs(), direction.y.abs(), direction.z.abs());
+    if x >= y && x >= z {
+        if direction.x >= 0.0 { 0 } else { 1 }
+    } else if y >= z {
+        if direction.y >= 0.0 { 2 } else { 3 }
+    } else if direction.z >= 0.0 {
+        4
+    } else {
+        5
+    }
+}
+
+// Transforma un vértice al espacio de la luz guardando como profundidad su distancia a ella
+fn light_space_vertex(vertex: &Vertex, model_matrix: &Mat4, light_view_proj: &Mat4, viewport_matrix: &Mat4, light_position: Vec3) -> Vertex {
+    let world = model_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+    let clip = light_view_proj * world;
+    let world_position = Vec3::new(world.x, world.y, world.z);
+
+    let mut transformed = vertex.clone();
+    transformed.world_position = world_position;
+
+    // Detrás del plano cercano de esta cara: lo cubre otra cara del cubo
+    if clip.w < SHADOW_NEAR {
+        transformed.degenerate = true;
+        return transformed;
+    }
+
+    let screen = viewport_matrix * Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    transformed.transformed_position = Vec3::new(screen.x, screen.y, (world_position - light_position).magnitude());
+    transformed
+}
+
+// Escribe en el zbuffer de `framebuffer` la distancia a la luz de todos los planetas visibles
+// desde `light_view_proj`. Las estrellas no proyectan sombra.
+pub fn render_depth_only(framebuffer: &mut Framebuffer, scene: &Scene, light_view_proj: &Mat4, light_position: Vec3, vertex_array: &[Vertex]) {
+    let viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+    let transformations = calculate_planet_transformations(&scene.planets, scene.time, &scene.system_scale);
+    let positions = planet_positions(scene);
+
+    for ((planet, (_, rotation, scale)), translation) in scene.planets.iter().zip(transformations).zip(positions) {
+        if planet.shader_mode == STAR_SHADER_MODE {
+            continue;
+        }
+
+        let model_matrix = create_tilted_model_matrix(translation, scale, planet.axial_tilt, rotation.y);
+        let transformed: Vec<Vertex> = vertex_array.iter()
+            .map(|vertex| light_space_vertex(vertex, &model_matrix, light_view_proj, &viewport_matrix, light_position))
+            .collect();
+
+        for tri in transformed.chunks_exact(3) {
+            for fragment in triangle(&tri[0], &tri[1], &tri[2]) {
+                if fragment.position.x >= 0.0 && fragment.position.y >= 0.0 {
+                    framebuffer.point(fragment.position.x as usize, fragment.position.y as usize, fragment.depth);
+                }
+            }
+        }
+    }
+}
+
+// Renderiza las seis caras del mapa de sombras alrededor de una estrella en `light_position`
+pub fn build_shadow_map(scene: &Scene, light_position: Vec3, vertex_array: &[Vertex]) -> ShadowMap {
+    let projection = perspective(1.0, PI / 2.0, SHADOW_NEAR, SHADOW_FAR);
+    let faces = face_axes().iter().map(|(axis, up)| {
+        let light_view_proj = projection * look_at(&light_position, &(light_position + axis), up);
+        let mut depth = Framebuffer::new(SHADOW_MAP_SIZE, SHADOW_MAP_SIZE);
+        render_depth_only(&mut depth, scene, &light_view_proj, light_position, vertex_array);
+        ShadowFace { light_view_proj, depth }
+    }).collect();
+
+    ShadowMap {
+        light_position,
+        viewport_matrix: create_viewport_matrix(SHADOW_MAP_SIZE as f32, SHADOW_MAP_SIZE as f32),
+        faces,
+    }
+}
+
+impl ShadowMap {
+    // Fracción de luz que recibe un punto: 1.0 iluminado, SHADOW_AMBIENT en sombra total
+    pub fn visibility(&self, world_position: Vec3) -> f32 {
+        SHADOW_AMBIENT + (1.0 - SHADOW_AMBIENT) * self.lit_fraction(world_position)
+    }
+
+    // Fracción de la vecindad de 3x3 texels (PCF) que ve la luz: 1.0 iluminado, 0.0 en sombra.
+    // Promediar la vecindad suaviza el borde de la sombra.
+    pub fn lit_fraction(&self, world_position: Vec3) -> f32 {
+        let to_point = world_position - self.light_position;
+        let distance = to_point.magnitude();
+        let face = &self.faces[face_index(&to_point)];
+
+        let clip = face.light_view_proj * Vec4::new(world_position.x, world_position.y, world_position.z, 1.0);
+        if clip.w <= 0.0 {
+            return 1.0;
+        }
+        let screen = self.viewport_matrix * Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+
+        let bias = SHADOW_BIAS + SHADOW_BIAS_PER_UNIT * distance;
+        let mut lit_samples = 0;
+        let mut samples = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let x = screen.x as i32 + dx;
+                let y = screen.y as i32 + dy;
+                if x < 0 || y < 0 || x >= SHADOW_MAP_SIZE as i32 || y >= SHADOW_MAP_SIZE as i32 {
+                    continue;
+                }
+
+                samples += 1;
+                let occluder_distance = face.depth.zbuffer[y as usize * SHADOW_MAP_SIZE + x as usize];
+                if distance - bias <= occluder_distance {
+                    lit_samples += 1;
+                }
+            }
+        }
+
+        if samples == 0 {
+            return 1.0;
+        }
+        lit_samples as f32 / samples as f32
+    }
+}
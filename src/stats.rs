@@ -0,0 +1,131 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::vertex::Vertex;
+
+// Tamaño en píxeles de cada tile de la rejilla de pantalla
+pub const TILE_SIZE: usize = 32;
+
+// Límite opcional de trabajo por frame. Al superarlo se dejan de dibujar los objetos que
+// quedan, que se ordenan antes de menor a mayor tamaño aparente en pantalla.
+#[derive(Clone, Copy, Default)]
+pub struct RenderBudget {
+    pub max_triangles: Option<usize>,
+    pub max_fragments: Option<usize>,
+}
+
+impl RenderBudget {
+    pub fn is_limited(&self) -> bool {
+        self.max_triangles.is_some() || self.max_fragments.is_some()
+    }
+}
+
+// Contadores del frame actual; se reinician con `reset` antes de renderizar
+pub struct RenderStats {
+    pub triangles: usize,
+    pub fragments: usize,
+    pub skipped_objects: usize,  // Objetos omitidos por superar el presupuesto
+    pub culled_objects: usize,   // Objetos descartados por quedar fuera del frustum
+    pub tiles_x: usize,
+    pub tiles_y: usize,
+    pub tile_triangles: Vec<u32>,  // Triángulos cuyo bounding box toca cada tile
+}
+
+impl RenderStats {
+    pub fn new(width: usize, height: usize) -> Self {
+        let tiles_x = width.div_ceil(TILE_SIZE);
+        let tiles_y = height.div_ceil(TILE_SIZE);
+        RenderStats {
+            triangles: 0,
+            fragments: 0,
+            skipped_objects: 0,
+            culled_objects: 0,
+            tiles_x,
+            tiles_y,
+            tile_triangles: vec![0; tiles_x * tiles_y],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.triangles = 0;
+        self.fragments = 0;
+        self.skipped_objects = 0;
+        self.culled_objects = 0;
+        for count in self.tile_triangles.iter_mut() {
+            *count = 0;
+        }
+    }
+
+    pub fn over_budget(&self, budget: &RenderBudget) -> bool {
+        budget.max_triangles.is_some_and(|max| self.triangles >= max)
+            || budget.max_fragments.is_some_and(|max| self.fragments >= max)
+    }
+
+    // Asigna un triángulo a todos los tiles que cubre su bounding box en pantalla
+    pub fn bin_triangle(&mut self, tri: &[Vertex]) {
+        if tri.iter().any(|vertex| vertex.degenerate) {
+            return;
+        }
+
+        let xs = tri.iter().map(|vertex| vertex.transformed_position.x);
+        let ys = tri.iter().map(|vertex| vertex.transformed_position.y);
+        let (min_x, max_x) = xs.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+        let (min_y, max_y) = ys.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), y| (lo.min(y), hi.max(y)));
+
+        let width = (self.tiles_x * TILE_SIZE) as f32;
+        let height = (self.tiles_y * TILE_SIZE) as f32;
+        if max_x < 0.0 || max_y < 0.0 || min_x >= width || min_y >= height {
+            return;
+        }
+
+        let first_x = (min_x.max(0.0) as usize / TILE_SIZE).min(self.tiles_x - 1);
+        let last_x = (max_x.min(width - 1.0) as usize / TILE_SIZE).min(self.tiles_x - 1);
+        let first_y = (min_y.max(0.0) as usize / TILE_SIZE).min(self.tiles_y - 1);
+        let last_y = (max_y.min(height - 1.0) as usize / TILE_SIZE).min(self.tiles_y - 1);
+
+        for tile_y in first_y..=last_y {
+            for tile_x in first_x..=last_x {
+                self.tile_triangles[tile_y * self.tiles_x + tile_x] += 1;
+            }
+        }
+    }
+}
+
+// Rampa de color de frío (pocos triángulos) a caliente (muchos)
+fn heat_color(t: f32) -> Color {
+    let stops = [
+        Color::new(0, 0, 255),
+        Color::new(0, 255, 255),
+        Color::new(0, 255, 0),
+        Color::new(255, 255, 0),
+        Color::new(255, 0, 0),
+    ];
+
+    let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let index = (scaled as usize).min(stops.len() - 2);
+    stops[index].lerp(&stops[index + 1], scaled - index as f32)
+}
+
+// Post-proceso de depuración: tiñe cada tile según su carga y dibuja los bordes de la rejilla
+pub fn draw_tile_heatmap(framebuffer: &mut Framebuffer, stats: &RenderStats) {
+    let max_count = stats.tile_triangles.iter().copied().max().unwrap_or(0).max(1);
+    let grid_color = Color::new(90, 90, 90).to_hex();
+
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let tile_x = (x / TILE_SIZE).min(stats.tiles_x - 1);
+            let tile_y = (y / TILE_SIZE).min(stats.tiles_y - 1);
+            let index = y * framebuffer.width + x;
+
+            if x % TILE_SIZE == 0 || y % TILE_SIZE == 0 {
+                framebuffer.buffer[index] = grid_color;
+                continue;
+            }
+
+            let count = stats.tile_triangles[tile_y * stats.tiles_x + tile_x];
+            if count > 0 {
+                let heat = heat_color(count as f32 / max_count as f32);
+                framebuffer.buffer[index] = Color::from_hex(framebuffer.buffer[index]).blend(heat, 0.35).to_hex();
+            }
+        }
+    }
+}
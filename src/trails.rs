@@ -0,0 +1,99 @@
+use nalgebra_glm::{Vec3, Vec4};
+use std::collections::VecDeque;
+
+use crate::framebuffer::Framebuffer;
+use crate::line::line;
+use crate::orbits::screen_segment;
+use crate::Uniforms;
+
+// Muestras de la estela por defecto; con `TRAIL_SAMPLE_INTERVAL` cubren un segundo simulado
+pub const DEFAULT_TRAIL_LENGTH: usize = 40;
+
+// Frames simulados entre dos muestras. Se mide en tiempo simulado y no en frames de pantalla para
+// que la estela cubra el mismo tramo de órbita con cualquier escala de tiempo.
+const TRAIL_SAMPLE_INTERVAL: f32 = 1.5;
+
+const TRAIL_COLOR: u32 = 0xA0C8FF;
+
+// Opacidad junto al planeta; se reduce linealmente hasta 0 en la muestra más antigua
+const TRAIL_MAX_ALPHA: f32 = 0.7;
+
+// La estela va sobre la línea de la órbita, que ya escribió su profundidad: se adelanta un poco
+// (en profundidad de pantalla) para pasar la prueba de profundidad encima de ella
+const TRAIL_DEPTH_BIAS: f32 = 5e-5;
+
+// Posiciones recientes de un planeta en el mundo, de la más nueva a la más antigua
+#[derive(Default)]
+pub struct Trail {
+    points: VecDeque<Vec3>,
+    last_sample: Option<f32>,  // Tiempo simulado al que corresponde la muestra más nueva
+}
+
+impl Trail {
+    // Añade las muestras que tocan desde la anterior hasta `time`, conservando como mucho
+    // `length`. Si en un frame pasan varios intervalos (escala de tiempo alta) los puntos
+    // intermedios se interpolan entre la última muestra y `position`.
+    pub fn record(&mut self, position: Vec3, time: f32, length: usize) {
+        let last = match self.last_sample {
+            // El tiempo ha retrocedido (se ha cargado otra escena): la estela anterior ya no vale
+            Some(last) if time >= last && length > 0 => last,
+            _ => {
+                self.points.clear();
+                if length > 0 {
+                    self.points.push_front(position);
+                    self.last_sample = Some(time);
+                } else {
+                    self.last_sample = None;
+                }
+                return;
+            }
+        };
+
+        let samples = ((time - last) / TRAIL_SAMPLE_INTERVAL) as usize;
+        if samples == 0 {
+            return;
+        }
+        let previous = self.points.front().copied().unwrap_or(position);
+        for sample in (samples.saturating_sub(length) + 1)..=samples {
+            self.points.push_front(previous.lerp(&position, sample as f32 / samples as f32));
+        }
+        self.points.truncate(length);
+        self.last_sample = Some(last + samples as f32 * TRAIL_SAMPLE_INTERVAL);
+    }
+}
+
+// Dibuja la estela como segmentos desde la posición actual `head` hasta la muestra más antigua,
+// cada vez más transparentes. Pasa la prueba de profundidad sin escribir en el z-buffer, así que
+// debe ir después de lo opaco.
+pub fn draw_trail(framebuffer: &mut Framebuffer, uniforms: &Uniforms, head: Vec3, trail: &Trail) {
+    if trail.points.is_empty() {
+        return;
+    }
+    let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+    let clip = |point: Vec3| view_projection * Vec4::new(point.x, point.y, point.z, 1.0);
+    let alpha = |index: usize| TRAIL_MAX_ALPHA * (1.0 - index as f32 / trail.points.len() as f32);
+
+    framebuffer.set_current_color(TRAIL_COLOR);
+    let mut start = head;
+    for (index, &end) in trail.points.iter().enumerate() {
+        if (end - start).magnitude() <= f32::EPSILON {
+            continue;
+        }
+        let (start_alpha, end_alpha) = (alpha(index), alpha(index + 1));
+        let segment = screen_segment(framebuffer, uniforms, clip(start), clip(end));
+        start = end;
+        let Some((a, b)) = segment else {
+            continue;
+        };
+
+        let fragments = line(&a, &b);
+        let steps = fragments.len().max(2) - 1;
+        for (step, fragment) in fragments.iter().enumerate() {
+            if fragment.position.x >= 0.0 && fragment.position.y >= 0.0 {
+                let t = step as f32 / steps as f32;
+                let alpha = start_alpha + (end_alpha - start_alpha) * t;
+                framebuffer.blend_point(fragment.position.x as usize, fragment.position.y as usize, fragment.depth - TRAIL_DEPTH_BIAS, alpha);
+            }
+        }
+    }
+}
@@ -5,6 +5,12 @@ use crate::color::Color;
 
 pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
   let mut fragments = Vec::new();
+
+  // Los vértices con w≈0 no tienen una posición en pantalla válida
+  if v1.degenerate || v2.degenerate || v3.degenerate {
+    return fragments;
+  }
+
   let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
 
   let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
@@ -13,15 +19,30 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
 
   let triangle_area = edge_function(&a, &b, &c);
 
+  // Los atributos son lineales en el espacio del mundo, no en pantalla: se interpolan divididos
+  // por la w de cada vértice y se vuelve a dividir por la 1/w interpolada
+  let (inv_w1, inv_w2, inv_w3) = (1.0 / v1.clip_w, 1.0 / v2.clip_w, 1.0 / v3.clip_w);
+  let perspective_correct = inv_w1 > 0.0 && inv_w2 > 0.0 && inv_w3 > 0.0;
+
   for y in min_y..=max_y {
     for x in min_x..=max_x {
       let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
 
       let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
 
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
+      if (0.0..=1.0).contains(&w1) &&
+         (0.0..=1.0).contains(&w2) &&
+         (0.0..=1.0).contains(&w3) {
+
+        // La profundidad (z/w) sí es lineal en pantalla y sigue usando los pesos sin corregir
+        let depth = a.z * w1 + b.z * w2 + c.z * w3;
+
+        let (w1, w2, w3) = if perspective_correct {
+          let inv_w = w1 * inv_w1 + w2 * inv_w2 + w3 * inv_w3;
+          (w1 * inv_w1 / inv_w, w2 * inv_w2 / inv_w, w3 * inv_w3 / inv_w)
+        } else {
+          (w1, w2, w3)
+        };
 
         let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
         let normal = normal.normalize();
@@ -31,12 +52,16 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
         let base_color = Color::new(100, 100, 100);
         let lit_color = base_color * intensity;
 
-        let depth = a.z * w1 + b.z * w2 + c.z * w3;
-
         let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
-
-        fragments.push(
-            Fragment::new(
+        let world_position = v1.world_position * w1 + v2.world_position * w2 + v3.world_position * w3;
+        let tex_coords = v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3;
+        let tangent = v1.transformed_tangent * w1 + v2.transformed_tangent * w2 + v3.transformed_tangent * w3;
+
+        fragments.push(Fragment {
+            world_position,
+            tex_coords,
+            tangent,
+            ..Fragment::new(
                 x as f32,
                 y as f32,
                 lit_color,
@@ -45,7 +70,7 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
                 intensity,
                 vertex_position,
             )
-        );
+        });
       }
     }
   }
@@ -53,6 +78,30 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
   fragments
 }
 
+// Un triángulo mira hacia atrás si, ya proyectado, sus vértices giran al revés que las caras
+// frontales de los modelos (antihorario en el OBJ; `edge_function` es positiva para ellas porque
+// el viewport invierte el eje Y). Los de área nula tampoco producen fragmentos y se descartan igual.
+pub fn is_back_facing(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> bool {
+  edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position) <= 0.0
+}
+
+// Sombreado plano: sustituye las normales de los vértices por la normal de la cara, calculada con
+// las posiciones en mundo. El sentido se elige para que coincida con el de las normales del modelo.
+pub fn flatten_normals(tri: &mut [Vertex; 3]) {
+  let edge_a = tri[1].world_position - tri[0].world_position;
+  let edge_b = tri[2].world_position - tri[0].world_position;
+  let face_normal = edge_a.cross(&edge_b);
+  if face_normal.magnitude() <= f32::EPSILON {
+    return;  // Triángulo degenerado: se conservan las normales interpoladas
+  }
+
+  let vertex_normals = tri[0].transformed_normal + tri[1].transformed_normal + tri[2].transformed_normal;
+  let face_normal = if dot(&face_normal, &vertex_normals) < 0.0 { -face_normal } else { face_normal }.normalize();
+  for vertex in tri.iter_mut() {
+    vertex.transformed_normal = face_normal;
+  }
+}
+
 fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
     let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -6,9 +6,19 @@ pub struct Vertex {
   pub position: Vec3,
   pub normal: Vec3,
   pub tex_coords: Vec2,
+  // Dirección en que crece la coordenada u; `w` (±1) da el sentido de la bitangente. Cero si el
+  // modelo no tiene coordenadas de textura
+  pub tangent: Vec4,
   pub color: Color,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  pub transformed_tangent: Vec4,
+  pub world_position: Vec3,
+  // w en clip space, para interpolar los atributos con corrección de perspectiva; 1 si el vértice
+  // no pasa por una proyección en perspectiva (la interpolación queda lineal en pantalla)
+  pub clip_w: f32,
+  // w≈0 en clip space: la división de perspectiva no es válida y el triángulo se descarta
+  pub degenerate: bool,
 }
 
 impl Vertex {
@@ -17,9 +27,14 @@ impl Vertex {
       position,
       normal,
       tex_coords,
+      tangent: Vec4::zeros(),
       color: Color::black(),
       transformed_position: position,
       transformed_normal: normal,
+      transformed_tangent: Vec4::zeros(),
+      world_position: position,
+      clip_w: 1.0,
+      degenerate: false,
     }
   }
 
@@ -28,9 +43,14 @@ impl Vertex {
       position,
       normal: Vec3::new(0.0, 0.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
+      tangent: Vec4::zeros(),
       color,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+      transformed_tangent: Vec4::zeros(),
+      world_position: position,
+      clip_w: 1.0,
+      degenerate: false,
     }
   }
 
@@ -46,9 +66,14 @@ impl Default for Vertex {
       position: Vec3::new(0.0, 0.0, 0.0),
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
+      tangent: Vec4::zeros(),
       color: Color::black(),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      transformed_tangent: Vec4::zeros(),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
+      clip_w: 1.0,
+      degenerate: false,
     }
   }
 }
@@ -0,0 +1,51 @@
+use nalgebra_glm::Vec3;
+
+// Transición animada de la cámara hacia un destino de warp
+pub struct WarpAnimator {
+    start_eye: Vec3,
+    start_center: Vec3,
+    end_eye: Vec3,
+    end_center: Vec3,
+    duration: f32,  // Segundos
+    elapsed: f32,
+}
+
+impl WarpAnimator {
+    // Empieza en la pose actual de la cámara. Para cambiar de destino a mitad de camino basta
+    // con crear otro animador desde la pose interpolada, sin volver al punto de partida.
+    pub fn new(start_eye: Vec3, start_center: Vec3, end_eye: Vec3, end_center: Vec3, duration: f32) -> Self {
+        WarpAnimator {
+            start_eye,
+            start_center,
+            end_eye,
+            end_center,
+            duration,
+            elapsed: 0.0,
+        }
+    }
+
+    // Avanza el reloj de la animación y devuelve la pose `(eye, center)` correspondiente
+    pub fn step(&mut self, delta_time: f32) -> (Vec3, Vec3) {
+        self.elapsed = (self.elapsed + delta_time).min(self.duration);
+        let progress = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+        let t = ease_in_out(progress);
+
+        (
+            self.start_eye + (self.end_eye - self.start_eye) * t,
+            self.start_center + (self.end_center - self.start_center) * t,
+        )
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+// Curva cúbica que acelera al salir y frena al llegar (t de 0 a 1)
+fn ease_in_out(t: f32) -> f32 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}